@@ -0,0 +1,213 @@
+use crate::assertions::resources::format_bytes;
+use crate::assertions::timing::{evaluate_comparison, operator_str};
+use crate::assertions::AssertionResult;
+use crate::parser::ast::{
+    BytesPredicate, LengthPredicate, OutputSelector, StringComparisonOperator,
+};
+use crate::runner::shell::{RunResult, Truncation};
+
+/// Longest prefix shown when a byte assertion reports the actual output
+const MAX_SHOWN_BYTES: usize = 32;
+
+/// The exact bytes a RUN wrote. `stdout.raw` and `stdout` share the same
+/// bytes since ANSI stripping only applies to the decoded text.
+pub fn get_output_bytes<'a>(result: &'a RunResult, selector: &OutputSelector) -> &'a [u8] {
+    match selector {
+        OutputSelector::Stdout | OutputSelector::StdoutRaw => &result.stdout_bytes,
+        OutputSelector::Stderr => &result.stderr_bytes,
+    }
+}
+
+pub fn get_output_truncation<'a>(
+    result: &'a RunResult,
+    selector: &OutputSelector,
+) -> Option<&'a Truncation> {
+    match selector {
+        OutputSelector::Stdout | OutputSelector::StdoutRaw => result.stdout_truncation.as_ref(),
+        OutputSelector::Stderr => result.stderr_truncation.as_ref(),
+    }
+}
+
+/// Number of bytes a RUN wrote, including any past the `max_output` limit.
+pub fn output_length(result: &RunResult, selector: &OutputSelector) -> usize {
+    match get_output_truncation(result, selector) {
+        Some(truncation) => truncation.total_bytes as usize,
+        None => get_output_bytes(result, selector).len(),
+    }
+}
+
+/// Fails an assertion on output that was cut off at the `max_output` limit,
+/// which would otherwise only check part of what the command wrote. Reporters
+/// point at `truncation.path` for the full output.
+pub fn truncated_output(selector: &OutputSelector, truncation: &Truncation) -> AssertionResult {
+    let limit = format_bytes(truncation.limit_bytes);
+    AssertionResult::with_error(
+        false,
+        format!("{} within the {} max_output limit", selector_name(selector), limit),
+        format!("{} written", format_bytes(truncation.total_bytes)),
+        format!(
+            "Output was truncated at the max_output limit. Add PARTIAL to assert on the first {} only",
+            limit
+        ),
+    )
+}
+
+pub fn selector_name(selector: &OutputSelector) -> &'static str {
+    match selector {
+        OutputSelector::Stdout | OutputSelector::StdoutRaw => "stdout",
+        OutputSelector::Stderr => "stderr",
+    }
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Formats bytes as a hex literal, truncating long output.
+fn format_hex_literal(bytes: &[u8]) -> String {
+    if bytes.len() > MAX_SHOWN_BYTES {
+        format!(
+            "hex\"{}\"… ({} bytes)",
+            encode_hex(&bytes[..MAX_SHOWN_BYTES]),
+            bytes.len()
+        )
+    } else {
+        format!("hex\"{}\"", encode_hex(bytes))
+    }
+}
+
+pub fn evaluate_bytes_predicate(bytes: &[u8], predicate: &BytesPredicate) -> AssertionResult {
+    let (passed, expected) = match predicate {
+        BytesPredicate::Equals { operator, value } => {
+            let is_equal = bytes == value.bytes.as_slice();
+            match operator {
+                StringComparisonOperator::Equal => (is_equal, format!("== {}", value.raw)),
+                StringComparisonOperator::NotEqual => (!is_equal, format!("!= {}", value.raw)),
+            }
+        }
+        BytesPredicate::Contains { value } => (
+            bytes
+                .windows(value.bytes.len())
+                .any(|window| window == value.bytes.as_slice()),
+            format!("to contain {}", value.raw),
+        ),
+        BytesPredicate::StartsWith { value } => (
+            bytes.starts_with(&value.bytes),
+            format!("to start with {}", value.raw),
+        ),
+    };
+
+    AssertionResult::new(passed, expected, format_hex_literal(bytes))
+}
+
+pub fn evaluate_length_predicate(
+    selector: &OutputSelector,
+    length: usize,
+    predicate: &LengthPredicate,
+) -> AssertionResult {
+    let passed = evaluate_comparison(length as f64, &predicate.operator, predicate.value as f64);
+
+    AssertionResult::new(
+        passed,
+        format!(
+            "{}.length {} {}",
+            selector_name(selector),
+            operator_str(&predicate.operator),
+            predicate.value
+        ),
+        format!("{} bytes", length),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::{ComparisonOperator, HexLiteral};
+
+    fn hex(bytes: &[u8]) -> HexLiteral {
+        HexLiteral {
+            bytes: bytes.to_vec(),
+            raw: format!("hex\"{}\"", encode_hex(bytes)),
+        }
+    }
+
+    const GZIP_HEADER: &[u8] = &[0x1f, 0x8b, 0x08, 0x00, 0xff];
+
+    #[test]
+    fn test_bytes_equals() {
+        let predicate = BytesPredicate::Equals {
+            operator: StringComparisonOperator::Equal,
+            value: hex(GZIP_HEADER),
+        };
+        assert!(evaluate_bytes_predicate(GZIP_HEADER, &predicate).passed);
+
+        let result = evaluate_bytes_predicate(&GZIP_HEADER[..3], &predicate);
+        assert!(!result.passed);
+        assert_eq!(result.actual, "hex\"1f8b08\"");
+    }
+
+    #[test]
+    fn test_bytes_not_equals() {
+        let predicate = BytesPredicate::Equals {
+            operator: StringComparisonOperator::NotEqual,
+            value: hex(&[0x00]),
+        };
+        assert!(evaluate_bytes_predicate(GZIP_HEADER, &predicate).passed);
+    }
+
+    #[test]
+    fn test_bytes_contains_and_starts_with() {
+        let contains = BytesPredicate::Contains {
+            value: hex(&[0x08, 0x00]),
+        };
+        assert!(evaluate_bytes_predicate(GZIP_HEADER, &contains).passed);
+
+        let starts_with = BytesPredicate::StartsWith {
+            value: hex(&[0x1f, 0x8b]),
+        };
+        assert!(evaluate_bytes_predicate(GZIP_HEADER, &starts_with).passed);
+        assert!(!evaluate_bytes_predicate(&[0x8b], &starts_with).passed);
+    }
+
+    #[test]
+    fn test_long_output_is_truncated() {
+        let predicate = BytesPredicate::Contains {
+            value: hex(&[0x01]),
+        };
+        let result = evaluate_bytes_predicate(&[0u8; 100], &predicate);
+        assert!(result.actual.ends_with("… (100 bytes)"));
+    }
+
+    #[test]
+    fn test_length_predicate() {
+        let predicate = LengthPredicate {
+            operator: ComparisonOperator::Equal,
+            value: 1024,
+        };
+        let result = evaluate_length_predicate(&OutputSelector::Stdout, 1024, &predicate);
+        assert!(result.passed);
+        assert_eq!(result.expected, "stdout.length == 1024");
+
+        let predicate = LengthPredicate {
+            operator: ComparisonOperator::LessThan,
+            value: 10,
+        };
+        let result = evaluate_length_predicate(&OutputSelector::Stderr, 12, &predicate);
+        assert!(!result.passed);
+        assert_eq!(result.actual, "12 bytes");
+    }
+
+    #[test]
+    fn test_truncated_output_names_limit_and_full_output() {
+        let truncation = Truncation {
+            limit_bytes: 1024,
+            total_bytes: 5000,
+            path: ".hone/runs/x/run-stdout.txt".to_string(),
+        };
+        let result = truncated_output(&OutputSelector::Stdout, &truncation);
+        assert!(!result.passed);
+        assert_eq!(result.expected, "stdout within the 1KB max_output limit");
+        assert_eq!(result.actual, "5KB written");
+        assert!(result.error.unwrap().contains("Add PARTIAL"));
+    }
+}
@@ -1,5 +1,7 @@
+use crate::assertions::bytes::encode_hex;
 use crate::assertions::AssertionResult;
 use crate::parser::ast::{FilePredicate, RegexLiteral, StringComparisonOperator, StringLiteral};
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 
 struct FileExistsResult {
@@ -26,6 +28,9 @@ pub async fn evaluate_file_predicate(
         FilePredicate::Equals { operator, value } => {
             evaluate_file_equals(&resolved_path, operator, value, &file_path.raw, cwd).await
         }
+        FilePredicate::Sha256 { operator, value } => {
+            evaluate_file_sha256(&resolved_path, operator, value, &file_path.raw).await
+        }
     }
 }
 
@@ -238,6 +243,39 @@ async fn evaluate_file_equals(
     )
 }
 
+async fn evaluate_file_sha256(
+    file_path: &Path,
+    operator: &StringComparisonOperator,
+    value: &StringLiteral,
+    path_raw: &str,
+) -> AssertionResult {
+    let op_str = match operator {
+        StringComparisonOperator::Equal => "==",
+        StringComparisonOperator::NotEqual => "!=",
+    };
+    let expected = format!("file {} sha256 {} {}", path_raw, op_str, value.raw);
+
+    // Digests are taken over the raw bytes, so binary files are supported
+    let content = match tokio::fs::read(file_path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return AssertionResult::new(false, expected, "file does not exist".to_string())
+        }
+        Err(e) => {
+            return AssertionResult::new(false, expected, format!("failed to read file: {}", e))
+        }
+    };
+
+    let digest = encode_hex(&Sha256::digest(&content));
+    let is_equal = digest.eq_ignore_ascii_case(&value.value);
+    let passed = match operator {
+        StringComparisonOperator::Equal => is_equal,
+        StringComparisonOperator::NotEqual => !is_equal,
+    };
+
+    AssertionResult::new(passed, expected, digest)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -530,4 +568,35 @@ mod tests {
             result.actual
         );
     }
+
+    #[tokio::test]
+    async fn test_evaluate_file_sha256() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        tokio::fs::write(temp_dir.path().join("data.bin"), &[0xFF, 0x00, 0x1F])
+            .await
+            .unwrap();
+        tokio::fs::write(temp_dir.path().join("hello.txt"), "hello")
+            .await
+            .unwrap();
+        let cwd = temp_dir.path().to_str().unwrap();
+
+        let hello_digest = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824";
+        let predicate = FilePredicate::Sha256 {
+            operator: StringComparisonOperator::Equal,
+            value: make_string_literal(hello_digest),
+        };
+        let result =
+            evaluate_file_predicate(&make_string_literal("hello.txt"), &predicate, cwd).await;
+        assert!(result.passed, "{}", result.actual);
+
+        let result =
+            evaluate_file_predicate(&make_string_literal("data.bin"), &predicate, cwd).await;
+        assert!(!result.passed);
+        assert_eq!(result.actual.len(), 64);
+
+        let result =
+            evaluate_file_predicate(&make_string_literal("missing"), &predicate, cwd).await;
+        assert!(!result.passed);
+        assert_eq!(result.actual, "file does not exist");
+    }
 }
@@ -0,0 +1,138 @@
+use crate::assertions::output::evaluate_output_predicate;
+use crate::assertions::timing::{evaluate_comparison, operator_str};
+use crate::assertions::AssertionResult;
+use crate::parser::ast::{HttpPredicate, HttpRequestSpec};
+use crate::runner::http::HttpRequest;
+
+pub fn evaluate_http_predicate(
+    spec: &HttpRequestSpec,
+    requests: &[HttpRequest],
+    predicate: &HttpPredicate,
+) -> AssertionResult {
+    let matching: Vec<&HttpRequest> = requests
+        .iter()
+        .filter(|request| request.matches(spec))
+        .collect();
+
+    match predicate {
+        HttpPredicate::Received => AssertionResult::new(
+            !matching.is_empty(),
+            format!("http request \"{}\" received", spec.raw),
+            describe_requests(requests),
+        ),
+        HttpPredicate::Count { operator, value } => AssertionResult::new(
+            evaluate_comparison(matching.len() as f64, operator, *value as f64),
+            format!(
+                "http request \"{}\" count {} {}",
+                spec.raw,
+                operator_str(operator),
+                value
+            ),
+            matching.len().to_string(),
+        ),
+        HttpPredicate::Body(predicate) => match matching.last() {
+            Some(request) => {
+                evaluate_output_predicate(&String::from_utf8_lossy(&request.body), predicate)
+            }
+            None => AssertionResult::new(
+                false,
+                format!("http request \"{}\" received", spec.raw),
+                describe_requests(requests),
+            ),
+        },
+    }
+}
+
+/// Each request as `<METHOD> <target>`, one per line.
+fn describe_requests(requests: &[HttpRequest]) -> String {
+    if requests.is_empty() {
+        return "no requests".to_string();
+    }
+    requests
+        .iter()
+        .map(|request| format!("{} {}", request.method, request.target))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::{ComparisonOperator, OutputPredicate, QuoteType, StringLiteral};
+
+    fn request(method: &str, target: &str, body: &str) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            target: target.to_string(),
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn requests() -> Vec<HttpRequest> {
+        vec![
+            request("GET", "/v1/items?page=1", ""),
+            request("POST", "/v1/items", "{\"name\": \"a\"}"),
+            request("POST", "/v1/items", "{\"name\": \"b\"}"),
+        ]
+    }
+
+    fn spec(raw: &str) -> HttpRequestSpec {
+        let (method, path) = raw.split_once(' ').unwrap();
+        HttpRequestSpec {
+            method: method.to_string(),
+            path: path.to_string(),
+            raw: raw.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_received() {
+        let predicate = HttpPredicate::Received;
+        assert!(evaluate_http_predicate(&spec("GET /v1/items"), &requests(), &predicate).passed);
+
+        let result = evaluate_http_predicate(&spec("DELETE /v1/items"), &requests(), &predicate);
+        assert!(!result.passed);
+        assert_eq!(
+            result.expected,
+            "http request \"DELETE /v1/items\" received"
+        );
+        assert_eq!(
+            result.actual,
+            "GET /v1/items?page=1\nPOST /v1/items\nPOST /v1/items"
+        );
+
+        let result = evaluate_http_predicate(&spec("GET /v1/items"), &[], &predicate);
+        assert_eq!(result.actual, "no requests");
+    }
+
+    #[test]
+    fn test_count() {
+        let predicate = HttpPredicate::Count {
+            operator: ComparisonOperator::Equal,
+            value: 2,
+        };
+        assert!(evaluate_http_predicate(&spec("POST /v1/items"), &requests(), &predicate).passed);
+
+        let result = evaluate_http_predicate(&spec("GET /v1/items"), &requests(), &predicate);
+        assert!(!result.passed);
+        assert_eq!(result.expected, "http request \"GET /v1/items\" count == 2");
+        assert_eq!(result.actual, "1");
+    }
+
+    #[test]
+    fn test_body_of_last_matching_request() {
+        let predicate = HttpPredicate::Body(OutputPredicate::Contains {
+            value: StringLiteral {
+                value: "\"b\"".to_string(),
+                raw: "'\"b\"'".to_string(),
+                quote_type: QuoteType::Single,
+            },
+        });
+        assert!(evaluate_http_predicate(&spec("POST /v1/items"), &requests(), &predicate).passed);
+        assert!(
+            !evaluate_http_predicate(&spec("POST /v1/items"), &requests()[..2], &predicate).passed
+        );
+        assert!(!evaluate_http_predicate(&spec("PUT /v1/items"), &requests(), &predicate).passed);
+    }
+}
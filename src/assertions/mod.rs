@@ -1,6 +1,10 @@
+pub mod bytes;
 pub mod exitcode;
 pub mod filesystem;
+pub mod http;
 pub mod output;
+pub mod resources;
+pub mod stub;
 pub mod timing;
 
 #[derive(Debug, Clone)]
@@ -282,9 +282,15 @@ mod tests {
             stdout: "stdout_data".to_string(),
             stdout_raw: "raw_data".to_string(),
             stderr: "stderr_data".to_string(),
+            stdout_bytes: Vec::new(),
+            stderr_bytes: Vec::new(),
             exit_code: 0,
             duration_ms: 100,
             stderr_path: "/tmp/stderr".to_string(),
+            resources: None,
+            bench: None,
+            stdout_truncation: None,
+            stderr_truncation: None,
         };
         assert_eq!(
             get_output_value(&run_result, &OutputSelector::Stdout),
@@ -299,9 +305,15 @@ mod tests {
             stdout: "stdout_data".to_string(),
             stdout_raw: "raw_data".to_string(),
             stderr: "stderr_data".to_string(),
+            stdout_bytes: Vec::new(),
+            stderr_bytes: Vec::new(),
             exit_code: 0,
             duration_ms: 100,
             stderr_path: "/tmp/stderr".to_string(),
+            resources: None,
+            bench: None,
+            stdout_truncation: None,
+            stderr_truncation: None,
         };
         assert_eq!(
             get_output_value(&run_result, &OutputSelector::StdoutRaw),
@@ -316,9 +328,15 @@ mod tests {
             stdout: "stdout_data".to_string(),
             stdout_raw: "raw_data".to_string(),
             stderr: "stderr_data".to_string(),
+            stdout_bytes: Vec::new(),
+            stderr_bytes: Vec::new(),
             exit_code: 0,
             duration_ms: 100,
             stderr_path: "/tmp/stderr".to_string(),
+            resources: None,
+            bench: None,
+            stdout_truncation: None,
+            stderr_truncation: None,
         };
         assert_eq!(
             get_output_value(&run_result, &OutputSelector::Stderr),
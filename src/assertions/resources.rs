@@ -0,0 +1,103 @@
+use crate::assertions::timing::{evaluate_comparison, operator_str};
+use crate::assertions::AssertionResult;
+use crate::parser::ast::{MemoryPredicate, MemorySize, MemoryUnit};
+
+pub fn memory_to_bytes(size: &MemorySize) -> f64 {
+    let multiplier = match size.unit {
+        MemoryUnit::Bytes => 1.0,
+        MemoryUnit::Kilobytes => 1024.0,
+        MemoryUnit::Megabytes => 1024.0 * 1024.0,
+        MemoryUnit::Gigabytes => 1024.0 * 1024.0 * 1024.0,
+    };
+    size.value * multiplier
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+
+    let value = bytes as f64;
+    if value >= GB {
+        format!("{:.2}GB", value / GB)
+    } else if value >= MB {
+        format!("{:.1}MB", value / MB)
+    } else if value >= KB {
+        format!("{:.0}KB", value / KB)
+    } else {
+        format!("{}B", bytes)
+    }
+}
+
+pub fn evaluate_memory_predicate(
+    max_rss_bytes: u64,
+    predicate: &MemoryPredicate,
+) -> AssertionResult {
+    let expected_bytes = memory_to_bytes(&predicate.value);
+    let passed = evaluate_comparison(max_rss_bytes as f64, &predicate.operator, expected_bytes);
+
+    AssertionResult::new(
+        passed,
+        format!(
+            "max_rss {} {}",
+            operator_str(&predicate.operator),
+            predicate.value.raw
+        ),
+        format_bytes(max_rss_bytes),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::ComparisonOperator;
+
+    fn make_predicate(op: ComparisonOperator, value: f64, unit: MemoryUnit) -> MemoryPredicate {
+        let suffix = match unit {
+            MemoryUnit::Bytes => "B",
+            MemoryUnit::Kilobytes => "KB",
+            MemoryUnit::Megabytes => "MB",
+            MemoryUnit::Gigabytes => "GB",
+        };
+        MemoryPredicate {
+            operator: op,
+            value: MemorySize {
+                value,
+                unit,
+                raw: format!("{}{}", value, suffix),
+            },
+        }
+    }
+
+    #[test]
+    fn test_memory_to_bytes() {
+        let predicate = make_predicate(ComparisonOperator::LessThan, 1.5, MemoryUnit::Kilobytes);
+        assert_eq!(memory_to_bytes(&predicate.value), 1536.0);
+        let predicate = make_predicate(ComparisonOperator::LessThan, 2.0, MemoryUnit::Megabytes);
+        assert_eq!(memory_to_bytes(&predicate.value), 2097152.0);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(4096), "4KB");
+        assert_eq!(format_bytes(52_428_800), "50.0MB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.00GB");
+    }
+
+    #[test]
+    fn test_memory_predicate_passes_under_limit() {
+        let predicate = make_predicate(ComparisonOperator::LessThan, 50.0, MemoryUnit::Megabytes);
+        let result = evaluate_memory_predicate(10 * 1024 * 1024, &predicate);
+        assert!(result.passed);
+        assert_eq!(result.expected, "max_rss < 50MB");
+    }
+
+    #[test]
+    fn test_memory_predicate_fails_over_limit() {
+        let predicate = make_predicate(ComparisonOperator::LessThan, 50.0, MemoryUnit::Megabytes);
+        let result = evaluate_memory_predicate(100 * 1024 * 1024, &predicate);
+        assert!(!result.passed);
+        assert_eq!(result.actual, "100.0MB");
+    }
+}
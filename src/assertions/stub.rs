@@ -0,0 +1,173 @@
+use crate::assertions::output::evaluate_output_predicate;
+use crate::assertions::timing::{evaluate_comparison, operator_str};
+use crate::assertions::AssertionResult;
+use crate::parser::ast::{RegexLiteral, StubPredicate};
+use crate::runner::stub::StubCall;
+
+pub fn evaluate_stub_predicate(
+    command: &str,
+    calls: &[StubCall],
+    predicate: &StubPredicate,
+) -> AssertionResult {
+    match predicate {
+        StubPredicate::Called => AssertionResult::new(
+            !calls.is_empty(),
+            format!("stub {} to be called", command),
+            describe_calls(command, calls),
+        ),
+        StubPredicate::CalledWith { value } => AssertionResult::new(
+            calls.iter().any(|call| call.args_line() == value.value),
+            format!("stub {} called with {}", command, value.raw),
+            describe_calls(command, calls),
+        ),
+        StubPredicate::CalledMatching { value } => evaluate_called_matching(command, calls, value),
+        StubPredicate::CallCount { operator, value } => AssertionResult::new(
+            evaluate_comparison(calls.len() as f64, operator, *value as f64),
+            format!(
+                "stub {} call_count {} {}",
+                command,
+                operator_str(operator),
+                value
+            ),
+            calls.len().to_string(),
+        ),
+        StubPredicate::Stdin(predicate) => match calls.last() {
+            Some(call) => {
+                evaluate_output_predicate(&String::from_utf8_lossy(&call.stdin), predicate)
+            }
+            None => AssertionResult::new(
+                false,
+                format!("stub {} to be called", command),
+                "not called".to_string(),
+            ),
+        },
+    }
+}
+
+fn evaluate_called_matching(
+    command: &str,
+    calls: &[StubCall],
+    value: &RegexLiteral,
+) -> AssertionResult {
+    let expected = format!("stub {} called with {}", command, value.raw);
+    let pattern = if value.flags.is_empty() {
+        value.pattern.clone()
+    } else {
+        format!("(?{}){}", value.flags, value.pattern)
+    };
+
+    match regex::Regex::new(&pattern) {
+        Ok(re) => AssertionResult::new(
+            calls.iter().any(|call| re.is_match(&call.args_line())),
+            expected,
+            describe_calls(command, calls),
+        ),
+        Err(e) => AssertionResult::with_error(
+            false,
+            expected,
+            describe_calls(command, calls),
+            format!("Invalid regex: {}", e),
+        ),
+    }
+}
+
+/// Each call as a command line, one per line.
+fn describe_calls(command: &str, calls: &[StubCall]) -> String {
+    if calls.is_empty() {
+        return "not called".to_string();
+    }
+    calls
+        .iter()
+        .map(|call| {
+            format!("{} {}", command, call.args_line())
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::{ComparisonOperator, OutputPredicate, QuoteType, StringLiteral};
+
+    fn calls() -> Vec<StubCall> {
+        vec![
+            StubCall {
+                args: vec!["get".to_string(), "pods".to_string()],
+                stdin: Vec::new(),
+            },
+            StubCall {
+                args: vec!["apply".to_string(), "-f".to_string(), "-".to_string()],
+                stdin: b"kind: Pod\n".to_vec(),
+            },
+        ]
+    }
+
+    fn string(value: &str) -> StringLiteral {
+        StringLiteral {
+            value: value.to_string(),
+            raw: format!("\"{}\"", value),
+            quote_type: QuoteType::Double,
+        }
+    }
+
+    #[test]
+    fn test_called() {
+        assert!(evaluate_stub_predicate("kubectl", &calls(), &StubPredicate::Called).passed);
+
+        let result = evaluate_stub_predicate("kubectl", &[], &StubPredicate::Called);
+        assert!(!result.passed);
+        assert_eq!(result.expected, "stub kubectl to be called");
+        assert_eq!(result.actual, "not called");
+    }
+
+    #[test]
+    fn test_called_with() {
+        let predicate = StubPredicate::CalledWith {
+            value: string("get pods"),
+        };
+        assert!(evaluate_stub_predicate("kubectl", &calls(), &predicate).passed);
+
+        let predicate = StubPredicate::CalledWith {
+            value: string("get"),
+        };
+        let result = evaluate_stub_predicate("kubectl", &calls(), &predicate);
+        assert!(!result.passed);
+        assert_eq!(result.actual, "kubectl get pods\nkubectl apply -f -");
+
+        let predicate = StubPredicate::CalledMatching {
+            value: RegexLiteral {
+                pattern: "^apply ".to_string(),
+                flags: String::new(),
+                raw: "/^apply /".to_string(),
+            },
+        };
+        assert!(evaluate_stub_predicate("kubectl", &calls(), &predicate).passed);
+    }
+
+    #[test]
+    fn test_call_count() {
+        let predicate = StubPredicate::CallCount {
+            operator: ComparisonOperator::Equal,
+            value: 2,
+        };
+        assert!(evaluate_stub_predicate("kubectl", &calls(), &predicate).passed);
+
+        let result = evaluate_stub_predicate("kubectl", &calls()[..1], &predicate);
+        assert!(!result.passed);
+        assert_eq!(result.expected, "stub kubectl call_count == 2");
+        assert_eq!(result.actual, "1");
+    }
+
+    #[test]
+    fn test_stdin_of_last_call() {
+        let predicate = StubPredicate::Stdin(OutputPredicate::Contains {
+            value: string("kind: Pod"),
+        });
+        assert!(evaluate_stub_predicate("kubectl", &calls(), &predicate).passed);
+        assert!(!evaluate_stub_predicate("kubectl", &calls()[..1], &predicate).passed);
+        assert!(!evaluate_stub_predicate("kubectl", &[], &predicate).passed);
+    }
+}
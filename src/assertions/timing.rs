@@ -19,27 +19,48 @@ pub fn format_duration(ms: f64) -> String {
 pub fn evaluate_duration_predicate(
     duration_ms: u64,
     predicate: &DurationPredicate,
+) -> AssertionResult {
+    evaluate_named_duration_predicate("duration", duration_ms, predicate)
+}
+
+/// Evaluates a duration predicate against a measurement other than wall-clock
+/// time, such as `cpu_time`, naming it in the expected message.
+pub fn evaluate_named_duration_predicate(
+    name: &str,
+    duration_ms: u64,
+    predicate: &DurationPredicate,
 ) -> AssertionResult {
     let expected_ms = duration_to_ms(&predicate.value);
     let passed = evaluate_comparison(duration_ms as f64, &predicate.operator, expected_ms);
 
-    let op_str = match predicate.operator {
+    AssertionResult::new(
+        passed,
+        format!(
+            "{} {} {}",
+            name,
+            operator_str(&predicate.operator),
+            predicate.value.raw
+        ),
+        format_duration(duration_ms as f64),
+    )
+}
+
+pub(crate) fn operator_str(operator: &ComparisonOperator) -> &'static str {
+    match operator {
         ComparisonOperator::Equal => "==",
         ComparisonOperator::NotEqual => "!=",
         ComparisonOperator::LessThan => "<",
         ComparisonOperator::LessThanOrEqual => "<=",
         ComparisonOperator::GreaterThan => ">",
         ComparisonOperator::GreaterThanOrEqual => ">=",
-    };
-
-    AssertionResult::new(
-        passed,
-        format!("duration {} {}", op_str, predicate.value.raw),
-        format_duration(duration_ms as f64),
-    )
+    }
 }
 
-fn evaluate_comparison(actual: f64, operator: &ComparisonOperator, expected: f64) -> bool {
+pub(crate) fn evaluate_comparison(
+    actual: f64,
+    operator: &ComparisonOperator,
+    expected: f64,
+) -> bool {
     // Use a small epsilon for floating-point equality comparisons.
     // This handles cases like 1.001s (1000.9999999999999ms) vs 1001ms.
     // Epsilon of 1e-9 ms (1 picosecond) is far smaller than meaningful precision
@@ -1,10 +1,10 @@
 use async_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, InsertTextFormat,
-    Position,
+    CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, CompletionTextEdit,
+    InsertTextFormat, Position, Range, TextEdit,
 };
 
 use crate::lsp::shell::ShellCommands;
-use crate::parser::ast::ParsedFile;
+use crate::parser::ast::{ASTNode, ParsedFile};
 
 /// Converts a UTF-16 code unit offset to a byte offset in a UTF-8 string.
 /// LSP protocol uses UTF-16 code units for character positions.
@@ -53,6 +53,7 @@ impl CompletionProvider {
             CompletionContextType::InsideTest => self.inside_test_completions(&context),
             CompletionContextType::AfterExpect => self.assertion_completions(&context),
             CompletionContextType::AfterRun => self.shell_command_completions(),
+            CompletionContextType::TestTag => self.tag_completions(parsed, &context, position),
             CompletionContextType::Unknown => Vec::new(),
         };
 
@@ -113,6 +114,21 @@ impl CompletionProvider {
             };
         }
 
+        // Check if we're typing an @tag after a TEST name
+        if is_test_line(prefix)
+            && prefix
+                .rsplit(' ')
+                .next()
+                .is_some_and(|w| w.starts_with('@'))
+        {
+            return CompletionContextInfo {
+                context_type: CompletionContextType::TestTag,
+                current_line: current_line.to_string(),
+                prefix: prefix.to_string(),
+                indent,
+            };
+        }
+
         // Determine if we're inside a test block by checking AST nodes
         // In the line-oriented syntax, we're inside a test from the TEST line
         // until we hit another TEST or the end of file
@@ -190,6 +206,14 @@ impl CompletionProvider {
                 insert_text_format: Some(InsertTextFormat::SNIPPET),
                 ..Default::default()
             },
+            CompletionItem {
+                label: "TIMEOUT".to_string(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                detail: Some("Set the command timeout for this test".to_string()),
+                insert_text: Some("TIMEOUT ${1:5s}".to_string()),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                ..Default::default()
+            },
         ];
 
         // Also suggest shell commands at this level
@@ -241,7 +265,7 @@ impl CompletionProvider {
                     "Check the contents of a file".to_string(),
                 )),
                 insert_text: Some(
-                    "file \"${1:path}\" ${2|contains,==,!=,matches,exists|}".to_string(),
+                    "file \"${1:path}\" ${2|contains,==,!=,matches,exists,sha256|}".to_string(),
                 ),
                 insert_text_format: Some(InsertTextFormat::SNIPPET),
                 ..Default::default()
@@ -257,9 +281,103 @@ impl CompletionProvider {
                 insert_text_format: Some(InsertTextFormat::SNIPPET),
                 ..Default::default()
             },
+            CompletionItem {
+                label: "max_rss".to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some("Assert on peak memory use".to_string()),
+                documentation: Some(async_lsp::lsp_types::Documentation::String(
+                    "Check the peak resident set size of the processes the command started"
+                        .to_string(),
+                )),
+                insert_text: Some("max_rss ${1|<,<=,>,>=|} ${2:50MB}".to_string()),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                ..Default::default()
+            },
+            CompletionItem {
+                label: "stdout.bytes".to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some("Assert on exact output bytes".to_string()),
+                documentation: Some(async_lsp::lsp_types::Documentation::String(
+                    "Compare the bytes written to stdout against a hex literal".to_string(),
+                )),
+                insert_text: Some(
+                    "stdout.bytes ${1|starts_with,contains,==,!=|} hex\"${2:1f8b08}\"".to_string(),
+                ),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                ..Default::default()
+            },
+            CompletionItem {
+                label: "stdout.length".to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some("Assert on output size in bytes".to_string()),
+                documentation: Some(async_lsp::lsp_types::Documentation::String(
+                    "Check how many bytes the command wrote to stdout".to_string(),
+                )),
+                insert_text: Some("stdout.length ${1|==,<,<=,>,>=|} ${2:1024}".to_string()),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                ..Default::default()
+            },
+            CompletionItem {
+                label: "cpu_time".to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some("Assert on CPU time".to_string()),
+                documentation: Some(async_lsp::lsp_types::Documentation::String(
+                    "Check the user plus system CPU time the command used".to_string(),
+                )),
+                insert_text: Some("cpu_time ${1|<,<=,>,>=|} ${2:1s}".to_string()),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                ..Default::default()
+            },
         ]
     }
 
+    /// Tags already used in the document, replacing the `@word` being typed.
+    fn tag_completions(
+        &self,
+        parsed: &ParsedFile,
+        context: &CompletionContextInfo,
+        position: Position,
+    ) -> Vec<CompletionItem> {
+        let typed = context.prefix.rsplit(' ').next().unwrap_or_default();
+        let start = position.character - typed.encode_utf16().count() as u32;
+        let range = Range {
+            start: Position {
+                line: position.line,
+                character: start,
+            },
+            end: position,
+        };
+
+        let mut tags: Vec<&str> = parsed
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                // The line being edited holds the partly typed tag
+                ASTNode::Test(test) if test.line != position.line as usize + 1 => {
+                    Some(test.tags.iter().map(String::as_str))
+                }
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+
+        tags.into_iter()
+            .map(|tag| CompletionItem {
+                label: format!("@{}", tag),
+                kind: Some(CompletionItemKind::ENUM_MEMBER),
+                detail: Some("Tag".to_string()),
+                filter_text: Some(format!("@{}", tag)),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range,
+                    new_text: format!("@{}", tag),
+                })),
+                ..Default::default()
+            })
+            .collect()
+    }
+
     fn shell_command_completions(&self) -> Vec<CompletionItem> {
         // Get common commands with descriptions
         let mut items: Vec<CompletionItem> = self
@@ -309,9 +427,28 @@ enum CompletionContextType {
     InsideTest,
     AfterExpect,
     AfterRun,
+    TestTag,
     Unknown,
 }
 
+/// Whether `prefix` is a `TEST` line past its quoted name, with or without a
+/// SKIP/ONLY/TODO modifier.
+fn is_test_line(prefix: &str) -> bool {
+    let closed = |quote: char| {
+        let count = prefix.matches(quote).count();
+        count > 0 && count.is_multiple_of(2)
+    };
+    if !closed('"') && !closed('\'') {
+        return false;
+    }
+    let line = prefix.trim_start();
+    let line = ["SKIP ", "ONLY ", "TODO "]
+        .iter()
+        .find_map(|modifier| line.strip_prefix(modifier))
+        .unwrap_or(line);
+    line.starts_with("TEST ")
+}
+
 #[derive(Debug, Clone)]
 struct CompletionContextInfo {
     context_type: CompletionContextType,
@@ -453,6 +590,40 @@ mod tests {
         assert_eq!(context.context_type, CompletionContextType::AfterExpect);
     }
 
+    #[test]
+    fn test_tag_completions_offer_known_tags() {
+        let provider = CompletionProvider::new();
+        let text = "TEST \"a\" @smoke @slow\nRUN ls\n\nTEST \"b\" @sm\nRUN ls\n";
+        let parsed = match parse_file(text, "test.hone") {
+            crate::parser::ParseResult::Success { file } => file,
+            _ => panic!("Failed to parse"),
+        };
+
+        let position = Position {
+            line: 3,
+            character: 12,
+        };
+        let context = provider.determine_context(&parsed, position, text);
+        assert_eq!(context.context_type, CompletionContextType::TestTag);
+
+        let items = provider.tag_completions(&parsed, &context, position);
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, vec!["@slow", "@smoke"]);
+        let Some(CompletionTextEdit::Edit(edit)) = &items[1].text_edit else {
+            panic!("expected a text edit");
+        };
+        assert_eq!(edit.range.start.character, 9);
+
+        // An @ inside the test name is not a tag
+        let text = "TEST \"mail @";
+        let position = Position {
+            line: 0,
+            character: 12,
+        };
+        let context = provider.determine_context(&parsed, position, text);
+        assert_ne!(context.context_type, CompletionContextType::TestTag);
+    }
+
     #[test]
     fn test_context_detection_after_run() {
         let provider = CompletionProvider::new();
@@ -582,7 +753,7 @@ mod tests {
         let insert_text = file.insert_text.as_ref().unwrap();
         assert_eq!(
             insert_text,
-            "file \"${1:path}\" ${2|contains,==,!=,matches,exists|}"
+            "file \"${1:path}\" ${2|contains,==,!=,matches,exists,sha256|}"
         );
         // Should not have block syntax (newlines or nested braces for file content)
         assert!(!insert_text.contains('\n'));
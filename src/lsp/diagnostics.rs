@@ -12,8 +12,9 @@ pub fn generate_diagnostics(uri: &Url, content: &str) -> Vec<Diagnostic> {
 
     match parse_result {
         ParseResult::Success { file } => {
-            // Process warnings
-            for warning in &file.warnings {
+            // Process warnings. Ones from imported files belong to those files;
+            // the parser reports their errors on the IMPORT line too
+            for warning in file.warnings.iter().filter(|w| w.filename == path) {
                 diagnostics.push(create_diagnostic_from_warning(warning));
             }
 
@@ -43,7 +44,7 @@ pub fn generate_diagnostics(uri: &Url, content: &str) -> Vec<Diagnostic> {
                 })
                 .collect();
 
-            for error in &file.errors {
+            for error in file.errors.iter().filter(|e| e.filename == path) {
                 // Skip if this error was already reported via an error node
                 if !error_node_messages
                     .iter()
@@ -286,6 +287,17 @@ fn check_assertion_types(assert_node: &crate::parser::AssertNode) -> Vec<Diagnos
                 }
             }
         },
+        AssertionExpression::Bytes { .. } => {
+            // Hex literals are validated by the parser and are never empty
+        }
+        AssertionExpression::Length { predicate, .. } => {
+            if predicate.value == 0 && predicate.operator == ComparisonOperator::LessThan {
+                diagnostics.push(create_semantic_diagnostic(
+                    assert_node.line,
+                    "Output length can never be below 0; did you mean == 0?",
+                ));
+            }
+        }
         AssertionExpression::ExitCode { predicate, .. } => {
             if predicate.value < 0 {
                 diagnostics.push(create_semantic_diagnostic(
@@ -299,7 +311,10 @@ fn check_assertion_types(assert_node: &crate::parser::AssertNode) -> Vec<Diagnos
                 ));
             }
         }
-        AssertionExpression::Duration { predicate, .. } => {
+        AssertionExpression::Duration { predicate, .. }
+        | AssertionExpression::DurationStat { predicate, .. }
+        | AssertionExpression::CpuTime { predicate, .. }
+        | AssertionExpression::TestDuration { predicate } => {
             if predicate.value.value < 0.0 {
                 diagnostics.push(create_semantic_diagnostic(
                     assert_node.line,
@@ -321,6 +336,19 @@ fn check_assertion_types(assert_node: &crate::parser::AssertNode) -> Vec<Diagnos
                 ));
             }
         }
+        AssertionExpression::MaxRss { predicate, .. } => {
+            if predicate.value.value == 0.0
+                && matches!(
+                    predicate.operator,
+                    ComparisonOperator::LessThan | ComparisonOperator::LessThanOrEqual
+                )
+            {
+                diagnostics.push(create_semantic_diagnostic(
+                    assert_node.line,
+                    "max_rss can never be below 0; did you mean a larger limit?",
+                ));
+            }
+        }
         AssertionExpression::File { path, predicate } => {
             if path.value.is_empty() {
                 diagnostics.push(create_semantic_diagnostic(
@@ -342,6 +370,9 @@ fn check_assertion_types(assert_node: &crate::parser::AssertNode) -> Vec<Diagnos
                 FilePredicate::Equals { .. } => {
                     // `== ""` is valid - it checks for empty file content
                 }
+                FilePredicate::Sha256 { .. } => {
+                    // Digest format is validated by the parser
+                }
                 FilePredicate::Matches { value } => {
                     if value.pattern.is_empty() {
                         diagnostics.push(create_semantic_diagnostic(
@@ -368,6 +399,9 @@ fn check_assertion_types(assert_node: &crate::parser::AssertNode) -> Vec<Diagnos
                 }
             }
         }
+        AssertionExpression::Stub { .. } | AssertionExpression::Http { .. } => {
+            // Validated by the parser
+        }
     }
 
     diagnostics
@@ -174,6 +174,44 @@ impl FormattingProvider {
                     result.push('\n');
                     current_line = env.line + 1;
                 }
+                ASTNode::Timeout(timeout) => {
+                    let indent = self.get_indent(if in_test_block { 1 } else { 0 });
+                    result.push_str(&indent);
+                    result.push_str("TIMEOUT ");
+                    result.push_str(&timeout.duration.raw);
+                    result.push('\n');
+                    current_line = timeout.line + 1;
+                }
+                ASTNode::Retry(retry) => {
+                    let indent = self.get_indent(if in_test_block { 1 } else { 0 });
+                    result.push_str(&indent);
+                    result.push_str(&format!("RETRY {}\n", retry.count));
+                    current_line = retry.line + 1;
+                }
+                ASTNode::SkipIf(skip_if) => {
+                    let indent = self.get_indent(if in_test_block { 1 } else { 0 });
+                    result.push_str(&indent);
+                    result.push_str(&format!("SKIP IF {}\n", skip_if.raw));
+                    current_line = skip_if.line + 1;
+                }
+                ASTNode::Import(import) => {
+                    let indent = self.get_indent(if in_test_block { 1 } else { 0 });
+                    result.push_str(&indent);
+                    result.push_str(&format!("IMPORT \"{}\"\n", import.path));
+                    current_line = import.line + 1;
+                }
+                ASTNode::Stub(stub) => {
+                    let indent = self.get_indent(if in_test_block { 1 } else { 0 });
+                    result.push_str(&indent);
+                    result.push_str(&format!("STUB {}\n", stub.raw));
+                    current_line = stub.line + 1;
+                }
+                ASTNode::Http(http) => {
+                    let indent = self.get_indent(if in_test_block { 1 } else { 0 });
+                    result.push_str(&indent);
+                    result.push_str(&format!("HTTP {}\n", http.raw));
+                    current_line = http.line + 1;
+                }
                 ASTNode::Pragma(_) => {
                     // Already handled above
                     current_line = node_line + 1;
@@ -80,6 +80,7 @@ pub fn handle_initialize(_params: InitializeParams) -> InitializeResult {
             hover_provider: Some(HoverProviderCapability::Simple(true)),
             document_symbol_provider: Some(OneOf::Left(true)),
             document_formatting_provider: Some(OneOf::Left(true)),
+            definition_provider: Some(OneOf::Left(true)),
             semantic_tokens_provider: Some(
                 SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
                     legend,
@@ -226,6 +227,33 @@ pub fn handle_hover(state: &ServerState, params: HoverParams) -> Option<Hover> {
     state.hover_provider.provide_hover(text, &params)
 }
 
+/// Jumps from an `IMPORT` line to the imported file.
+pub fn handle_goto_definition(
+    state: &ServerState,
+    params: GotoDefinitionParams,
+) -> Option<GotoDefinitionResponse> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    tracing::debug!("Definition requested for: {}", uri);
+
+    let text = state.get_document(uri)?;
+    let line = params.text_document_position_params.position.line as usize + 1;
+
+    let crate::parser::ParseResult::Success { file } = crate::parser::parse_file(text, uri.path())
+    else {
+        return None;
+    };
+    let resolved = file.nodes.iter().find_map(|node| match node {
+        crate::parser::ASTNode::Import(import) if import.line == line => Some(&import.resolved),
+        _ => None,
+    })?;
+
+    let path = std::fs::canonicalize(resolved).unwrap_or_else(|_| resolved.into());
+    Some(GotoDefinitionResponse::Scalar(Location {
+        uri: Url::from_file_path(path).ok()?,
+        range: Range::default(),
+    }))
+}
+
 pub fn handle_document_symbols(
     state: &ServerState,
     params: DocumentSymbolParams,
@@ -359,9 +387,43 @@ mod tests {
         assert!(result.capabilities.hover_provider.is_some());
         assert!(result.capabilities.document_symbol_provider.is_some());
         assert!(result.capabilities.document_formatting_provider.is_some());
+        assert!(result.capabilities.definition_provider.is_some());
         assert!(result.capabilities.semantic_tokens_provider.is_some());
     }
 
+    #[test]
+    fn test_goto_definition_opens_imported_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(dir.join("common")).unwrap();
+        std::fs::write(dir.join("common/setup.hone"), "ENV MODE=test\n").unwrap();
+
+        let mut state = ServerState::new();
+        let uri = Url::from_file_path(dir.join("main.hone")).unwrap();
+        let text = "IMPORT \"common/setup.hone\"\nTEST \"uses setup\"\n".to_string();
+        state.open_document(uri.clone(), text);
+
+        let params = |line| GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position { line, character: 3 },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let Some(GotoDefinitionResponse::Scalar(location)) =
+            handle_goto_definition(&state, params(0))
+        else {
+            panic!("expected a location for the IMPORT line");
+        };
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(dir.join("common/setup.hone")).unwrap()
+        );
+        assert!(handle_goto_definition(&state, params(1)).is_none());
+    }
+
     #[test]
     fn test_handle_initialize_trigger_characters() {
         let params = InitializeParams::default();
@@ -31,7 +31,10 @@ impl HoverProvider {
         let (word, _start, _end) = self.extract_word_at_position(&chars, char_idx)?;
 
         // Look up documentation for the word
-        let documentation = self.get_documentation(&word)?;
+        let documentation = match word.strip_prefix('@') {
+            Some(tag) => self.tag_doc(tag, content)?,
+            None => self.get_documentation(&word)?,
+        };
 
         Some(Hover {
             contents: HoverContents::Markup(MarkupContent {
@@ -79,16 +82,59 @@ impl HoverProvider {
         Some((word, start, end))
     }
 
+    /// Lists the tests in the document that carry `@tag`.
+    fn tag_doc(&self, tag: &str, content: &str) -> Option<String> {
+        let crate::parser::ParseResult::Success { file } = crate::parser::parse_file(content, "")
+        else {
+            return None;
+        };
+        let tests: Vec<String> = file
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                crate::parser::ast::ASTNode::Test(test) if test.tags.iter().any(|t| t == tag) => {
+                    Some(format!("- `{}` (line {})", test.name, test.line))
+                }
+                _ => None,
+            })
+            .collect();
+        if tests.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "# @{tag}\n\nTag on {count} {tests_text} in this file:\n\n{list}\n\nRun them with `--tag {tag}`, or leave them out with `--exclude-tag {tag}`.\n",
+            count = tests.len(),
+            tests_text = if tests.len() == 1 { "test" } else { "tests" },
+            list = tests.join("\n"),
+        ))
+    }
+
     fn get_documentation(&self, word: &str) -> Option<String> {
         match word {
             "TEST" => Some(self.test_keyword_doc()),
             "RUN" => Some(self.run_keyword_doc()),
             "ASSERT" => Some(self.assert_keyword_doc()),
+            "TIMEOUT" => Some(self.timeout_keyword_doc()),
+            "RETRY" => Some(self.retry_keyword_doc()),
+            "REPEAT" => Some(self.repeat_keyword_doc()),
+            "PARTIAL" => Some(self.partial_keyword_doc()),
+            "WITH" => Some(self.with_keyword_doc()),
+            "SKIP" => Some(self.skip_keyword_doc()),
+            "ONLY" => Some(self.only_keyword_doc()),
+            "TODO" => Some(self.todo_keyword_doc()),
+            "IMPORT" => Some(self.import_keyword_doc()),
+            "STUB" | "stub" => Some(self.stub_keyword_doc()),
+            "HTTP" | "http" => Some(self.http_keyword_doc()),
             "stdout" => Some(self.stdout_assertion_doc()),
             "stdout_raw" => Some(self.stdout_raw_assertion_doc()),
             "stderr" => Some(self.stderr_assertion_doc()),
             "exitcode" | "exit_code" => Some(self.exitcode_assertion_doc()),
             "duration" => Some(self.duration_assertion_doc()),
+            "max_rss" => Some(self.max_rss_assertion_doc()),
+            "cpu_time" => Some(self.cpu_time_assertion_doc()),
+            "bytes" => Some(self.bytes_assertion_doc()),
+            "length" => Some(self.length_assertion_doc()),
             "file" => Some(self.file_assertion_doc()),
             _ => None,
         }
@@ -136,6 +182,9 @@ ASSERT <assertion-type> <predicate>
 - `stderr` - Assert on standard error
 - `exit_code` - Assert on exit code
 - `duration` - Assert on execution duration
+- `max_rss` - Assert on peak memory use
+- `cpu_time` - Assert on CPU time
+- `stdout.bytes` / `stdout.length` - Assert on the exact bytes written
 - `file` - Assert on file content
 
 ## Example
@@ -172,6 +221,281 @@ Named runs allow referencing specific command results in assertions.
         .to_string()
     }
 
+    fn timeout_keyword_doc(&self) -> String {
+        r#"# TIMEOUT
+
+Set the command timeout for the RUN statements that follow it in the current test.
+Overrides the file-level `#! timeout:` pragma.
+
+## Syntax
+
+```hone
+TIMEOUT <duration>
+RUN <command> TIMEOUT <duration>
+```
+
+The inline form applies to a single RUN and takes precedence over both.
+
+## Example
+
+```hone
+TEST "release build"
+TIMEOUT 5s
+RUN cargo check
+RUN cargo build --release TIMEOUT 180s
+```
+"#
+        .to_string()
+    }
+
+    fn with_keyword_doc(&self) -> String {
+        r#"# WITH
+
+Run a TEST once for each row of parameter values.
+
+## Syntax
+
+```hone
+TEST "name {param}" WITH param IN value1, value2
+TEST "name {a} {b}" WITH a, b IN (a1, b1), (a2, b2)
+```
+
+Each row runs as its own TEST, named by filling in the `{param}`
+placeholders. Parameters are set as environment variables. Quote values
+that contain commas or spaces.
+
+## Example
+
+```hone
+TEST "convert to {fmt}" WITH fmt IN json, yaml, toml
+RUN mycli convert --to "$fmt" input.txt
+ASSERT exit_code == 0
+```
+"#
+        .to_string()
+    }
+
+    fn retry_keyword_doc(&self) -> String {
+        r#"# RETRY
+
+Run the current test again, in a fresh shell, when it fails.
+Overrides the file-level `#! retries:` pragma.
+
+## Syntax
+
+```hone
+RETRY <count>
+```
+
+A test that passes on a retry is reported as **flaky**, along with why each
+earlier attempt failed.
+
+## Example
+
+```hone
+TEST "daemon answers"
+RETRY 3
+RUN curl -sf localhost:8080/health
+ASSERT exit_code == 0
+```
+"#
+        .to_string()
+    }
+
+    fn skip_keyword_doc(&self) -> String {
+        r#"# SKIP
+
+Mark a test as skipped. It is reported but not run.
+
+## Syntax
+
+```hone
+SKIP TEST "<name>"
+SKIP IF missing <command>
+SKIP IF unset <VAR>
+SKIP IF env <VAR> == <value>
+SKIP IF uname != <value>
+```
+
+`SKIP IF` inside a test skips it only when the condition holds, and
+reports why.
+
+## Example
+
+```hone
+SKIP TEST "uploads to staging"
+RUN ./upload --env staging
+ASSERT exit_code == 0
+
+TEST "builds the image"
+SKIP IF missing docker
+RUN docker build .
+```
+"#
+        .to_string()
+    }
+
+    fn only_keyword_doc(&self) -> String {
+        r#"# ONLY
+
+Run only the tests marked `ONLY`, across every file in the run. All other
+tests are reported as skipped.
+
+## Syntax
+
+```hone
+ONLY TEST "<name>"
+```
+
+Pass `--forbid-only` in CI to fail the run if an `ONLY` is left in.
+"#
+        .to_string()
+    }
+
+    fn todo_keyword_doc(&self) -> String {
+        r#"# TODO
+
+Mark a test as not written yet. It is reported as pending and not run.
+
+## Syntax
+
+```hone
+TODO TEST "<name>"
+```
+"#
+        .to_string()
+    }
+
+    fn import_keyword_doc(&self) -> String {
+        r#"# IMPORT
+
+Include the statements of another file where the IMPORT appears. The path is
+relative to the importing file. Imported files can't contain TEST blocks.
+
+## Syntax
+
+```hone
+IMPORT "<path>"
+```
+
+## Example
+
+```hone
+IMPORT "../common/setup.hone"
+```
+"#
+        .to_string()
+    }
+
+    fn stub_keyword_doc(&self) -> String {
+        r#"# STUB
+
+Put a fake command first on PATH for the rest of the TEST. Every call's
+arguments and stdin are recorded for `ASSERT stub` assertions.
+
+## Syntax
+
+```hone
+STUB <command> [STDOUT "<text>"] [STDERR "<text>"] [EXIT <code>]
+STUB <command> SCRIPT "<shell code>"
+ASSERT stub <command> called
+ASSERT stub <command> called with "<args>"
+ASSERT stub <command> call_count == <n>
+ASSERT stub <command> stdin contains "<text>"
+```
+
+## Example
+
+```hone
+STUB kubectl STDOUT "pod-a\n"
+RUN mycli deploy
+ASSERT stub kubectl called with "get pods"
+```
+"#
+        .to_string()
+    }
+
+    fn http_keyword_doc(&self) -> String {
+        r#"# HTTP
+
+Add a route to an HTTP server for the rest of the TEST. The server listens
+on localhost at `$HONE_HTTP_URL` and records every request for
+`ASSERT http request` assertions. Unknown routes get a 404.
+
+## Syntax
+
+```hone
+HTTP <METHOD> <path> [STATUS <code>] [HEADER "<name>: <value>"] [BODY "<text>"]
+HTTP <METHOD> <path> [STATUS <code>] FILE "<fixture path>"
+ASSERT http request "<METHOD> <path>" received
+ASSERT http request "<METHOD> <path>" count == <n>
+ASSERT http request "<METHOD> <path>" body contains "<text>"
+```
+
+## Example
+
+```hone
+HTTP POST /v1/items STATUS 201 BODY '{"id": 1}'
+RUN mycli create --api "$HONE_HTTP_URL"
+ASSERT http request "POST /v1/items" body contains '"name"'
+```
+"#
+        .to_string()
+    }
+
+    fn partial_keyword_doc(&self) -> String {
+        r#"# PARTIAL
+
+Allow an output assertion to check output that was truncated.
+
+## Syntax
+
+```hone
+ASSERT <output assertion> PARTIAL
+```
+
+Output past the `#! max_output:` limit (default `10MB`) is only saved to
+disk. Assertions on truncated output fail unless marked `PARTIAL`, which
+checks the kept part. `stdout.length` always counts every byte.
+
+## Example
+
+```hone
+#! max_output: 1MB
+
+RUN generate-report
+ASSERT stdout matches /^Report/ PARTIAL
+```
+"#
+        .to_string()
+    }
+
+    fn repeat_keyword_doc(&self) -> String {
+        r#"# REPEAT
+
+Run a command several times and record duration statistics.
+
+## Syntax
+
+```hone
+RUN <name>: <command> REPEAT <count>
+```
+
+Repetition stops at the first non-zero exit code.
+
+## Example
+
+```hone
+RUN startup: mycli --version REPEAT 20
+ASSERT startup.duration.p95 < 200ms
+ASSERT startup.duration.median < 100ms
+```
+
+Available statistics: `min`, `median`, `p95`, `max`.
+"#
+        .to_string()
+    }
+
     fn stdout_assertion_doc(&self) -> String {
         r#"# stdout
 
@@ -310,6 +634,103 @@ expect duration <= 2s
         .to_string()
     }
 
+    fn max_rss_assertion_doc(&self) -> String {
+        r#"# max_rss
+
+Assert on the peak resident set size of the processes a command started.
+
+## Syntax
+
+```hone
+ASSERT max_rss <operator> <size>
+```
+
+## Units
+
+- `B`, `KB`, `MB`, `GB` - powers of 1024
+
+## Example
+
+```hone
+ASSERT max_rss < 50MB
+ASSERT build.max_rss <= 1.5GB
+```
+
+The RUN runs in a subshell so its memory can be measured exactly; `cd` and
+variables it sets do not carry over to later RUNs.
+"#
+        .to_string()
+    }
+
+    fn cpu_time_assertion_doc(&self) -> String {
+        r#"# cpu_time
+
+Assert on the user plus system CPU time a command used.
+
+## Syntax
+
+```hone
+ASSERT cpu_time <operator> <time>
+```
+
+## Example
+
+```hone
+ASSERT cpu_time < 1s
+ASSERT build.cpu_time <= 30s
+```
+"#
+        .to_string()
+    }
+
+    fn bytes_assertion_doc(&self) -> String {
+        r#"# bytes
+
+Assert on the exact bytes written to stdout or stderr, for binary output.
+
+## Syntax
+
+```hone
+ASSERT stdout.bytes <predicate> hex"<digits>"
+```
+
+## Predicates
+
+- `== hex"..."` / `!= hex"..."` - Output is exactly these bytes
+- `contains hex"..."` - Output contains these bytes
+- `starts_with hex"..."` - Output begins with these bytes
+
+## Example
+
+```hone
+ASSERT stdout.bytes starts_with hex"1f8b08"
+ASSERT stderr.bytes == hex"ff fe"
+```
+"#
+        .to_string()
+    }
+
+    fn length_assertion_doc(&self) -> String {
+        r#"# length
+
+Assert on the number of bytes written to stdout or stderr.
+
+## Syntax
+
+```hone
+ASSERT stdout.length <operator> <count>
+```
+
+## Example
+
+```hone
+ASSERT stdout.length == 1024
+ASSERT build.stderr.length == 0
+```
+"#
+        .to_string()
+    }
+
     fn file_assertion_doc(&self) -> String {
         r#"# file
 
@@ -322,6 +743,7 @@ Assert on file content or existence.
 - `matches /regex/` - File content matches the regex pattern
 - `equals "text"` or `== "text"` - File content equals the text exactly
 - `!= "text"` - File content does not equal the text
+- `sha256 == "digest"` - SHA-256 of the file's bytes equals the hex digest
 
 ## Example
 
@@ -389,6 +811,29 @@ mod tests {
         assert!(provider.get_documentation("TEST").is_some());
         assert!(provider.get_documentation("RUN").is_some());
         assert!(provider.get_documentation("ASSERT").is_some());
+        assert!(provider.get_documentation("TIMEOUT").is_some());
+        assert!(provider.get_documentation("REPEAT").is_some());
+        assert!(provider.get_documentation("PARTIAL").is_some());
+        assert!(provider.get_documentation("WITH").is_some());
+        assert!(provider.get_documentation("RETRY").is_some());
+        assert!(provider.get_documentation("SKIP").is_some());
+        assert!(provider.get_documentation("ONLY").is_some());
+        assert!(provider.get_documentation("TODO").is_some());
+        assert!(provider.get_documentation("IMPORT").is_some());
+        assert!(provider.get_documentation("STUB").is_some());
+        assert!(provider.get_documentation("HTTP").is_some());
+    }
+
+    #[test]
+    fn test_tag_doc_lists_tagged_tests() {
+        let provider = HoverProvider::new();
+        let content = "TEST \"a\" @smoke\nRUN true\n\nTEST \"b\" @slow\nRUN true\n";
+
+        let doc = provider.tag_doc("smoke", content).unwrap();
+        assert!(doc.starts_with("# @smoke"));
+        assert!(doc.contains("- `a` (line 1)"));
+        assert!(!doc.contains("`b`"));
+        assert!(provider.tag_doc("missing", content).is_none());
     }
 
     #[test]
@@ -399,6 +844,10 @@ mod tests {
         assert!(provider.get_documentation("stderr").is_some());
         assert!(provider.get_documentation("exitcode").is_some());
         assert!(provider.get_documentation("duration").is_some());
+        assert!(provider.get_documentation("max_rss").is_some());
+        assert!(provider.get_documentation("cpu_time").is_some());
+        assert!(provider.get_documentation("bytes").is_some());
+        assert!(provider.get_documentation("length").is_some());
         assert!(provider.get_documentation("file").is_some());
     }
 
@@ -366,6 +366,144 @@ impl SemanticTokensProvider {
                         prev_start = start;
                     }
                 }
+                ASTNode::Timeout(timeout_node) => {
+                    let line_idx = timeout_node.line.saturating_sub(1);
+                    if let Some((line, start, length)) =
+                        Self::find_token_in_line(&lines, line_idx, "TIMEOUT")
+                    {
+                        let (delta_line, delta_start) = if line == prev_line {
+                            (0, start.saturating_sub(prev_start))
+                        } else {
+                            (line.saturating_sub(prev_line), start)
+                        };
+
+                        tokens.push(SemanticToken {
+                            delta_line: delta_line as u32,
+                            delta_start: delta_start as u32,
+                            length: length as u32,
+                            token_type: self.token_type_index(&SemanticTokenType::KEYWORD),
+                            token_modifiers_bitset: 0,
+                        });
+
+                        prev_line = line;
+                        prev_start = start;
+                    }
+                }
+                ASTNode::Retry(retry_node) => {
+                    let line_idx = retry_node.line.saturating_sub(1);
+                    if let Some((line, start, length)) =
+                        Self::find_token_in_line(&lines, line_idx, "RETRY")
+                    {
+                        let (delta_line, delta_start) = if line == prev_line {
+                            (0, start.saturating_sub(prev_start))
+                        } else {
+                            (line.saturating_sub(prev_line), start)
+                        };
+
+                        tokens.push(SemanticToken {
+                            delta_line: delta_line as u32,
+                            delta_start: delta_start as u32,
+                            length: length as u32,
+                            token_type: self.token_type_index(&SemanticTokenType::KEYWORD),
+                            token_modifiers_bitset: 0,
+                        });
+
+                        prev_line = line;
+                        prev_start = start;
+                    }
+                }
+                ASTNode::SkipIf(skip_if) => {
+                    let line_idx = skip_if.line.saturating_sub(1);
+                    if let Some((line, start, length)) =
+                        Self::find_token_in_line(&lines, line_idx, "SKIP")
+                    {
+                        let (delta_line, delta_start) = if line == prev_line {
+                            (0, start.saturating_sub(prev_start))
+                        } else {
+                            (line.saturating_sub(prev_line), start)
+                        };
+
+                        tokens.push(SemanticToken {
+                            delta_line: delta_line as u32,
+                            delta_start: delta_start as u32,
+                            length: length as u32,
+                            token_type: self.token_type_index(&SemanticTokenType::KEYWORD),
+                            token_modifiers_bitset: 0,
+                        });
+
+                        prev_line = line;
+                        prev_start = start;
+                    }
+                }
+                ASTNode::Import(import) => {
+                    let line_idx = import.line.saturating_sub(1);
+                    if let Some((line, start, length)) =
+                        Self::find_token_in_line(&lines, line_idx, "IMPORT")
+                    {
+                        let (delta_line, delta_start) = if line == prev_line {
+                            (0, start.saturating_sub(prev_start))
+                        } else {
+                            (line.saturating_sub(prev_line), start)
+                        };
+
+                        tokens.push(SemanticToken {
+                            delta_line: delta_line as u32,
+                            delta_start: delta_start as u32,
+                            length: length as u32,
+                            token_type: self.token_type_index(&SemanticTokenType::KEYWORD),
+                            token_modifiers_bitset: 0,
+                        });
+
+                        prev_line = line;
+                        prev_start = start;
+                    }
+                }
+                ASTNode::Stub(stub) => {
+                    let line_idx = stub.line.saturating_sub(1);
+                    if let Some((line, start, length)) =
+                        Self::find_token_in_line(&lines, line_idx, "STUB")
+                    {
+                        let (delta_line, delta_start) = if line == prev_line {
+                            (0, start.saturating_sub(prev_start))
+                        } else {
+                            (line.saturating_sub(prev_line), start)
+                        };
+
+                        tokens.push(SemanticToken {
+                            delta_line: delta_line as u32,
+                            delta_start: delta_start as u32,
+                            length: length as u32,
+                            token_type: self.token_type_index(&SemanticTokenType::KEYWORD),
+                            token_modifiers_bitset: 0,
+                        });
+
+                        prev_line = line;
+                        prev_start = start;
+                    }
+                }
+                ASTNode::Http(http) => {
+                    let line_idx = http.line.saturating_sub(1);
+                    if let Some((line, start, length)) =
+                        Self::find_token_in_line(&lines, line_idx, "HTTP")
+                    {
+                        let (delta_line, delta_start) = if line == prev_line {
+                            (0, start.saturating_sub(prev_start))
+                        } else {
+                            (line.saturating_sub(prev_line), start)
+                        };
+
+                        tokens.push(SemanticToken {
+                            delta_line: delta_line as u32,
+                            delta_start: delta_start as u32,
+                            length: length as u32,
+                            token_type: self.token_type_index(&SemanticTokenType::KEYWORD),
+                            token_modifiers_bitset: 0,
+                        });
+
+                        prev_line = line;
+                        prev_start = start;
+                    }
+                }
                 ASTNode::Pragma(_) => {
                     // Already handled above
                 }
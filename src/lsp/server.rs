@@ -83,7 +83,7 @@ pub async fn run_lsp_server() -> Result<()> {
         DidSaveTextDocument, Exit, Initialized,
     };
     use async_lsp::lsp_types::request::{
-        Completion, DocumentSymbolRequest, Formatting, HoverRequest, Initialize,
+        Completion, DocumentSymbolRequest, Formatting, GotoDefinition, HoverRequest, Initialize,
         SemanticTokensFullRequest, Shutdown,
     };
 
@@ -149,6 +149,14 @@ pub async fn run_lsp_server() -> Result<()> {
                     Ok(result)
                 }
             })
+            .request::<GotoDefinition, _>(|state, params| {
+                let state = state.clone();
+                async move {
+                    tracing::debug!("Handling definition request");
+                    let result = crate::lsp::handlers::handle_goto_definition(&state, params);
+                    Ok(result)
+                }
+            })
             .request::<DocumentSymbolRequest, _>(|state, params| {
                 let state = state.clone();
                 async move {
@@ -35,7 +35,12 @@ impl SymbolsProvider {
 
                     current_test_symbol = Some(DocumentSymbol {
                         name: test.name.clone(),
-                        detail: Some("test".to_string()),
+                        detail: Some(
+                            std::iter::once("test".to_string())
+                                .chain(test.tags.iter().map(|tag| format!("@{}", tag)))
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                        ),
                         kind: SymbolKind::FUNCTION,
                         tags: None,
                         range: Range {
@@ -186,6 +191,7 @@ impl SymbolsProvider {
 }
 
 fn extract_assertion_name(assert: &crate::parser::ast::AssertNode) -> String {
+    use crate::assertions::bytes::selector_name;
     use crate::parser::ast::AssertionExpression;
 
     match &assert.expression {
@@ -208,11 +214,27 @@ fn extract_assertion_name(assert: &crate::parser::ast::AssertNode) -> String {
 
             format!("expect {} {}", selector_str, predicate_str)
         }
+        AssertionExpression::Bytes { selector, .. } => {
+            format!("expect {} bytes", selector_name(selector))
+        }
+        AssertionExpression::Length { selector, .. } => {
+            format!("expect {} length", selector_name(selector))
+        }
         AssertionExpression::ExitCode { .. } => "expect exitcode".to_string(),
         AssertionExpression::Duration { .. } => "expect duration".to_string(),
+        AssertionExpression::DurationStat { statistic, .. } => {
+            format!("expect duration {}", statistic.as_str())
+        }
+        AssertionExpression::MaxRss { .. } => "expect max rss".to_string(),
+        AssertionExpression::CpuTime { .. } => "expect cpu time".to_string(),
+        AssertionExpression::TestDuration { .. } => "expect test duration".to_string(),
         AssertionExpression::File { path, .. } => {
             format!("expect file \"{}\"", path.value)
         }
+        AssertionExpression::Stub { command, .. } => format!("expect stub {}", command),
+        AssertionExpression::Http { request, .. } => {
+            format!("expect http request {}", request.raw)
+        }
     }
 }
 
@@ -241,10 +263,16 @@ mod tests {
             nodes: vec![
                 ASTNode::Test(TestNode {
                     name: "my test".to_string(),
+                    params: None,
+                    modifier: None,
+                    tags: vec![],
                     line: 1,
                 }),
                 ASTNode::Test(TestNode {
                     name: "another test".to_string(),
+                    params: None,
+                    modifier: None,
+                    tags: vec![],
                     line: 5,
                 }),
             ],
@@ -269,6 +297,9 @@ mod tests {
             nodes: vec![
                 ASTNode::Test(TestNode {
                     name: "my test".to_string(),
+                    params: None,
+                    modifier: None,
+                    tags: vec![],
                     line: 1,
                 }),
                 ASTNode::Assert(AssertNode {
@@ -279,6 +310,7 @@ mod tests {
                             value: 0,
                         },
                     },
+                    partial: false,
                     line: 2,
                     raw: "expect exitcode 0".to_string(),
                 }),
@@ -307,6 +339,9 @@ mod tests {
             pragmas: vec![],
             nodes: vec![ASTNode::Test(TestNode {
                 name: "日本語テスト".to_string(),
+                params: None,
+                modifier: None,
+                tags: vec![],
                 line: 1,
             })],
             warnings: vec![],
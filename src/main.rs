@@ -1,4 +1,8 @@
 use clap::{Parser, Subcommand};
+use hone::runner::baseline::{parse_baseline_name, parse_percentage};
+use hone::runner::config::{parse_env_assignment, parse_env_pattern};
+use hone::runner::resources::{report_rusage, set_rusage_helper, RUSAGE_HELPER_ARG};
+use hone::runner::ProjectConfig;
 use hone::{run_lsp_server, run_tests, run_watch_mode, OutputFormat, RunnerOptions};
 
 mod setup;
@@ -20,6 +24,15 @@ struct Cli {
     #[arg(long)]
     shell: Option<String>,
 
+    /// Run every file once in each of these shells (e.g. bash,zsh,dash)
+    #[arg(
+        long,
+        value_name = "SHELLS",
+        value_delimiter = ',',
+        conflicts_with = "shell"
+    )]
+    shell_matrix: Option<Vec<String>>,
+
     /// Enable verbose output
     #[arg(long, short)]
     verbose: bool,
@@ -28,15 +41,57 @@ struct Cli {
     #[arg(long = "test")]
     test_filter: Option<String>,
 
-    /// Output format
-    #[arg(long = "output-format", value_enum, default_value = "text")]
-    output_format: OutputFormat,
+    /// Output format [default: text]
+    #[arg(long = "output-format", value_enum)]
+    output_format: Option<OutputFormat>,
 
     /// Watch mode: re-run tests when files change
     #[arg(long, short)]
     watch: bool,
+
+    /// Save per-RUN timings as a named baseline in .hone/baselines/
+    #[arg(long, value_name = "NAME", value_parser = parse_baseline_name)]
+    save_baseline: Option<String>,
+
+    /// Compare per-RUN timings against a saved baseline
+    #[arg(long, value_name = "NAME", value_parser = parse_baseline_name)]
+    baseline: Option<String>,
+
+    /// Fail RUNs that are more than this much slower than the baseline (e.g. 20%)
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percentage, requires = "baseline")]
+    max_regression: Option<f64>,
+
+    /// Run the tests N times and report pass/fail counts per TEST
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    repeat: Option<u64>,
+
+    /// Repeat the tests until an iteration fails (at most --repeat times if given)
+    #[arg(long)]
+    until_failure: bool,
+
+    /// Fail if any TEST is marked ONLY, so a focused run can't slip into CI
+    #[arg(long)]
+    forbid_only: bool,
+
+    /// Run only tests whose tags match (e.g. "smoke and not slow"); repeatable
+    #[arg(long = "tag", value_name = "EXPR")]
+    tags: Vec<String>,
+
+    /// Leave out tests whose tags match; repeatable
+    #[arg(long = "exclude-tag", value_name = "EXPR")]
+    exclude_tags: Vec<String>,
+
+    /// Set an environment variable for every test, over pragmas; repeatable
+    #[arg(long = "env", value_name = "KEY=VAL", value_parser = parse_env_assignment)]
+    env: Vec<(String, String)>,
+
+    /// Pass host environment variables matching a glob (e.g. CI, AWS_*); repeatable
+    #[arg(long = "pass-env", value_name = "PATTERN", value_parser = parse_env_pattern)]
+    pass_env: Vec<String>,
 }
 
+// Parsed once at startup, so the size of `Run` doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Run tests (default command)
@@ -48,6 +103,15 @@ enum Commands {
         #[arg(long)]
         shell: Option<String>,
 
+        /// Run every file once in each of these shells (e.g. bash,zsh,dash)
+        #[arg(
+            long,
+            value_name = "SHELLS",
+            value_delimiter = ',',
+            conflicts_with = "shell"
+        )]
+        shell_matrix: Option<Vec<String>>,
+
         /// Enable verbose output
         #[arg(long, short)]
         verbose: bool,
@@ -56,13 +120,53 @@ enum Commands {
         #[arg(long = "test")]
         test_filter: Option<String>,
 
-        /// Output format
-        #[arg(long = "output-format", value_enum, default_value = "text")]
-        output_format: OutputFormat,
+        /// Output format [default: text]
+        #[arg(long = "output-format", value_enum)]
+        output_format: Option<OutputFormat>,
 
         /// Watch mode: re-run tests when files change
         #[arg(long, short)]
         watch: bool,
+
+        /// Save per-RUN timings as a named baseline in .hone/baselines/
+        #[arg(long, value_name = "NAME", value_parser = parse_baseline_name)]
+        save_baseline: Option<String>,
+
+        /// Compare per-RUN timings against a saved baseline
+        #[arg(long, value_name = "NAME", value_parser = parse_baseline_name)]
+        baseline: Option<String>,
+
+        /// Fail RUNs that are more than this much slower than the baseline (e.g. 20%)
+        #[arg(long, value_name = "PERCENT", value_parser = parse_percentage, requires = "baseline")]
+        max_regression: Option<f64>,
+
+        /// Run the tests N times and report pass/fail counts per TEST
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        repeat: Option<u64>,
+
+        /// Repeat the tests until an iteration fails (at most --repeat times if given)
+        #[arg(long)]
+        until_failure: bool,
+
+        /// Fail if any TEST is marked ONLY, so a focused run can't slip into CI
+        #[arg(long)]
+        forbid_only: bool,
+
+        /// Run only tests whose tags match (e.g. "smoke and not slow"); repeatable
+        #[arg(long = "tag", value_name = "EXPR")]
+        tags: Vec<String>,
+
+        /// Leave out tests whose tags match; repeatable
+        #[arg(long = "exclude-tag", value_name = "EXPR")]
+        exclude_tags: Vec<String>,
+
+        /// Set an environment variable for every test, over pragmas; repeatable
+        #[arg(long = "env", value_name = "KEY=VAL", value_parser = parse_env_assignment)]
+        env: Vec<(String, String)>,
+
+        /// Pass host environment variables matching a glob (e.g. CI, AWS_*); repeatable
+        #[arg(long = "pass-env", value_name = "PATTERN", value_parser = parse_env_pattern)]
+        pass_env: Vec<String>,
     },
     /// Start the Language Server Protocol (LSP) server
     Lsp,
@@ -90,8 +194,21 @@ To remove the configuration manually:
     },
 }
 
+fn main() -> anyhow::Result<()> {
+    // A measured RUN's subshell execs hone as its rusage helper; that has to
+    // exit quickly, without starting a runtime or parsing a command line
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(RUSAGE_HELPER_ARG) {
+        report_rusage(&args[2..]);
+    }
+    if let Ok(exe) = std::env::current_exe() {
+        set_rusage_helper(exe);
+    }
+    run()
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn run() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     let skip_update_check = matches!(
@@ -130,17 +247,41 @@ async fn main() -> anyhow::Result<()> {
         Some(Commands::Run {
             patterns,
             shell,
+            shell_matrix,
             verbose,
             test_filter,
             output_format,
             watch,
+            save_baseline,
+            baseline,
+            max_regression,
+            repeat,
+            until_failure,
+            forbid_only,
+            tags,
+            exclude_tags,
+            env,
+            pass_env,
         }) => {
             let options = RunnerOptions {
                 shell,
+                shell_matrix,
                 verbose,
                 test_filter,
-                output_format,
+                output_format: OutputFormat::default(),
+                save_baseline,
+                baseline,
+                max_regression,
+                repeat: repeat.map(|n| n as usize),
+                until_failure,
+                forbid_only,
+                tags,
+                exclude_tags,
+                env,
+                pass_env,
+                project: ProjectConfig::default(),
             };
+            let (patterns, options) = with_project_config(patterns, output_format, options)?;
             if watch {
                 run_watch_mode(patterns, options).await?;
                 Ok(())
@@ -153,18 +294,50 @@ async fn main() -> anyhow::Result<()> {
         None => {
             let options = RunnerOptions {
                 shell: cli.shell,
+                shell_matrix: cli.shell_matrix,
                 verbose: cli.verbose,
                 test_filter: cli.test_filter,
-                output_format: cli.output_format,
+                output_format: OutputFormat::default(),
+                save_baseline: cli.save_baseline,
+                baseline: cli.baseline,
+                max_regression: cli.max_regression,
+                repeat: cli.repeat.map(|n| n as usize),
+                until_failure: cli.until_failure,
+                forbid_only: cli.forbid_only,
+                tags: cli.tags,
+                exclude_tags: cli.exclude_tags,
+                env: cli.env,
+                pass_env: cli.pass_env,
+                project: ProjectConfig::default(),
             };
+            let (patterns, options) =
+                with_project_config(cli.patterns, cli.output_format, options)?;
             if cli.watch {
-                run_watch_mode(cli.patterns, options).await?;
+                run_watch_mode(patterns, options).await?;
                 Ok(())
             } else {
-                let results = run_tests(cli.patterns, options).await?;
+                let results = run_tests(patterns, options).await?;
                 update::show_update_notification_if_available();
                 std::process::exit(if results.has_failures() { 1 } else { 0 });
             }
         }
     }
 }
+
+/// Loads the nearest `hone.toml` for the settings the command line left out:
+/// patterns and output format here, and what applies under pragmas per file.
+fn with_project_config(
+    patterns: Vec<String>,
+    output_format: Option<OutputFormat>,
+    mut options: RunnerOptions,
+) -> anyhow::Result<(Vec<String>, RunnerOptions)> {
+    let project = ProjectConfig::discover(&std::env::current_dir()?)?.unwrap_or_default();
+    let patterns = if patterns.is_empty() {
+        project.resolved_patterns()
+    } else {
+        patterns
+    };
+    options.output_format = output_format.or(project.output_format).unwrap_or_default();
+    options.project = project;
+    Ok((patterns, options))
+}
@@ -59,6 +59,13 @@ pub struct RegexLiteral {
     pub raw: String,
 }
 
+/// A byte string such as `hex"1f8b08"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HexLiteral {
+    pub bytes: Vec<u8>,
+    pub raw: String,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum DurationUnit {
     Milliseconds,
@@ -72,6 +79,22 @@ pub struct Duration {
     pub raw: String,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryUnit {
+    Bytes,
+    Kilobytes,
+    Megabytes,
+    Gigabytes,
+}
+
+/// A memory amount such as `50MB`. Units are powers of 1024.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemorySize {
+    pub value: f64,
+    pub unit: MemoryUnit,
+    pub raw: String,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum OutputSelector {
     Stdout,
@@ -109,6 +132,27 @@ pub enum OutputPredicate {
     },
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum BytesPredicate {
+    Equals {
+        operator: StringComparisonOperator,
+        value: HexLiteral,
+    },
+    Contains {
+        value: HexLiteral,
+    },
+    StartsWith {
+        value: HexLiteral,
+    },
+}
+
+/// Byte count comparison for `stdout.length` and `stderr.length`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LengthPredicate {
+    pub operator: ComparisonOperator,
+    pub value: u64,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ExitCodePredicate {
     pub operator: StringComparisonOperator,
@@ -121,6 +165,32 @@ pub struct DurationPredicate {
     pub value: Duration,
 }
 
+/// Statistic over the iterations of a RUN with `REPEAT`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DurationStatistic {
+    Min,
+    Median,
+    P95,
+    Max,
+}
+
+impl DurationStatistic {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DurationStatistic::Min => "min",
+            DurationStatistic::Median => "median",
+            DurationStatistic::P95 => "p95",
+            DurationStatistic::Max => "max",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryPredicate {
+    pub operator: ComparisonOperator,
+    pub value: MemorySize,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum FilePredicate {
     Exists,
@@ -134,6 +204,11 @@ pub enum FilePredicate {
         operator: StringComparisonOperator,
         value: StringLiteral,
     },
+    /// Hex-encoded SHA-256 digest of the file's bytes, compared case-insensitively
+    Sha256 {
+        operator: StringComparisonOperator,
+        value: StringLiteral,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -143,6 +218,18 @@ pub enum AssertionExpression {
         selector: OutputSelector,
         predicate: OutputPredicate,
     },
+    /// `stdout.bytes` / `stderr.bytes`: the exact bytes written, undecoded
+    Bytes {
+        target: Option<String>,
+        selector: OutputSelector,
+        predicate: BytesPredicate,
+    },
+    /// `stdout.length` / `stderr.length`: number of bytes written
+    Length {
+        target: Option<String>,
+        selector: OutputSelector,
+        predicate: LengthPredicate,
+    },
     ExitCode {
         target: Option<String>,
         predicate: ExitCodePredicate,
@@ -151,17 +238,100 @@ pub enum AssertionExpression {
         target: Option<String>,
         predicate: DurationPredicate,
     },
+    /// `duration.<statistic>` over the iterations of a RUN with `REPEAT`
+    DurationStat {
+        target: Option<String>,
+        statistic: DurationStatistic,
+        predicate: DurationPredicate,
+    },
+    /// Peak resident set size of the processes started by a RUN
+    MaxRss {
+        target: Option<String>,
+        predicate: MemoryPredicate,
+    },
+    /// User plus system CPU time consumed by a RUN
+    CpuTime {
+        target: Option<String>,
+        predicate: DurationPredicate,
+    },
+    /// `test.duration`: wall-clock time of the whole TEST block, checked once
+    /// the block has finished
+    TestDuration { predicate: DurationPredicate },
     File {
         path: StringLiteral,
         predicate: FilePredicate,
     },
+    /// `stub <command> ...`: the calls recorded by a STUB in this TEST
+    Stub {
+        command: String,
+        predicate: StubPredicate,
+    },
+    /// `http request "<METHOD> <path>" ...`: requests received by the TEST's
+    /// HTTP server
+    Http {
+        request: HttpRequestSpec,
+        predicate: HttpPredicate,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StubPredicate {
+    /// `called`: at least once
+    Called,
+    /// `called with "<args>"`: some call's arguments, joined by spaces, are exactly these
+    CalledWith { value: StringLiteral },
+    /// `called with /<regex>/`: some call's arguments, joined by spaces, match
+    CalledMatching { value: RegexLiteral },
+    /// `call_count <op> <n>`
+    CallCount {
+        operator: ComparisonOperator,
+        value: u64,
+    },
+    /// `stdin <predicate>`: what the most recent call read from stdin
+    Stdin(OutputPredicate),
+}
+
+/// A method and path, as in `"POST /v1/items"`. Without a `?` the path
+/// matches any query string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequestSpec {
+    pub method: String,
+    pub path: String,
+    pub raw: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpPredicate {
+    /// `received`: at least once
+    Received,
+    /// `count <op> <n>`
+    Count {
+        operator: ComparisonOperator,
+        value: u64,
+    },
+    /// `body <predicate>`: the body of the most recent matching request
+    Body(OutputPredicate),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum PragmaType {
     Shell,
+    /// `shells`: run the file once in each listed shell
+    Shells,
     Env,
     Timeout,
+    Budget,
+    MaxOutput,
+    /// `retries`: times a failed TEST is run again before it fails
+    Retries,
+    /// `require`: commands that must be on PATH, or the file's TESTs are skipped
+    Require,
+    /// `env_file`: dotenv file whose variables are added to the environment;
+    /// the value is the path resolved against the file's directory
+    EnvFile,
+    /// `hermetic`: `true` or `false`, whether the shell gets the fixed locale,
+    /// timezone, terminal size, umask and private HOME
+    Hermetic,
     Unknown,
 }
 
@@ -183,19 +353,85 @@ pub struct CommentNode {
 #[derive(Debug, Clone, PartialEq)]
 pub struct TestNode {
     pub name: String,
+    /// Set for a parameterized TEST, whose name may use `{name}` placeholders
+    pub params: Option<TestParams>,
+    /// `SKIP`, `ONLY` or `TODO` in front of `TEST`
+    pub modifier: Option<TestModifier>,
+    /// `@tag`s after the name, without the `@`
+    pub tags: Vec<String>,
     pub line: usize,
 }
 
+/// Words that `--tag` expressions use as operators, so they can't be tags.
+pub const RESERVED_TAGS: [&str; 3] = ["and", "or", "not"];
+
+/// Whether `tag` (without the `@`) is a well-formed tag name.
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && !tag.starts_with(['-', '.'])
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TestModifier {
+    /// `SKIP TEST`: reported as skipped without running
+    Skip,
+    /// `ONLY TEST`: when any TEST has it, the others are skipped
+    Only,
+    /// `TODO TEST`: a test still to be written, reported as pending
+    Todo,
+}
+
+impl TestModifier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestModifier::Skip => "SKIP",
+            TestModifier::Only => "ONLY",
+            TestModifier::Todo => "TODO",
+        }
+    }
+}
+
+/// Values a parameterized TEST runs with: `WITH fmt IN json, yaml` or
+/// `WITH fmt, ext IN (json, .json), (yaml, .yml)`. The TEST runs once per row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestParams {
+    pub names: Vec<String>,
+    /// Each row has one value per name
+    pub rows: Vec<Vec<String>>,
+}
+
+impl TestParams {
+    /// Fills each `{name}` placeholder in `template` with its value from `row`.
+    pub fn expand_name(&self, template: &str, row: &[String]) -> String {
+        self.names
+            .iter()
+            .zip(row)
+            .fold(template.to_string(), |name, (param, value)| {
+                name.replace(&format!("{{{}}}", param), value)
+            })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct RunNode {
     pub name: Option<String>,
     pub command: String,
+    /// Inline `TIMEOUT <duration>` override for this RUN only
+    pub timeout: Option<Duration>,
+    /// `REPEAT <count>`: run the command this many times and record duration statistics
+    pub repeat: Option<u32>,
     pub line: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct AssertNode {
     pub expression: AssertionExpression,
+    /// `PARTIAL`: the assertion may check output that was cut off at the
+    /// `max_output` limit
+    pub partial: bool,
     pub line: usize,
     pub raw: String,
 }
@@ -207,6 +443,100 @@ pub struct EnvNode {
     pub line: usize,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeoutNode {
+    pub duration: Duration,
+    pub line: usize,
+}
+
+/// `RETRY <count>`: times the enclosing TEST is run again after failing,
+/// overriding the `retries` pragma
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryNode {
+    pub count: u32,
+    pub line: usize,
+}
+
+/// `SKIP IF <condition>`: skips the enclosing TEST when the condition holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipIfNode {
+    pub condition: SkipCondition,
+    /// The condition as written, after `SKIP IF`
+    pub raw: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipCondition {
+    /// `missing <command>`: the command is not on PATH
+    Missing(String),
+    /// `unset <VAR>`
+    Unset(String),
+    /// `env <VAR> == <value>` or `!=`
+    Env {
+        name: String,
+        operator: StringComparisonOperator,
+        value: String,
+    },
+    /// `uname == <value>` or `!=`, compared with `uname -s`
+    Uname {
+        operator: StringComparisonOperator,
+        value: String,
+    },
+}
+
+/// `IMPORT "<path>"`: statements shared from another file, resolved relative
+/// to the importing file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportNode {
+    /// The path as written
+    pub path: String,
+    /// The path joined onto the importing file's directory; nodes below carry
+    /// line numbers from this file
+    pub resolved: String,
+    pub nodes: Vec<ASTNode>,
+    pub line: usize,
+}
+
+/// `STUB <command> ...`: puts a script named `command` first on PATH for the
+/// rest of the TEST, recording the arguments and stdin of every call
+#[derive(Debug, Clone, PartialEq)]
+pub struct StubNode {
+    pub command: String,
+    pub behavior: StubBehavior,
+    /// The statement as written, after `STUB`
+    pub raw: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StubBehavior {
+    /// `STDOUT`, `STDERR` and `EXIT` clauses; output not given is empty and the
+    /// exit code defaults to 0
+    Canned {
+        stdout: String,
+        stderr: String,
+        exit_code: i32,
+    },
+    /// `SCRIPT "<shell code>"`, run by `/bin/sh` with the call's arguments and stdin
+    Script(String),
+}
+
+/// `HTTP <METHOD> <path> ...`: a route of the HTTP server the TEST gets at
+/// `$HONE_HTTP_URL`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpNode {
+    pub request: HttpRequestSpec,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// From `BODY "<text>"`, or the contents of the fixture named by
+    /// `FILE "<path>"`, read relative to the file the statement is in
+    pub body: Vec<u8>,
+    /// The statement as written, after `HTTP`
+    pub raw: String,
+    pub line: usize,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ErrorNode {
     pub message: String,
@@ -222,6 +552,12 @@ pub enum ASTNode {
     Run(RunNode),
     Assert(AssertNode),
     Env(EnvNode),
+    Timeout(TimeoutNode),
+    Retry(RetryNode),
+    SkipIf(SkipIfNode),
+    Import(ImportNode),
+    Stub(StubNode),
+    Http(HttpNode),
     Error(ErrorNode),
 }
 
@@ -234,6 +570,12 @@ impl ASTNode {
             ASTNode::Run(node) => node.line,
             ASTNode::Assert(node) => node.line,
             ASTNode::Env(node) => node.line,
+            ASTNode::Timeout(node) => node.line,
+            ASTNode::Retry(node) => node.line,
+            ASTNode::SkipIf(node) => node.line,
+            ASTNode::Import(node) => node.line,
+            ASTNode::Stub(node) => node.line,
+            ASTNode::Http(node) => node.line,
             ASTNode::Error(node) => node.span.start_line,
         }
     }
@@ -0,0 +1,108 @@
+use crate::parser::lexer::parse_string_literal;
+use std::path::Path;
+
+/// Reads a dotenv file for the `#! env_file:` pragma. Errors name the file
+/// and line.
+pub fn load_env_file(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read env file {}: {}", path.display(), e))?;
+    parse_dotenv(&content).map_err(|(line, message)| {
+        format!("Invalid env file {}:{}: {}", path.display(), line, message)
+    })
+}
+
+/// Parses `KEY=value` lines. Blank lines and `#` comments are skipped, an
+/// `export ` prefix is allowed, and values may be quoted like hone strings.
+/// Values are literal: `$VAR` is not expanded.
+pub fn parse_dotenv(content: &str) -> Result<Vec<(String, String)>, (usize, String)> {
+    let mut vars = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let assignment = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+        let Some((key, value)) = assignment.split_once('=') else {
+            return Err((line_number, format!("expected KEY=value, got: {}", trimmed)));
+        };
+        let key = key.trim();
+        if !super::parse::is_valid_env_key(key) {
+            return Err((
+                line_number,
+                format!("invalid environment variable name: \"{}\"", key),
+            ));
+        }
+
+        vars.push((key.to_string(), parse_value(value.trim(), line_number)?));
+    }
+
+    Ok(vars)
+}
+
+fn parse_value(value: &str, line_number: usize) -> Result<String, (usize, String)> {
+    if !value.starts_with('"') && !value.starts_with('\'') {
+        // An unquoted value ends where a ` #` comment starts
+        let value = value.split(" #").next().unwrap_or_default();
+        return Ok(value.trim_end().to_string());
+    }
+
+    match parse_string_literal(value, 0) {
+        Some((literal, end)) => {
+            let rest = value[end..].trim_start();
+            if rest.is_empty() || rest.starts_with('#') {
+                Ok(literal.value)
+            } else {
+                Err((
+                    line_number,
+                    format!("unexpected text after quoted value: {}", rest),
+                ))
+            }
+        }
+        None => Err((line_number, format!("unterminated quoted value: {}", value))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dotenv() {
+        let content = r#"
+# test settings
+CI=true
+export API_URL = http://localhost:8080 # local stub
+GREETING="hello\nworld"
+LITERAL='$HOME stays'
+EMPTY=
+"#;
+        let vars = parse_dotenv(content).unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("CI".to_string(), "true".to_string()),
+                ("API_URL".to_string(), "http://localhost:8080".to_string()),
+                ("GREETING".to_string(), "hello\nworld".to_string()),
+                ("LITERAL".to_string(), "$HOME stays".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dotenv_errors() {
+        assert_eq!(parse_dotenv("A=1\nnot a var\n").unwrap_err().0, 2);
+        assert!(parse_dotenv("1BAD=x").unwrap_err().1.contains("\"1BAD\""));
+        assert!(parse_dotenv("A=\"open")
+            .unwrap_err()
+            .1
+            .contains("unterminated"));
+        assert!(parse_dotenv("A=\"x\" y")
+            .unwrap_err()
+            .1
+            .contains("unexpected text"));
+    }
+}
@@ -31,6 +31,13 @@ impl ParseErrorCollector {
         });
     }
 
+    /// Adds errors and warnings that were collected for another file, such as
+    /// an imported one, keeping their filenames and lines.
+    pub fn extend(&mut self, errors: Vec<ParseErrorDetail>, warnings: Vec<ParseWarning>) {
+        self.errors.extend(errors);
+        self.warnings.extend(warnings);
+    }
+
     pub fn has_errors(&self) -> bool {
         !self.errors.is_empty()
     }
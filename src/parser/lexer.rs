@@ -1,5 +1,6 @@
 use crate::parser::ast::{
-    ComparisonOperator, Duration, DurationUnit, QuoteType, RegexLiteral, StringLiteral,
+    ComparisonOperator, Duration, DurationUnit, HexLiteral, MemorySize, MemoryUnit, QuoteType,
+    RegexLiteral, StringLiteral,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -10,6 +11,12 @@ pub enum TokenType {
     Run,
     Assert,
     Env,
+    Timeout,
+    Retry,
+    SkipIf,
+    Import,
+    Stub,
+    Http,
     Empty,
     Unknown,
     Error,
@@ -49,7 +56,11 @@ pub fn classify_line(line: &str, line_number: usize) -> Token {
         };
     }
 
-    if trimmed.starts_with("TEST ") {
+    if trimmed.starts_with("TEST ")
+        || ["SKIP TEST ", "ONLY TEST ", "TODO TEST "]
+            .iter()
+            .any(|prefix| trimmed.starts_with(prefix))
+    {
         return Token {
             token_type: TokenType::Test,
             content: trimmed.to_string(),
@@ -81,6 +92,54 @@ pub fn classify_line(line: &str, line_number: usize) -> Token {
         };
     }
 
+    if trimmed.starts_with("TIMEOUT ") {
+        return Token {
+            token_type: TokenType::Timeout,
+            content: trimmed.to_string(),
+            line: line_number,
+        };
+    }
+
+    if trimmed.starts_with("SKIP IF ") {
+        return Token {
+            token_type: TokenType::SkipIf,
+            content: trimmed.to_string(),
+            line: line_number,
+        };
+    }
+
+    if trimmed.starts_with("IMPORT ") {
+        return Token {
+            token_type: TokenType::Import,
+            content: trimmed.to_string(),
+            line: line_number,
+        };
+    }
+
+    if trimmed.starts_with("STUB ") {
+        return Token {
+            token_type: TokenType::Stub,
+            content: trimmed.to_string(),
+            line: line_number,
+        };
+    }
+
+    if trimmed.starts_with("HTTP ") {
+        return Token {
+            token_type: TokenType::Http,
+            content: trimmed.to_string(),
+            line: line_number,
+        };
+    }
+
+    if trimmed.starts_with("RETRY ") {
+        return Token {
+            token_type: TokenType::Retry,
+            content: trimmed.to_string(),
+            line: line_number,
+        };
+    }
+
     Token {
         token_type: TokenType::Unknown,
         content: trimmed.to_string(),
@@ -275,6 +334,96 @@ pub fn parse_duration(input: &str, start_byte_index: usize) -> Option<(Duration,
     ))
 }
 
+pub fn parse_memory_size(input: &str, start_byte_index: usize) -> Option<(MemorySize, usize)> {
+    let remaining = input.get(start_byte_index..)?;
+    let leading = remaining.len() - remaining.trim_start_matches(' ').len();
+    let mut byte_offset = leading;
+
+    for ch in remaining[byte_offset..].chars() {
+        if !ch.is_ascii_digit() && ch != '.' {
+            break;
+        }
+        byte_offset += ch.len_utf8();
+    }
+
+    if byte_offset == leading {
+        return None;
+    }
+
+    let value = remaining[leading..byte_offset].parse::<f64>().ok()?;
+    if !value.is_finite() {
+        return None;
+    }
+
+    let unit_start = byte_offset;
+    for ch in remaining[byte_offset..].chars() {
+        if !ch.is_ascii_uppercase() {
+            break;
+        }
+        byte_offset += ch.len_utf8();
+    }
+
+    let unit = match &remaining[unit_start..byte_offset] {
+        "B" => MemoryUnit::Bytes,
+        "KB" => MemoryUnit::Kilobytes,
+        "MB" => MemoryUnit::Megabytes,
+        "GB" => MemoryUnit::Gigabytes,
+        _ => return None,
+    };
+
+    let raw = remaining[leading..byte_offset].to_string();
+
+    Some((
+        MemorySize { value, unit, raw },
+        start_byte_index + byte_offset,
+    ))
+}
+
+/// Parses `hex"..."`. Pairs of hex digits may be separated by spaces.
+pub fn parse_hex_literal(input: &str, start_byte_index: usize) -> Option<(HexLiteral, usize)> {
+    let remaining = input.get(start_byte_index..)?;
+    let body = remaining.strip_prefix("hex\"")?;
+    let close = body.find('"')?;
+    let digits: String = body[..close].chars().filter(|c| *c != ' ').collect();
+
+    if digits.is_empty()
+        || !digits.len().is_multiple_of(2)
+        || !digits.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+
+    let bytes = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .ok()?;
+    let end = 4 + close + 1; // hex" + digits + closing quote
+
+    Some((
+        HexLiteral {
+            bytes,
+            raw: remaining[..end].to_string(),
+        },
+        start_byte_index + end,
+    ))
+}
+
+/// Parses a non-negative integer such as a byte count, skipping leading spaces.
+pub fn parse_unsigned(input: &str, start_byte_index: usize) -> Option<(u64, usize)> {
+    let remaining = input.get(start_byte_index..)?;
+    let leading = remaining.len() - remaining.trim_start_matches(' ').len();
+    let digits = remaining[leading..]
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(remaining.len() - leading);
+    if digits == 0 {
+        return None;
+    }
+
+    let value = remaining[leading..leading + digits].parse::<u64>().ok()?;
+    Some((value, start_byte_index + leading + digits))
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ParseNumberResult {
     Success(i32, usize),
@@ -445,6 +594,46 @@ mod tests {
         assert_eq!(classify_line("ENV FOO=bar", 1).token_type, TokenType::Env);
     }
 
+    #[test]
+    fn test_classify_line_timeout() {
+        assert_eq!(
+            classify_line("TIMEOUT 5s", 1).token_type,
+            TokenType::Timeout
+        );
+    }
+
+    #[test]
+    fn test_classify_line_test_modifiers() {
+        for line in ["SKIP TEST \"a\"", "ONLY TEST \"a\"", "TODO TEST \"a\""] {
+            assert_eq!(classify_line(line, 1).token_type, TokenType::Test);
+        }
+        assert_eq!(
+            classify_line("SKIPTEST \"a\"", 1).token_type,
+            TokenType::Unknown
+        );
+    }
+
+    #[test]
+    fn test_classify_line_retry() {
+        assert_eq!(classify_line("RETRY 3", 1).token_type, TokenType::Retry);
+        assert_eq!(
+            classify_line("SKIP IF missing docker", 1).token_type,
+            TokenType::SkipIf
+        );
+        assert_eq!(
+            classify_line("IMPORT \"common/setup.hone\"", 1).token_type,
+            TokenType::Import
+        );
+        assert_eq!(
+            classify_line("STUB kubectl EXIT 1", 1).token_type,
+            TokenType::Stub
+        );
+        assert_eq!(
+            classify_line("HTTP GET /v1/items STATUS 200", 1).token_type,
+            TokenType::Http
+        );
+    }
+
     #[test]
     fn test_classify_line_unknown() {
         assert_eq!(
@@ -623,6 +812,36 @@ mod tests {
         assert!(parse_regex_literal("/pattern\\", 0).is_none());
     }
 
+    #[test]
+    fn test_parse_memory_size_units() {
+        let (size, end) = parse_memory_size("50MB", 0).unwrap();
+        assert_eq!(size.value, 50.0);
+        assert_eq!(size.unit, MemoryUnit::Megabytes);
+        assert_eq!(size.raw, "50MB");
+        assert_eq!(end, 4);
+
+        let (size, _) = parse_memory_size(" 1.5GB", 0).unwrap();
+        assert_eq!(size.unit, MemoryUnit::Gigabytes);
+        assert_eq!(size.raw, "1.5GB");
+
+        assert_eq!(
+            parse_memory_size("512KB", 0).unwrap().0.unit,
+            MemoryUnit::Kilobytes
+        );
+        assert_eq!(
+            parse_memory_size("64B", 0).unwrap().0.unit,
+            MemoryUnit::Bytes
+        );
+    }
+
+    #[test]
+    fn test_parse_memory_size_invalid() {
+        assert!(parse_memory_size("50", 0).is_none());
+        assert!(parse_memory_size("50mb", 0).is_none());
+        assert!(parse_memory_size("50TB", 0).is_none());
+        assert!(parse_memory_size("MB", 0).is_none());
+    }
+
     #[test]
     fn test_parse_duration_milliseconds() {
         let result = parse_duration("200ms", 0);
@@ -1107,4 +1326,32 @@ mod tests {
         // Verify parse_number also handles bare minus correctly
         assert!(parse_number("-", 0).is_none());
     }
+
+    #[test]
+    fn test_parse_hex_literal() {
+        let (hex, end) = parse_hex_literal("hex\"1f8b08\" rest", 0).unwrap();
+        assert_eq!(hex.bytes, vec![0x1f, 0x8b, 0x08]);
+        assert_eq!(hex.raw, "hex\"1f8b08\"");
+        assert_eq!(end, 11);
+
+        let (hex, _) = parse_hex_literal("hex\"DE AD be ef\"", 0).unwrap();
+        assert_eq!(hex.bytes, vec![0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_parse_hex_literal_invalid() {
+        assert!(parse_hex_literal("hex\"abc\"", 0).is_none());
+        assert!(parse_hex_literal("hex\"zz\"", 0).is_none());
+        assert!(parse_hex_literal("hex\"\"", 0).is_none());
+        assert!(parse_hex_literal("hex\"1f8b", 0).is_none());
+        assert!(parse_hex_literal("\"1f8b\"", 0).is_none());
+    }
+
+    #[test]
+    fn test_parse_unsigned() {
+        assert_eq!(parse_unsigned(" 1024", 0), Some((1024, 5)));
+        assert_eq!(parse_unsigned("0", 0), Some((0, 1)));
+        assert!(parse_unsigned("-1", 0).is_none());
+        assert!(parse_unsigned("abc", 0).is_none());
+    }
 }
@@ -1,4 +1,5 @@
 pub mod ast;
+pub mod dotenv;
 pub mod errors;
 pub mod lexer;
 mod parse;
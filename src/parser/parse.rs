@@ -2,14 +2,33 @@ use crate::parser::ast::*;
 use crate::parser::errors::ParseErrorCollector;
 use crate::parser::lexer::*;
 use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 pub fn parse_file(content: &str, filename: &str) -> ParseResult {
+    // Canonical paths of the files being parsed, outermost first, so that
+    // IMPORT cycles can be reported instead of recursing forever
+    let mut importing: Vec<PathBuf> = std::fs::canonicalize(filename).into_iter().collect();
+
+    // Always return Success with error nodes embedded in the AST
+    // This enables LSP features on partial/invalid files
+    ParseResult::Success {
+        file: parse_nodes(content, filename, &mut importing, &mut HashSet::new()),
+    }
+}
+
+/// `run_names` are the RUNs named so far in the current TEST. An imported
+/// file shares the importer's, so each can refer to the other's named RUNs.
+fn parse_nodes(
+    content: &str,
+    filename: &str,
+    importing: &mut Vec<PathBuf>,
+    run_names: &mut HashSet<String>,
+) -> ParsedFile {
     let lines: Vec<&str> = content.lines().collect();
     let mut collector = ParseErrorCollector::new(filename.to_string());
     let mut pragmas: Vec<PragmaNode> = Vec::new();
     let mut nodes: Vec<ASTNode> = Vec::new();
-    let mut run_names: HashSet<String> = HashSet::new();
 
     let mut in_pragma_section = true;
 
@@ -38,7 +57,9 @@ pub fn parse_file(content: &str, filename: &str) -> ParseResult {
                     continue;
                 }
 
-                if let Some(pragma) = parse_pragma(&token.content, line_number, &mut collector) {
+                if let Some(pragma) =
+                    parse_pragma(&token.content, line_number, filename, &mut collector)
+                {
                     pragmas.push(pragma.clone());
                     nodes.push(ASTNode::Pragma(pragma));
                 }
@@ -54,8 +75,7 @@ pub fn parse_file(content: &str, filename: &str) -> ParseResult {
 
             TokenType::Run => {
                 in_pragma_section = false;
-                if let Some(run) =
-                    parse_run(&token.content, line_number, &mut collector, &mut run_names)
+                if let Some(run) = parse_run(&token.content, line_number, &mut collector, run_names)
                 {
                     nodes.push(ASTNode::Run(run));
                 }
@@ -63,7 +83,9 @@ pub fn parse_file(content: &str, filename: &str) -> ParseResult {
 
             TokenType::Assert => {
                 in_pragma_section = false;
-                if let Some(assert) = parse_assert(&token.content, line_number, &mut collector) {
+                if let Some(assert) =
+                    parse_assert(&token.content, line_number, &mut collector, run_names)
+                {
                     nodes.push(ASTNode::Assert(assert));
                 }
             }
@@ -75,6 +97,60 @@ pub fn parse_file(content: &str, filename: &str) -> ParseResult {
                 }
             }
 
+            TokenType::Timeout => {
+                in_pragma_section = false;
+                if let Some(timeout) = parse_timeout(&token.content, line_number, &mut collector) {
+                    nodes.push(ASTNode::Timeout(timeout));
+                }
+            }
+
+            TokenType::Retry => {
+                in_pragma_section = false;
+                if let Some(retry) = parse_retry(&token.content, line_number, &mut collector) {
+                    nodes.push(ASTNode::Retry(retry));
+                }
+            }
+
+            TokenType::SkipIf => {
+                in_pragma_section = false;
+                if let Some(skip_if) = parse_skip_if(&token.content, line_number, &mut collector) {
+                    nodes.push(ASTNode::SkipIf(skip_if));
+                }
+            }
+
+            TokenType::Stub => {
+                in_pragma_section = false;
+                if let Some(stub) = parse_stub(&token.content, line_number, &mut collector) {
+                    nodes.push(ASTNode::Stub(stub));
+                }
+            }
+
+            TokenType::Http => {
+                in_pragma_section = false;
+                if let Some(http) =
+                    parse_http(&token.content, line_number, filename, &mut collector)
+                {
+                    nodes.push(ASTNode::Http(http));
+                }
+            }
+
+            TokenType::Import => {
+                if let Some(import) = parse_import(
+                    &token.content,
+                    line_number,
+                    filename,
+                    &mut collector,
+                    importing,
+                    run_names,
+                ) {
+                    pragmas.extend(import.nodes.iter().filter_map(|node| match node {
+                        ASTNode::Pragma(pragma) => Some(pragma.clone()),
+                        _ => None,
+                    }));
+                    nodes.push(ASTNode::Import(import));
+                }
+            }
+
             TokenType::Unknown => {
                 in_pragma_section = false;
                 let span = Span::single_line(line_number, 0, line.len());
@@ -99,22 +175,109 @@ pub fn parse_file(content: &str, filename: &str) -> ParseResult {
         }
     }
 
-    // Always return Success with error nodes embedded in the AST
-    // This enables LSP features on partial/invalid files
-    ParseResult::Success {
-        file: ParsedFile {
-            filename: filename.to_string(),
-            pragmas,
-            nodes,
-            warnings: collector.get_warnings(),
-            errors: collector.get_errors(),
-        },
+    ParsedFile {
+        filename: filename.to_string(),
+        pragmas,
+        nodes,
+        warnings: collector.get_warnings(),
+        errors: collector.get_errors(),
+    }
+}
+
+fn parse_import(
+    content: &str,
+    line: usize,
+    filename: &str,
+    collector: &mut ParseErrorCollector,
+    importing: &mut Vec<PathBuf>,
+    run_names: &mut HashSet<String>,
+) -> Option<ImportNode> {
+    // IMPORT <path>
+    let path = match parse_param_value(&content[7..]) {
+        // After "IMPORT "
+        Ok(path) => path,
+        Err(_) => {
+            collector.add_error(
+                format!("Invalid IMPORT path: {}", content[7..].trim()),
+                line,
+            );
+            return None;
+        }
+    };
+
+    let resolved = Path::new(filename)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(&path);
+    let resolved_name = resolved.to_string_lossy().to_string();
+
+    let content = match std::fs::read_to_string(&resolved) {
+        Ok(content) => content,
+        Err(e) => {
+            collector.add_error(format!("Cannot read imported file {}: {}", path, e), line);
+            return None;
+        }
+    };
+
+    let canonical = std::fs::canonicalize(&resolved).unwrap_or_else(|_| resolved.clone());
+    if let Some(start) = importing.iter().position(|p| *p == canonical) {
+        let cycle: Vec<String> = importing[start..]
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| display_name(p))
+            .collect();
+        collector.add_error(format!("Import cycle: {}", cycle.join(" -> ")), line);
+        return None;
+    }
+
+    importing.push(canonical);
+    let imported = parse_nodes(&content, &resolved_name, importing, run_names);
+    importing.pop();
+
+    let mut errors = imported.errors;
+    let mut nodes = Vec::with_capacity(imported.nodes.len());
+    for node in imported.nodes {
+        match node {
+            ASTNode::Test(test) => errors.push(ParseErrorDetail {
+                message: "TEST blocks can't be imported. IMPORT shares statements, so put them before a TEST or in the importing file".to_string(),
+                line: test.line,
+                filename: resolved_name.clone(),
+            }),
+            node => nodes.push(node),
+        }
+    }
+
+    if !errors.is_empty() {
+        collector.add_error(
+            format!(
+                "Imported file {} has {} error{}",
+                path,
+                errors.len(),
+                if errors.len() == 1 { "" } else { "s" }
+            ),
+            line,
+        );
     }
+    collector.extend(errors, imported.warnings);
+
+    Some(ImportNode {
+        path,
+        resolved: resolved_name,
+        nodes,
+        line,
+    })
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
 }
 
 fn parse_pragma(
     content: &str,
     line: usize,
+    filename: &str,
     collector: &mut ParseErrorCollector,
 ) -> Option<PragmaNode> {
     // Remove #! prefix
@@ -149,6 +312,28 @@ fn parse_pragma(
             raw: content.to_string(),
         }),
 
+        "shells" => {
+            let shells: Vec<&str> = pragma_value.split(',').map(str::trim).collect();
+            if shells.iter().any(|shell| shell.is_empty()) {
+                collector.add_error(
+                    format!(
+                        "Invalid shells pragma: {}. Expected a comma-separated list such as bash, zsh, dash",
+                        pragma_value
+                    ),
+                    line,
+                );
+                return None;
+            }
+
+            Some(PragmaNode {
+                pragma_type: PragmaType::Shells,
+                key: None,
+                value: shells.join(", "),
+                line,
+                raw: content.to_string(),
+            })
+        }
+
         "env" => {
             // Parse KEY=value
             let Some(eq_index) = pragma_value.find('=') else {
@@ -191,27 +376,136 @@ fn parse_pragma(
         }
 
         "timeout" => {
-            // Validate timeout format
-            let Some((duration, _)) = parse_duration(pragma_value, 0) else {
+            parse_timeout_value(pragma_value, line, collector)?;
+
+            Some(PragmaNode {
+                pragma_type: PragmaType::Timeout,
+                key: None,
+                value: pragma_value.to_string(),
+                line,
+                raw: content.to_string(),
+            })
+        }
+
+        "budget" => {
+            let is_valid = matches!(
+                parse_duration(pragma_value, 0),
+                Some((_, end)) if pragma_value[end..].trim().is_empty()
+            );
+            if !is_valid {
                 collector.add_error(
                     format!(
-                        "Invalid timeout format: {}. Expected format: <number>s or <number>ms",
+                        "Invalid budget format: {}. Expected format: <number>s or <number>ms",
                         pragma_value
                     ),
                     line,
                 );
                 return None;
-            };
+            }
+
+            Some(PragmaNode {
+                pragma_type: PragmaType::Budget,
+                key: None,
+                value: pragma_value.to_string(),
+                line,
+                raw: content.to_string(),
+            })
+        }
+
+        "max_output" => {
+            let is_valid = matches!(
+                parse_memory_size(pragma_value, 0),
+                Some((size, end)) if size.value > 0.0 && pragma_value[end..].trim().is_empty()
+            );
+            if !is_valid {
+                collector.add_error(
+                    format!(
+                        "Invalid max_output size: {}. Expected format: <number>B, KB, MB or GB",
+                        pragma_value
+                    ),
+                    line,
+                );
+                return None;
+            }
+
+            Some(PragmaNode {
+                pragma_type: PragmaType::MaxOutput,
+                key: None,
+                value: pragma_value.to_string(),
+                line,
+                raw: content.to_string(),
+            })
+        }
+
+        "retries" => {
+            parse_retry_count(pragma_value, "retries", line, collector)?;
+
+            Some(PragmaNode {
+                pragma_type: PragmaType::Retries,
+                key: None,
+                value: pragma_value.to_string(),
+                line,
+                raw: content.to_string(),
+            })
+        }
+
+        "require" => {
+            let commands: Vec<&str> = pragma_value.split(',').map(str::trim).collect();
+            if commands
+                .iter()
+                .any(|command| command.is_empty() || command.contains(char::is_whitespace))
+            {
+                collector.add_error(
+                    format!(
+                        "Invalid require pragma: {}. Expected a comma-separated list of commands such as git, jq",
+                        pragma_value
+                    ),
+                    line,
+                );
+                return None;
+            }
+
+            Some(PragmaNode {
+                pragma_type: PragmaType::Require,
+                key: None,
+                value: commands.join(", "),
+                line,
+                raw: content.to_string(),
+            })
+        }
 
-            // Convert to milliseconds and validate minimum
-            let ms_value = match duration.unit {
-                crate::parser::ast::DurationUnit::Seconds => duration.value * 1000.0,
-                crate::parser::ast::DurationUnit::Milliseconds => duration.value,
+        "env_file" => {
+            let path = match parse_param_value(pragma_value) {
+                Ok(path) => path,
+                Err(_) => {
+                    collector.add_error(format!("Invalid env_file path: {}", pragma_value), line);
+                    return None;
+                }
             };
-            if ms_value < 1.0 {
+            // Resolved here so it stays relative to this file when imported
+            let resolved = Path::new(filename)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(&path);
+            if let Err(e) = crate::parser::dotenv::load_env_file(&resolved) {
+                collector.add_error(e, line);
+                return None;
+            }
+
+            Some(PragmaNode {
+                pragma_type: PragmaType::EnvFile,
+                key: None,
+                value: resolved.to_string_lossy().into_owned(),
+                line,
+                raw: content.to_string(),
+            })
+        }
+
+        "hermetic" => {
+            if !matches!(pragma_value, "true" | "false") {
                 collector.add_error(
                     format!(
-                        "Timeout value too small: {}. Minimum timeout is 1ms",
+                        "Invalid hermetic pragma: {}. Expected true or false",
                         pragma_value
                     ),
                     line,
@@ -220,7 +514,7 @@ fn parse_pragma(
             }
 
             Some(PragmaNode {
-                pragma_type: PragmaType::Timeout,
+                pragma_type: PragmaType::Hermetic,
                 key: None,
                 value: pragma_value.to_string(),
                 line,
@@ -242,432 +536,1107 @@ fn parse_pragma(
     }
 }
 
-fn parse_test(content: &str, line: usize, collector: &mut ParseErrorCollector) -> Option<TestNode> {
-    // TEST "name"
-    let rest = &content[5..]; // After "TEST "
-    let Some(result) = parse_string_literal(rest, 0) else {
-        collector.add_error("Expected quoted string after TEST".to_string(), line);
-        return None;
+/// Validates a timeout value shared by the `timeout` pragma, the `TIMEOUT`
+/// statement and the inline RUN override.
+fn parse_timeout_value(
+    value: &str,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<Duration> {
+    let duration = match parse_duration(value, 0) {
+        Some((duration, end)) if value[end..].trim().is_empty() => duration,
+        _ => {
+            collector.add_error(
+                format!(
+                    "Invalid timeout format: {}. Expected format: <number>s or <number>ms",
+                    value
+                ),
+                line,
+            );
+            return None;
+        }
     };
 
-    let name = result.0.value.clone();
-
-    if name.is_empty() {
-        collector.add_error("Test name cannot be empty".to_string(), line);
+    // Convert to milliseconds and validate minimum
+    let ms_value = match duration.unit {
+        DurationUnit::Seconds => duration.value * 1000.0,
+        DurationUnit::Milliseconds => duration.value,
+    };
+    if ms_value < 1.0 {
+        collector.add_error(
+            format!("Timeout value too small: {}. Minimum timeout is 1ms", value),
+            line,
+        );
         return None;
     }
 
-    Some(TestNode { name, line })
+    Some(duration)
 }
 
-fn parse_run(
+fn parse_timeout(
     content: &str,
     line: usize,
     collector: &mut ParseErrorCollector,
-    run_names: &mut HashSet<String>,
-) -> Option<RunNode> {
-    // RUN <command> or RUN <name>: <command>
-    let rest = &content[4..]; // After "RUN "
-
-    // Check for named RUN (name: command)
-    static NAMED_RUN_RE: OnceLock<regex::Regex> = OnceLock::new();
-    let re = NAMED_RUN_RE.get_or_init(|| {
-        regex::Regex::new(r"^([a-zA-Z_][a-zA-Z0-9_-]*):\s*")
-            .expect("named run regex should be valid")
-    });
-
-    if let Some(captures) = re.captures(rest) {
-        if let (Some(name_match), Some(full_match)) = (captures.get(1), captures.get(0)) {
-            let name = name_match.as_str().to_string();
-            let matched_len = full_match.as_str().len();
-            let command = rest[matched_len..].to_string();
+) -> Option<TimeoutNode> {
+    // TIMEOUT <duration>
+    let rest = content[8..].trim(); // After "TIMEOUT "
+    let duration = parse_timeout_value(rest, line, collector)?;
 
-            if run_names.contains(&name) {
-                collector.add_error(
-                    format!(
-                        "Duplicate RUN name: \"{}\". RUN names must be unique within a test",
-                        name
-                    ),
-                    line,
-                );
-                return None;
-            }
+    Some(TimeoutNode { duration, line })
+}
 
-            run_names.insert(name.clone());
+fn parse_retry(
+    content: &str,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<RetryNode> {
+    // RETRY <count>
+    let rest = content[6..].trim(); // After "RETRY "
+    let count = parse_retry_count(rest, "RETRY", line, collector)?;
 
-            if command.trim().is_empty() {
-                collector.add_error("Empty command in RUN statement".to_string(), line);
-                return None;
-            }
+    Some(RetryNode { count, line })
+}
 
-            return Some(RunNode {
-                name: Some(name),
-                command: command.trim().to_string(),
+fn parse_skip_if(
+    content: &str,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<SkipIfNode> {
+    // SKIP IF <condition>
+    let raw = content[8..].trim(); // After "SKIP IF "
+    match parse_skip_condition(raw) {
+        Some(condition) => Some(SkipIfNode {
+            condition,
+            raw: raw.to_string(),
+            line,
+        }),
+        None => {
+            collector.add_error(
+                format!(
+                    "Invalid SKIP IF condition: {}. Expected missing <command>, unset <VAR>, env <VAR> == <value> or uname == <value>",
+                    raw
+                ),
                 line,
-            });
+            );
+            None
         }
     }
+}
 
-    // Unnamed RUN
-    if rest.trim().is_empty() {
-        collector.add_error("Empty command in RUN statement".to_string(), line);
-        return None;
-    }
+fn parse_skip_condition(condition: &str) -> Option<SkipCondition> {
+    let (kind, rest) = condition.split_once(char::is_whitespace)?;
+    let rest = rest.trim();
 
-    Some(RunNode {
-        name: None,
-        command: rest.trim().to_string(),
-        line,
-    })
-}
+    // `<op> <value>` with `==` or `!=`
+    let comparison = |text: &str| -> Option<(StringComparisonOperator, String)> {
+        let (operator, value) = if let Some(value) = text.strip_prefix("==") {
+            (StringComparisonOperator::Equal, value)
+        } else if let Some(value) = text.strip_prefix("!=") {
+            (StringComparisonOperator::NotEqual, value)
+        } else {
+            return None;
+        };
+        Some((operator, parse_param_value(value).ok()?))
+    };
 
-fn parse_env(content: &str, line: usize, collector: &mut ParseErrorCollector) -> Option<EnvNode> {
-    // ENV KEY=value
-    let rest = &content[4..]; // After "ENV "
-    let Some(eq_index) = rest.find('=') else {
-        collector.add_error(
-            "Invalid ENV syntax: expected KEY=value format".to_string(),
-            line,
-        );
-        return None;
-    };
-
-    let key = rest[..eq_index].trim().to_string();
-    let value = rest[eq_index + 1..].to_string();
-
-    if key.is_empty() {
-        collector.add_error("Invalid ENV syntax: empty key".to_string(), line);
-        return None;
+    match kind {
+        "missing" if !rest.is_empty() && !rest.contains(char::is_whitespace) => {
+            Some(SkipCondition::Missing(rest.to_string()))
+        }
+        "unset" if is_valid_env_key(rest) => Some(SkipCondition::Unset(rest.to_string())),
+        "env" => {
+            let (name, comparison_text) = rest.split_once(char::is_whitespace)?;
+            if !is_valid_env_key(name) {
+                return None;
+            }
+            let (operator, value) = comparison(comparison_text.trim())?;
+            Some(SkipCondition::Env {
+                name: name.to_string(),
+                operator,
+                value,
+            })
+        }
+        "uname" => {
+            let (operator, value) = comparison(rest)?;
+            Some(SkipCondition::Uname { operator, value })
+        }
+        _ => None,
     }
+}
 
-    // Validate key format (valid environment variable name)
-    static ENV_KEY_RE: OnceLock<regex::Regex> = OnceLock::new();
-    let re = ENV_KEY_RE.get_or_init(|| {
-        regex::Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").expect("env key regex should be valid")
-    });
-
-    if !re.is_match(&key) {
+fn parse_stub(content: &str, line: usize, collector: &mut ParseErrorCollector) -> Option<StubNode> {
+    // STUB <command> [STDOUT "<text>"] [STDERR "<text>"] [EXIT <code>]
+    // STUB <command> SCRIPT "<shell code>"
+    let rest = content[5..].trim(); // After "STUB "
+    let name_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    let command = &rest[..name_end];
+    if !is_valid_stub_command(command) {
         collector.add_error(
             format!(
-                "Invalid environment variable name: \"{}\". Names must start with a letter or underscore and contain only alphanumeric characters and underscores",
-                key
+                "Invalid STUB command name: {}. Expected a command name such as kubectl, without a path",
+                command
             ),
             line,
         );
         return None;
     }
 
-    Some(EnvNode { key, value, line })
-}
+    let mut stdout: Option<String> = None;
+    let mut stderr: Option<String> = None;
+    let mut script: Option<String> = None;
+    let mut exit_code: Option<i32> = None;
+
+    let mut i = skip_whitespace(rest, name_end);
+    while i < rest.len() {
+        let Some(keyword) = ["STDOUT", "STDERR", "EXIT", "SCRIPT"]
+            .into_iter()
+            .find(|keyword| match_word(rest, i, keyword))
+        else {
+            collector.add_error(
+                format!(
+                    "Unexpected text in STUB: {}. Expected STDOUT, STDERR, EXIT or SCRIPT",
+                    &rest[i..]
+                ),
+                line,
+            );
+            return None;
+        };
+        i = skip_whitespace(rest, i + keyword.len());
 
-fn parse_assert(
-    content: &str,
-    line: usize,
-    collector: &mut ParseErrorCollector,
-) -> Option<AssertNode> {
-    // ASSERT <expression>
-    let rest = &content[7..]; // After "ASSERT "
-    let expression = parse_assertion_expression(rest, line, collector)?;
+        let duplicate = if keyword == "EXIT" {
+            match parse_number_checked(rest, i) {
+                ParseNumberResult::Success(code, end) if (0..=255).contains(&code) => {
+                    i = end;
+                    exit_code.replace(code).is_some()
+                }
+                _ => {
+                    collector.add_error(
+                        "Invalid STUB exit code. Expected a whole number from 0 to 255".to_string(),
+                        line,
+                    );
+                    return None;
+                }
+            }
+        } else {
+            let Some((value, end)) = parse_string_literal(rest, i) else {
+                collector.add_error(format!("Expected quoted string after {}", keyword), line);
+                return None;
+            };
+            i = end;
+            let slot = match keyword {
+                "STDOUT" => &mut stdout,
+                "STDERR" => &mut stderr,
+                _ => &mut script,
+            };
+            slot.replace(value.value).is_some()
+        };
 
-    Some(AssertNode {
-        expression,
+        if duplicate {
+            collector.add_error(format!("{} may only be given once per STUB", keyword), line);
+            return None;
+        }
+        i = skip_whitespace(rest, i);
+    }
+
+    let behavior = match script {
+        Some(_) if stdout.is_some() || stderr.is_some() || exit_code.is_some() => {
+            collector.add_error(
+                "SCRIPT can't be combined with STDOUT, STDERR or EXIT".to_string(),
+                line,
+            );
+            return None;
+        }
+        Some(script) => StubBehavior::Script(script),
+        None => StubBehavior::Canned {
+            stdout: stdout.unwrap_or_default(),
+            stderr: stderr.unwrap_or_default(),
+            exit_code: exit_code.unwrap_or(0),
+        },
+    };
+
+    Some(StubNode {
+        command: command.to_string(),
+        behavior,
+        raw: rest.to_string(),
         line,
-        raw: content.to_string(),
     })
 }
 
-fn parse_assertion_expression(
-    input: &str,
+/// Whether `command` can name a stub: a plain file name, not a path.
+fn is_valid_stub_command(command: &str) -> bool {
+    static STUB_COMMAND_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = STUB_COMMAND_RE.get_or_init(|| {
+        regex::Regex::new(r"^[a-zA-Z0-9_][a-zA-Z0-9_.+-]*$")
+            .expect("stub command regex should be valid")
+    });
+    re.is_match(command)
+}
+
+/// Headers the HTTP server writes itself, so a route can't set them
+const HTTP_RESERVED_HEADERS: [&str; 3] = ["content-length", "connection", "transfer-encoding"];
+
+fn parse_http(
+    content: &str,
     line: usize,
+    filename: &str,
     collector: &mut ParseErrorCollector,
-) -> Option<AssertionExpression> {
-    let mut i = skip_whitespace(input, 0);
-
-    // Check for file assertion
-    if match_word(input, i, "file") {
-        return parse_file_assertion(input, line, collector);
-    }
+) -> Option<HttpNode> {
+    // HTTP <METHOD> <path> [STATUS <code>] [HEADER "<name>: <value>"]... [BODY "<text>" | FILE "<path>"]
+    let rest = content[5..].trim(); // After "HTTP "
+    let method_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    let path_start = skip_whitespace(rest, method_end);
+    let path_end = rest[path_start..]
+        .find(char::is_whitespace)
+        .map_or(rest.len(), |end| path_start + end);
+    let Some(request) = parse_http_request_spec(&rest[..path_end]) else {
+        collector.add_error(
+            format!(
+                "Invalid HTTP route: {}. Expected a method and path such as GET /v1/items",
+                &rest[..path_end]
+            ),
+            line,
+        );
+        return None;
+    };
 
-    // Check for stdout.raw first
-    if match_word(input, i, "stdout.raw") {
-        i += 10; // "stdout.raw"
-        return parse_output_assertion(input, i, OutputSelector::StdoutRaw, None, line, collector);
-    }
+    let mut status: Option<u16> = None;
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut body: Option<Vec<u8>> = None;
 
-    // Check for named target (e.g., build.stdout)
-    let mut target: Option<String> = None;
-    static NAMED_TARGET_RE: OnceLock<regex::Regex> = OnceLock::new();
-    let re = NAMED_TARGET_RE.get_or_init(|| {
-        regex::Regex::new(r"^([a-zA-Z_][a-zA-Z0-9_-]*)\.(.+)")
-            .expect("named target regex should be valid")
-    });
+    let mut i = skip_whitespace(rest, path_end);
+    while i < rest.len() {
+        let Some(keyword) = ["STATUS", "HEADER", "BODY", "FILE"]
+            .into_iter()
+            .find(|keyword| match_word(rest, i, keyword))
+        else {
+            collector.add_error(
+                format!(
+                    "Unexpected text in HTTP: {}. Expected STATUS, HEADER, BODY or FILE",
+                    &rest[i..]
+                ),
+                line,
+            );
+            return None;
+        };
+        i = skip_whitespace(rest, i + keyword.len());
+
+        if keyword == "STATUS" {
+            match parse_number_checked(rest, i) {
+                ParseNumberResult::Success(code, end) if (100..=599).contains(&code) => {
+                    i = end;
+                    if status.replace(code as u16).is_some() {
+                        collector
+                            .add_error("STATUS may only be given once per HTTP".to_string(), line);
+                        return None;
+                    }
+                }
+                _ => {
+                    collector.add_error(
+                        "Invalid HTTP status. Expected a whole number from 100 to 599".to_string(),
+                        line,
+                    );
+                    return None;
+                }
+            }
+            i = skip_whitespace(rest, i);
+            continue;
+        }
 
-    let mut effective_input = input;
-    if let Some(captures) = re.captures(input) {
-        if let (Some(potential_target_match), Some(remainder_match)) =
-            (captures.get(1), captures.get(2))
-        {
-            let potential_target = potential_target_match.as_str();
-            if potential_target != "stdout"
-                && potential_target != "stderr"
-                && potential_target != "exit_code"
-                && potential_target != "duration"
-            {
-                target = Some(potential_target.to_string());
-                effective_input = remainder_match.as_str();
-                i = 0;
+        let Some((value, end)) = parse_string_literal(rest, i) else {
+            collector.add_error(format!("Expected quoted string after {}", keyword), line);
+            return None;
+        };
+        i = skip_whitespace(rest, end);
+
+        match keyword {
+            "HEADER" => {
+                let header = value.value.split_once(':').and_then(|(name, value)| {
+                    is_valid_header_name(name).then(|| (name.to_string(), value.trim().to_string()))
+                });
+                let Some(header) = header else {
+                    collector.add_error(
+                        format!(
+                            "Invalid HTTP header: {}. Expected \"<name>: <value>\"",
+                            value.raw
+                        ),
+                        line,
+                    );
+                    return None;
+                };
+                if HTTP_RESERVED_HEADERS.contains(&header.0.to_ascii_lowercase().as_str()) {
+                    collector.add_error(
+                        format!(
+                            "HEADER can't set {}; hone sets it for every response",
+                            header.0
+                        ),
+                        line,
+                    );
+                    return None;
+                }
+                headers.push(header);
+            }
+            _ if body.is_some() => {
+                collector.add_error(
+                    "Only one of BODY or FILE may be given per HTTP".to_string(),
+                    line,
+                );
+                return None;
+            }
+            "BODY" => body = Some(value.value.into_bytes()),
+            _ => {
+                let resolved = Path::new(filename)
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .join(&value.value);
+                match std::fs::read(&resolved) {
+                    Ok(contents) => body = Some(contents),
+                    Err(e) => {
+                        collector.add_error(
+                            format!("Cannot read HTTP body file {}: {}", value.value, e),
+                            line,
+                        );
+                        return None;
+                    }
+                }
             }
         }
     }
 
-    // Parse selector
-    if match_word(effective_input, i, "stdout.raw") {
-        i += 10;
-        return parse_output_assertion(
-            effective_input,
-            i,
-            OutputSelector::StdoutRaw,
-            target,
-            line,
-            collector,
-        );
-    }
+    Some(HttpNode {
+        request,
+        status: status.unwrap_or(200),
+        headers,
+        body: body.unwrap_or_default(),
+        raw: rest.to_string(),
+        line,
+    })
+}
 
-    if match_word(effective_input, i, "stdout") {
-        i += 6;
-        return parse_output_assertion(
-            effective_input,
-            i,
-            OutputSelector::Stdout,
-            target,
-            line,
-            collector,
-        );
-    }
+/// Parses `<METHOD> <path>`, as in `POST /v1/items`.
+fn parse_http_request_spec(text: &str) -> Option<HttpRequestSpec> {
+    static REQUEST_SPEC_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = REQUEST_SPEC_RE.get_or_init(|| {
+        regex::Regex::new(r"^([A-Z]+)\s+(/\S*)$").expect("request spec regex should be valid")
+    });
+    let captures = re.captures(text.trim())?;
+    Some(HttpRequestSpec {
+        method: captures[1].to_string(),
+        path: captures[2].to_string(),
+        raw: text.trim().to_string(),
+    })
+}
 
-    if match_word(effective_input, i, "stderr") {
-        i += 6;
-        return parse_output_assertion(
-            effective_input,
-            i,
-            OutputSelector::Stderr,
-            target,
-            line,
-            collector,
-        );
-    }
+/// Whether `name` is an HTTP header name (an RFC 9110 token).
+fn is_valid_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
 
-    if match_word(effective_input, i, "exit_code") {
-        i += 9;
-        return parse_exit_code_assertion(effective_input, i, target, line, collector);
-    }
+/// Largest accepted retry count; a TEST failing more often than this is broken, not flaky
+const MAX_RETRIES: u32 = 10;
 
-    if match_word(effective_input, i, "duration") {
-        i += 8;
-        return parse_duration_assertion(effective_input, i, target, line, collector);
+fn parse_retry_count(
+    value: &str,
+    keyword: &str,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<u32> {
+    match value.parse::<u32>() {
+        Ok(count) if count <= MAX_RETRIES => Some(count),
+        _ => {
+            collector.add_error(
+                format!(
+                    "Invalid {} count: {}. Expected a whole number from 0 to {}",
+                    keyword, value, MAX_RETRIES
+                ),
+                line,
+            );
+            None
+        }
     }
+}
 
-    collector.add_error(format!("Unknown assertion type: {}", input), line);
-    None
+/// Largest accepted `REPEAT` count, to keep a typo from running a command for hours
+const MAX_REPEAT: u32 = 10_000;
+
+/// Trailing `TIMEOUT <duration>` and `REPEAT <count>` modifiers of a RUN.
+#[derive(Default)]
+struct RunModifiers {
+    timeout: Option<Duration>,
+    repeat: Option<u32>,
 }
 
-fn parse_output_assertion(
-    input: &str,
-    start_index: usize,
-    selector: OutputSelector,
-    target: Option<String>,
+/// Splits trailing `TIMEOUT <duration>` and `REPEAT <count>` modifiers off a RUN
+/// command. Each may appear once, in either order. Words inside quotes, or a
+/// `TIMEOUT` not followed by a duration or `REPEAT` not followed by a number,
+/// stay part of the command.
+fn split_run_modifiers<'a>(
+    command: &'a str,
     line: usize,
     collector: &mut ParseErrorCollector,
-) -> Option<AssertionExpression> {
-    let mut i = skip_whitespace(input, start_index);
+) -> Option<(&'a str, RunModifiers)> {
+    static RUN_MODIFIER_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = RUN_MODIFIER_RE.get_or_init(|| {
+        regex::Regex::new(r"\s+(TIMEOUT|REPEAT)\s+(\S+)\s*$")
+            .expect("run modifier regex should be valid")
+    });
 
-    // Parse predicate
-    if match_word(input, i, "contains") {
-        i += 8;
-        i = skip_whitespace(input, i);
+    let mut command = command;
+    let mut modifiers = RunModifiers::default();
 
-        let Some((string_lit, _)) = parse_string_literal(input, i) else {
+    while let Some(captures) = re.captures(command) {
+        let (Some(full_match), Some(keyword), Some(value)) =
+            (captures.get(0), captures.get(1), captures.get(2))
+        else {
+            break;
+        };
+
+        if in_shell_quotes(command, full_match.start()) {
+            break;
+        }
+
+        let duplicate = match keyword.as_str() {
+            "TIMEOUT" => {
+                if !matches!(parse_duration(value.as_str(), 0), Some((_, end)) if end == value.len())
+                {
+                    break;
+                }
+                let duplicate = modifiers.timeout.is_some();
+                modifiers.timeout = Some(parse_timeout_value(value.as_str(), line, collector)?);
+                duplicate
+            }
+            _ => {
+                if !value.as_str().bytes().all(|byte| byte.is_ascii_digit()) {
+                    break;
+                }
+                let duplicate = modifiers.repeat.is_some();
+                modifiers.repeat = Some(parse_repeat_count(value.as_str(), line, collector)?);
+                duplicate
+            }
+        };
+
+        if duplicate {
             collector.add_error(
-                "Expected quoted string after \"contains\"".to_string(),
+                format!("{} may only be given once per RUN", keyword.as_str()),
                 line,
             );
             return None;
-        };
+        }
 
-        return Some(AssertionExpression::Output {
-            target,
-            selector,
-            predicate: OutputPredicate::Contains { value: string_lit },
-        });
+        command = &command[..full_match.start()];
     }
 
-    if match_word(input, i, "matches") {
-        i += 7;
-        i = skip_whitespace(input, i);
-
-        let Some((regex_lit, _)) = parse_regex_literal(input, i) else {
-            collector.add_error("Expected regex literal after \"matches\"".to_string(), line);
-            return None;
-        };
+    Some((command, modifiers))
+}
 
-        return Some(AssertionExpression::Output {
-            target,
-            selector,
-            predicate: OutputPredicate::Matches { value: regex_lit },
-        });
+/// Whether byte `index` of a shell command is inside single or double quotes.
+fn in_shell_quotes(command: &str, index: usize) -> bool {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for ch in command[..index].chars() {
+        match (quote, ch) {
+            _ if escaped => escaped = false,
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (None, '\'' | '"') => quote = Some(ch),
+            _ => {}
+        }
     }
+    quote.is_some()
+}
 
-    // Check for == or !=
-    if let Some((op, end_index)) = parse_comparison_operator(input, i) {
-        if matches!(op, ComparisonOperator::Equal | ComparisonOperator::NotEqual) {
-            i = skip_whitespace(input, end_index);
-
-            let Some((string_lit, _)) = parse_string_literal(input, i) else {
-                collector.add_error(
-                    "Expected quoted string after comparison operator".to_string(),
-                    line,
-                );
-                return None;
-            };
+fn parse_repeat_count(
+    value: &str,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<u32> {
+    match value.parse::<u32>() {
+        Ok(count) if (1..=MAX_REPEAT).contains(&count) => Some(count),
+        _ => {
+            collector.add_error(
+                format!(
+                    "Invalid REPEAT count: {}. Expected a whole number from 1 to {}",
+                    value, MAX_REPEAT
+                ),
+                line,
+            );
+            None
+        }
+    }
+}
 
-            let string_op = match op {
-                ComparisonOperator::Equal => StringComparisonOperator::Equal,
-                ComparisonOperator::NotEqual => StringComparisonOperator::NotEqual,
-                _ => unreachable!(),
-            };
+fn parse_test(content: &str, line: usize, collector: &mut ParseErrorCollector) -> Option<TestNode> {
+    // [SKIP|ONLY|TODO] TEST "name"
+    let (modifier, content) = match content.split_once(' ') {
+        Some(("SKIP", rest)) => (Some(TestModifier::Skip), rest),
+        Some(("ONLY", rest)) => (Some(TestModifier::Only), rest),
+        Some(("TODO", rest)) => (Some(TestModifier::Todo), rest),
+        _ => (None, content),
+    };
+    let rest = &content[5..]; // After "TEST "
+    let Some(result) = parse_string_literal(rest, 0) else {
+        collector.add_error("Expected quoted string after TEST".to_string(), line);
+        return None;
+    };
 
-            return Some(AssertionExpression::Output {
-                target,
-                selector,
-                predicate: OutputPredicate::Equals {
-                    operator: string_op,
-                    value: string_lit,
-                },
-            });
+    let name = result.0.value.clone();
+
+    if name.is_empty() {
+        collector.add_error("Test name cannot be empty".to_string(), line);
+        return None;
+    }
+
+    let mut position = skip_whitespace(rest, result.1);
+    let mut tags: Vec<String> = Vec::new();
+    while rest[position..].starts_with('@') {
+        let end = rest[position..]
+            .find(' ')
+            .map_or(rest.len(), |offset| position + offset);
+        let tag = parse_tag(&rest[position + 1..end], line, collector)?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
         }
+        position = skip_whitespace(rest, end);
     }
 
-    let selector_str = match selector {
-        OutputSelector::Stdout => "stdout",
-        OutputSelector::StdoutRaw => "stdout.raw",
-        OutputSelector::Stderr => "stderr",
+    let params = if match_word(rest, position, "WITH") {
+        Some(parse_test_params(
+            &rest[position + 4..],
+            &name,
+            line,
+            collector,
+        )?)
+    } else if position < rest.len() {
+        // Older files may have text here that used to be ignored, so it stays a warning
+        collector.add_warning(
+            format!(
+                "Ignoring text after TEST name: {}. Tags start with @, e.g. @slow",
+                &rest[position..]
+            ),
+            line,
+        );
+        None
+    } else {
+        None
     };
 
-    collector.add_error(
-        format!(
-            "Expected predicate (contains, matches, ==, !=) after \"{}\"",
-            selector_str
-        ),
+    Some(TestNode {
+        name,
+        params,
+        modifier,
+        tags,
         line,
-    );
-    None
+    })
 }
 
-fn parse_exit_code_assertion(
-    input: &str,
-    start_index: usize,
-    target: Option<String>,
+fn parse_tag(tag: &str, line: usize, collector: &mut ParseErrorCollector) -> Option<String> {
+    if !is_valid_tag(tag) {
+        collector.add_error(
+            format!(
+                "Invalid tag: @{}. Tags use letters, digits, '-', '_' and '.'",
+                tag
+            ),
+            line,
+        );
+        return None;
+    }
+    if RESERVED_TAGS.contains(&tag) {
+        collector.add_error(
+            format!(
+                "Invalid tag: @{}. It is an operator in --tag expressions",
+                tag
+            ),
+            line,
+        );
+        return None;
+    }
+    Some(tag.to_string())
+}
+
+/// Parses the `<names> IN <rows>` part of a parameterized TEST.
+fn parse_test_params(
+    spec: &str,
+    template: &str,
     line: usize,
     collector: &mut ParseErrorCollector,
-) -> Option<AssertionExpression> {
-    let Some((op, end_index)) = parse_comparison_operator(input, start_index) else {
-        collector.add_error("Expected == or != after \"exit_code\"".to_string(), line);
+) -> Option<TestParams> {
+    let Some((names_part, rows_part)) = spec.split_once(" IN ") else {
+        collector.add_error(
+            "Expected WITH <name> IN <values>, e.g. WITH fmt IN json, yaml".to_string(),
+            line,
+        );
         return None;
     };
 
-    if !matches!(op, ComparisonOperator::Equal | ComparisonOperator::NotEqual) {
-        collector.add_error("Expected == or != after \"exit_code\"".to_string(), line);
-        return None;
+    let mut names: Vec<String> = Vec::new();
+    for name in names_part.split(',').map(str::trim) {
+        if !is_valid_env_key(name) {
+            collector.add_error(
+                format!(
+                    "Invalid parameter name: \"{}\". Parameters are set as environment variables, so names must start with a letter or underscore and contain only alphanumeric characters and underscores",
+                    name
+                ),
+                line,
+            );
+            return None;
+        }
+        if names.iter().any(|n| n == name) {
+            collector.add_error(format!("Duplicate parameter name: \"{}\"", name), line);
+            return None;
+        }
+        names.push(name.to_string());
     }
 
-    let num_value = match parse_number_checked(input, end_index) {
-        ParseNumberResult::Success(value, _) => value,
-        ParseNumberResult::Overflow => {
+    let mut rows = Vec::new();
+    for item in split_top_level(rows_part) {
+        let item = item.trim();
+        let values = match item.strip_prefix('(').and_then(|i| i.strip_suffix(')')) {
+            Some(inner) => split_top_level(inner),
+            None => vec![item],
+        };
+        let row = match values
+            .into_iter()
+            .map(parse_param_value)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(row) => row,
+            Err(message) => {
+                collector.add_error(message, line);
+                return None;
+            }
+        };
+        if row.len() != names.len() {
             collector.add_error(
-                "Exit code value is too large (must fit in i32 range)".to_string(),
+                format!(
+                    "Parameter row {} has {} value(s) but WITH declares {} parameter(s)",
+                    item,
+                    row.len(),
+                    names.len()
+                ),
                 line,
             );
             return None;
         }
-        ParseNumberResult::NotANumber => {
+        rows.push(row);
+    }
+
+    static PLACEHOLDER_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = PLACEHOLDER_RE.get_or_init(|| {
+        regex::Regex::new(r"\{([a-zA-Z_][a-zA-Z0-9_]*)\}")
+            .expect("placeholder regex should be valid")
+    });
+    for captures in re.captures_iter(template) {
+        let placeholder = &captures[1];
+        if !names.iter().any(|n| n == placeholder) {
             collector.add_error(
-                "Expected number after comparison operator".to_string(),
+                format!(
+                    "Unknown parameter {{{}}} in TEST name. Declared parameters: {}",
+                    placeholder,
+                    names.join(", ")
+                ),
                 line,
             );
             return None;
         }
-    };
+    }
 
-    let string_op = match op {
-        ComparisonOperator::Equal => StringComparisonOperator::Equal,
-        ComparisonOperator::NotEqual => StringComparisonOperator::NotEqual,
-        _ => unreachable!(),
-    };
+    let params = TestParams { names, rows };
+    let mut expanded = HashSet::new();
+    for row in &params.rows {
+        let name = params.expand_name(template, row);
+        if !expanded.insert(name.clone()) {
+            collector.add_error(
+                format!(
+                    "More than one parameter row is named \"{}\". Use the parameters in the TEST name so each row gets its own name",
+                    name
+                ),
+                line,
+            );
+            return None;
+        }
+    }
 
-    Some(AssertionExpression::ExitCode {
-        target,
-        predicate: ExitCodePredicate {
-            operator: string_op,
-            value: num_value,
-        },
-    })
+    Some(params)
 }
 
-fn parse_duration_assertion(
-    input: &str,
-    start_index: usize,
-    target: Option<String>,
+/// Splits on commas outside quotes and parentheses.
+fn split_top_level(input: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, ch) in input.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some('"') if ch == '\\' => escaped = true,
+            Some(q) if ch == q => quote = None,
+            Some(_) => {}
+            None => match ch {
+                '"' | '\'' => quote = Some(ch),
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    items.push(&input[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            },
+        }
+    }
+    items.push(&input[start..]);
+    items
+}
+
+/// A parameter value, either bare or as a quoted string literal.
+fn parse_param_value(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("Empty parameter value. Quote it to pass an empty string: \"\"".to_string());
+    }
+    if !value.starts_with('"') && !value.starts_with('\'') {
+        return Ok(value.to_string());
+    }
+    match parse_string_literal(value, 0) {
+        Some((literal, end)) if end == value.len() => Ok(literal.value),
+        _ => Err(format!("Invalid parameter value: {}", value)),
+    }
+}
+
+fn parse_run(
+    content: &str,
     line: usize,
     collector: &mut ParseErrorCollector,
-) -> Option<AssertionExpression> {
-    let Some((op, end_index)) = parse_comparison_operator(input, start_index) else {
+    run_names: &mut HashSet<String>,
+) -> Option<RunNode> {
+    // RUN <command> or RUN <name>: <command>
+    let rest = &content[4..]; // After "RUN "
+
+    // Check for named RUN (name: command)
+    static NAMED_RUN_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = NAMED_RUN_RE.get_or_init(|| {
+        regex::Regex::new(r"^([a-zA-Z_][a-zA-Z0-9_-]*):\s*")
+            .expect("named run regex should be valid")
+    });
+
+    if let Some(captures) = re.captures(rest) {
+        if let (Some(name_match), Some(full_match)) = (captures.get(1), captures.get(0)) {
+            let name = name_match.as_str().to_string();
+            let matched_len = full_match.as_str().len();
+
+            let (command, modifiers) = split_run_modifiers(&rest[matched_len..], line, collector)?;
+
+            if run_names.contains(&name) {
+                collector.add_error(
+                    format!(
+                        "Duplicate RUN name: \"{}\". RUN names must be unique within a test",
+                        name
+                    ),
+                    line,
+                );
+                return None;
+            }
+
+            run_names.insert(name.clone());
+
+            if command.trim().is_empty() {
+                collector.add_error("Empty command in RUN statement".to_string(), line);
+                return None;
+            }
+
+            return Some(RunNode {
+                name: Some(name),
+                command: command.trim().to_string(),
+                timeout: modifiers.timeout,
+                repeat: modifiers.repeat,
+                line,
+            });
+        }
+    }
+
+    // Unnamed RUN
+    let (command, modifiers) = split_run_modifiers(rest, line, collector)?;
+    if command.trim().is_empty() {
+        collector.add_error("Empty command in RUN statement".to_string(), line);
+        return None;
+    }
+
+    Some(RunNode {
+        name: None,
+        command: command.trim().to_string(),
+        timeout: modifiers.timeout,
+        repeat: modifiers.repeat,
+        line,
+    })
+}
+
+/// Assertion target that refers to the enclosing TEST block, unless the TEST
+/// has a RUN of that name
+const TEST_TARGET: &str = "test";
+
+fn parse_env(content: &str, line: usize, collector: &mut ParseErrorCollector) -> Option<EnvNode> {
+    // ENV KEY=value
+    let rest = &content[4..]; // After "ENV "
+    let Some(eq_index) = rest.find('=') else {
         collector.add_error(
-            "Expected comparison operator after \"duration\"".to_string(),
+            "Invalid ENV syntax: expected KEY=value format".to_string(),
             line,
         );
         return None;
     };
 
-    let Some((duration_value, _)) = parse_duration(input, end_index) else {
+    let key = rest[..eq_index].trim().to_string();
+    let value = rest[eq_index + 1..].to_string();
+
+    if key.is_empty() {
+        collector.add_error("Invalid ENV syntax: empty key".to_string(), line);
+        return None;
+    }
+
+    if !is_valid_env_key(&key) {
         collector.add_error(
-            "Expected duration value (e.g., 200ms, 1.5s) after comparison operator".to_string(),
+            format!(
+                "Invalid environment variable name: \"{}\". Names must start with a letter or underscore and contain only alphanumeric characters and underscores",
+                key
+            ),
             line,
         );
         return None;
-    };
+    }
 
-    Some(AssertionExpression::Duration {
-        target,
-        predicate: DurationPredicate {
-            operator: op,
-            value: duration_value,
-        },
-    })
+    Some(EnvNode { key, value, line })
 }
 
-fn parse_file_assertion(
-    input: &str,
+pub(super) fn is_valid_env_key(key: &str) -> bool {
+    static ENV_KEY_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = ENV_KEY_RE.get_or_init(|| {
+        regex::Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").expect("env key regex should be valid")
+    });
+    re.is_match(key)
+}
+
+fn parse_assert(
+    content: &str,
     line: usize,
     collector: &mut ParseErrorCollector,
-) -> Option<AssertionExpression> {
-    // file "path" <predicate>
-    let mut i = 4; // After "file"
-    i = skip_whitespace(input, i);
-
-    let Some((path, end_index)) = parse_string_literal(input, i) else {
-        collector.add_error("Expected quoted file path after \"file\"".to_string(), line);
-        return None;
+    run_names: &HashSet<String>,
+) -> Option<AssertNode> {
+    // ASSERT <expression> [PARTIAL]
+    let rest = &content[7..]; // After "ASSERT "
+    let (rest, partial) = match rest.trim_end().strip_suffix(" PARTIAL") {
+        Some(expression) => (expression, true),
+        None => (rest, false),
     };
-    i = skip_whitespace(input, end_index);
+    let expression = parse_assertion_expression(rest, line, collector, run_names)?;
 
-    // Parse predicate
-    if match_word(input, i, "exists") {
-        return Some(AssertionExpression::File {
-            path,
-            predicate: FilePredicate::Exists,
-        });
+    let checks_output = matches!(
+        expression,
+        AssertionExpression::Output { .. }
+            | AssertionExpression::Bytes { .. }
+            | AssertionExpression::Length { .. }
+    );
+    if partial && !checks_output {
+        collector.add_error(
+            "PARTIAL only applies to stdout and stderr assertions".to_string(),
+            line,
+        );
+        return None;
+    }
+
+    Some(AssertNode {
+        expression,
+        partial,
+        line,
+        raw: content.to_string(),
+    })
+}
+
+/// `run_names` are the RUNs named so far in the TEST; a RUN named `test`
+/// makes `test.<selector>` refer to it instead of the TEST.
+fn parse_assertion_expression(
+    input: &str,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+    run_names: &HashSet<String>,
+) -> Option<AssertionExpression> {
+    let mut i = skip_whitespace(input, 0);
+
+    // Check for file assertion
+    if match_word(input, i, "file") {
+        return parse_file_assertion(input, line, collector);
+    }
+
+    // `stub.stdout` is the output of a RUN named stub
+    if input[i..].starts_with("stub ") {
+        return parse_stub_assertion(input, i + 4, line, collector);
+    }
+
+    // `http.stdout` is the output of a RUN named http
+    if input[i..].starts_with("http ") {
+        return parse_http_assertion(input, i + 4, line, collector);
+    }
+
+    // Check for test-level duration
+    let test_is_run = run_names.contains(TEST_TARGET);
+    if !test_is_run && match_word(input, i, "test.duration") {
+        i += 13; // "test.duration"
+        let predicate = parse_duration_predicate(input, i, line, collector)?;
+        return Some(AssertionExpression::TestDuration { predicate });
+    }
+
+    // Check for stdout.raw first
+    if match_word(input, i, "stdout.raw") {
+        i += 10; // "stdout.raw"
+        return parse_output_assertion(input, i, OutputSelector::StdoutRaw, None, line, collector);
+    }
+
+    // Check for named target (e.g., build.stdout)
+    let mut target: Option<String> = None;
+    static NAMED_TARGET_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = NAMED_TARGET_RE.get_or_init(|| {
+        regex::Regex::new(r"^([a-zA-Z_][a-zA-Z0-9_-]*)\.(.+)")
+            .expect("named target regex should be valid")
+    });
+
+    let mut effective_input = input;
+    if let Some(captures) = re.captures(input) {
+        if let (Some(potential_target_match), Some(remainder_match)) =
+            (captures.get(1), captures.get(2))
+        {
+            let potential_target = potential_target_match.as_str();
+            if potential_target == TEST_TARGET && !test_is_run {
+                collector.add_error(
+                    format!(
+                        "Unknown test-level assertion: {}. Only test.duration is supported",
+                        input
+                    ),
+                    line,
+                );
+                return None;
+            }
+            if potential_target != "stdout"
+                && potential_target != "stderr"
+                && potential_target != "exit_code"
+                && potential_target != "duration"
+                && potential_target != "max_rss"
+                && potential_target != "cpu_time"
+            {
+                target = Some(potential_target.to_string());
+                effective_input = remainder_match.as_str();
+                i = 0;
+            }
+        }
+    }
+
+    // Parse selector
+    if let Some((selector, len)) = match_byte_selector(effective_input, i, "bytes") {
+        return parse_bytes_assertion(effective_input, i + len, selector, target, line, collector);
+    }
+
+    if let Some((selector, len)) = match_byte_selector(effective_input, i, "length") {
+        return parse_length_assertion(effective_input, i + len, selector, target, line, collector);
+    }
+
+    if match_word(effective_input, i, "stdout.raw") {
+        i += 10;
+        return parse_output_assertion(
+            effective_input,
+            i,
+            OutputSelector::StdoutRaw,
+            target,
+            line,
+            collector,
+        );
+    }
+
+    if match_word(effective_input, i, "stdout") {
+        i += 6;
+        return parse_output_assertion(
+            effective_input,
+            i,
+            OutputSelector::Stdout,
+            target,
+            line,
+            collector,
+        );
+    }
+
+    if match_word(effective_input, i, "stderr") {
+        i += 6;
+        return parse_output_assertion(
+            effective_input,
+            i,
+            OutputSelector::Stderr,
+            target,
+            line,
+            collector,
+        );
+    }
+
+    if match_word(effective_input, i, "exit_code") {
+        i += 9;
+        return parse_exit_code_assertion(effective_input, i, target, line, collector);
+    }
+
+    if match_word(effective_input, i, "duration") {
+        i += 8;
+        return parse_duration_assertion(effective_input, i, target, line, collector);
+    }
+
+    if match_word(effective_input, i, "max_rss") {
+        i += 7;
+        return parse_max_rss_assertion(effective_input, i, target, line, collector);
+    }
+
+    if match_word(effective_input, i, "cpu_time") {
+        i += 8;
+        let predicate = parse_duration_predicate(effective_input, i, line, collector)?;
+        return Some(AssertionExpression::CpuTime { target, predicate });
     }
 
+    collector.add_error(format!("Unknown assertion type: {}", input), line);
+    None
+}
+
+fn parse_output_assertion(
+    input: &str,
+    start_index: usize,
+    selector: OutputSelector,
+    target: Option<String>,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    let selector_str = match selector {
+        OutputSelector::Stdout => "stdout",
+        OutputSelector::StdoutRaw => "stdout.raw",
+        OutputSelector::Stderr => "stderr",
+    };
+    let predicate = parse_output_predicate(input, start_index, selector_str, line, collector)?;
+
+    Some(AssertionExpression::Output {
+        target,
+        selector,
+        predicate,
+    })
+}
+
+/// Parses `contains "<text>"`, `matches /<regex>/`, `== "<text>"` or
+/// `!= "<text>"` after the output named `subject`.
+fn parse_output_predicate(
+    input: &str,
+    start_index: usize,
+    subject: &str,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<OutputPredicate> {
+    let mut i = skip_whitespace(input, start_index);
+
+    // Parse predicate
     if match_word(input, i, "contains") {
         i += 8;
         i = skip_whitespace(input, i);
@@ -680,10 +1649,7 @@ fn parse_file_assertion(
             return None;
         };
 
-        return Some(AssertionExpression::File {
-            path,
-            predicate: FilePredicate::Contains { value: string_lit },
-        });
+        return Some(OutputPredicate::Contains { value: string_lit });
     }
 
     if match_word(input, i, "matches") {
@@ -695,197 +1661,1477 @@ fn parse_file_assertion(
             return None;
         };
 
-        return Some(AssertionExpression::File {
-            path,
-            predicate: FilePredicate::Matches { value: regex_lit },
-        });
+        return Some(OutputPredicate::Matches { value: regex_lit });
+    }
+
+    // Check for == or !=
+    if let Some((op, end_index)) = parse_comparison_operator(input, i) {
+        if matches!(op, ComparisonOperator::Equal | ComparisonOperator::NotEqual) {
+            i = skip_whitespace(input, end_index);
+
+            let Some((string_lit, _)) = parse_string_literal(input, i) else {
+                collector.add_error(
+                    "Expected quoted string after comparison operator".to_string(),
+                    line,
+                );
+                return None;
+            };
+
+            let string_op = match op {
+                ComparisonOperator::Equal => StringComparisonOperator::Equal,
+                ComparisonOperator::NotEqual => StringComparisonOperator::NotEqual,
+                _ => unreachable!(),
+            };
+
+            return Some(OutputPredicate::Equals {
+                operator: string_op,
+                value: string_lit,
+            });
+        }
+    }
+
+    collector.add_error(
+        format!(
+            "Expected predicate (contains, matches, ==, !=) after \"{}\"",
+            subject
+        ),
+        line,
+    );
+    None
+}
+
+/// Matches `stdout.<field>` or `stderr.<field>`, returning the selector and
+/// the length of the matched text.
+fn match_byte_selector(input: &str, i: usize, field: &str) -> Option<(OutputSelector, usize)> {
+    [
+        ("stdout", OutputSelector::Stdout),
+        ("stderr", OutputSelector::Stderr),
+    ]
+    .into_iter()
+    .find_map(|(stream, selector)| {
+        let word = format!("{}.{}", stream, field);
+        match_word(input, i, &word).then_some((selector, word.len()))
+    })
+}
+
+fn parse_bytes_assertion(
+    input: &str,
+    start_index: usize,
+    selector: OutputSelector,
+    target: Option<String>,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    let i = skip_whitespace(input, start_index);
+
+    type MakePredicate = fn(HexLiteral) -> BytesPredicate;
+    let (keyword, predicate_end, make_predicate): (&str, usize, MakePredicate) =
+        if match_word(input, i, "contains") {
+            ("\"contains\"", i + 8, |value| BytesPredicate::Contains {
+                value,
+            })
+        } else if match_word(input, i, "starts_with") {
+            ("\"starts_with\"", i + 11, |value| {
+                BytesPredicate::StartsWith { value }
+            })
+        } else {
+            match parse_comparison_operator(input, i) {
+                Some((ComparisonOperator::Equal, end_index)) => {
+                    ("comparison operator", end_index, |value| {
+                        BytesPredicate::Equals {
+                            operator: StringComparisonOperator::Equal,
+                            value,
+                        }
+                    })
+                }
+                Some((ComparisonOperator::NotEqual, end_index)) => {
+                    ("comparison operator", end_index, |value| {
+                        BytesPredicate::Equals {
+                            operator: StringComparisonOperator::NotEqual,
+                            value,
+                        }
+                    })
+                }
+                _ => {
+                    collector.add_error(
+                        "Expected predicate (contains, starts_with, ==, !=) after byte selector"
+                            .to_string(),
+                        line,
+                    );
+                    return None;
+                }
+            }
+        };
+
+    let value_start = skip_whitespace(input, predicate_end);
+    let Some((value, _)) = parse_hex_literal(input, value_start) else {
+        collector.add_error(
+            format!(
+                "Expected hex literal (e.g., hex\"1f8b08\") after {}",
+                keyword
+            ),
+            line,
+        );
+        return None;
+    };
+    let predicate = make_predicate(value);
+
+    Some(AssertionExpression::Bytes {
+        target,
+        selector,
+        predicate,
+    })
+}
+
+fn parse_length_assertion(
+    input: &str,
+    start_index: usize,
+    selector: OutputSelector,
+    target: Option<String>,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    let Some((operator, end_index)) = parse_comparison_operator(input, start_index) else {
+        collector.add_error(
+            "Expected comparison operator after \"length\"".to_string(),
+            line,
+        );
+        return None;
+    };
+
+    let Some((value, _)) = parse_unsigned(input, end_index) else {
+        collector.add_error(
+            "Expected byte count (e.g., 1024) after comparison operator".to_string(),
+            line,
+        );
+        return None;
+    };
+
+    Some(AssertionExpression::Length {
+        target,
+        selector,
+        predicate: LengthPredicate { operator, value },
+    })
+}
+
+fn parse_exit_code_assertion(
+    input: &str,
+    start_index: usize,
+    target: Option<String>,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    let Some((op, end_index)) = parse_comparison_operator(input, start_index) else {
+        collector.add_error("Expected == or != after \"exit_code\"".to_string(), line);
+        return None;
+    };
+
+    if !matches!(op, ComparisonOperator::Equal | ComparisonOperator::NotEqual) {
+        collector.add_error("Expected == or != after \"exit_code\"".to_string(), line);
+        return None;
+    }
+
+    let num_value = match parse_number_checked(input, end_index) {
+        ParseNumberResult::Success(value, _) => value,
+        ParseNumberResult::Overflow => {
+            collector.add_error(
+                "Exit code value is too large (must fit in i32 range)".to_string(),
+                line,
+            );
+            return None;
+        }
+        ParseNumberResult::NotANumber => {
+            collector.add_error(
+                "Expected number after comparison operator".to_string(),
+                line,
+            );
+            return None;
+        }
+    };
+
+    let string_op = match op {
+        ComparisonOperator::Equal => StringComparisonOperator::Equal,
+        ComparisonOperator::NotEqual => StringComparisonOperator::NotEqual,
+        _ => unreachable!(),
+    };
+
+    Some(AssertionExpression::ExitCode {
+        target,
+        predicate: ExitCodePredicate {
+            operator: string_op,
+            value: num_value,
+        },
+    })
+}
+
+fn parse_duration_assertion(
+    input: &str,
+    start_index: usize,
+    target: Option<String>,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    let Some(statistic_name) = input.get(start_index..).and_then(|s| s.strip_prefix('.')) else {
+        let predicate = parse_duration_predicate(input, start_index, line, collector)?;
+        return Some(AssertionExpression::Duration { target, predicate });
+    };
+
+    let name_len = statistic_name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(statistic_name.len());
+    let statistic = match &statistic_name[..name_len] {
+        "min" => DurationStatistic::Min,
+        "median" => DurationStatistic::Median,
+        "p95" => DurationStatistic::P95,
+        "max" => DurationStatistic::Max,
+        other => {
+            collector.add_error(
+                format!(
+                    "Unknown duration statistic: {}. Expected min, median, p95 or max",
+                    other
+                ),
+                line,
+            );
+            return None;
+        }
+    };
+
+    let predicate = parse_duration_predicate(input, start_index + 1 + name_len, line, collector)?;
+
+    Some(AssertionExpression::DurationStat {
+        target,
+        statistic,
+        predicate,
+    })
+}
+
+fn parse_duration_predicate(
+    input: &str,
+    start_index: usize,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<DurationPredicate> {
+    let Some((op, end_index)) = parse_comparison_operator(input, start_index) else {
+        collector.add_error(
+            "Expected comparison operator after \"duration\"".to_string(),
+            line,
+        );
+        return None;
+    };
+
+    let Some((duration_value, _)) = parse_duration(input, end_index) else {
+        collector.add_error(
+            "Expected duration value (e.g., 200ms, 1.5s) after comparison operator".to_string(),
+            line,
+        );
+        return None;
+    };
+
+    Some(DurationPredicate {
+        operator: op,
+        value: duration_value,
+    })
+}
+
+fn parse_max_rss_assertion(
+    input: &str,
+    start_index: usize,
+    target: Option<String>,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    let Some((op, end_index)) = parse_comparison_operator(input, start_index) else {
+        collector.add_error(
+            "Expected comparison operator after \"max_rss\"".to_string(),
+            line,
+        );
+        return None;
+    };
+
+    let Some((size, _)) = parse_memory_size(input, end_index) else {
+        collector.add_error(
+            "Expected memory size (e.g., 512KB, 50MB, 1GB) after comparison operator".to_string(),
+            line,
+        );
+        return None;
+    };
+
+    Some(AssertionExpression::MaxRss {
+        target,
+        predicate: MemoryPredicate {
+            operator: op,
+            value: size,
+        },
+    })
+}
+
+fn parse_stub_assertion(
+    input: &str,
+    start_index: usize,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    // stub <command> <predicate>
+    let mut i = skip_whitespace(input, start_index);
+    let name_end = input[i..]
+        .find(char::is_whitespace)
+        .map_or(input.len(), |end| i + end);
+    let command = input[i..name_end].to_string();
+    if !is_valid_stub_command(&command) {
+        collector.add_error(
+            format!("Invalid stub command name: {}", input[i..name_end].trim()),
+            line,
+        );
+        return None;
+    }
+    i = skip_whitespace(input, name_end);
+
+    if match_word(input, i, "called") {
+        i = skip_whitespace(input, i + 6);
+        if i >= input.len() {
+            return Some(AssertionExpression::Stub {
+                command,
+                predicate: StubPredicate::Called,
+            });
+        }
+
+        if match_word(input, i, "with") {
+            i = skip_whitespace(input, i + 4);
+            if let Some((value, _)) = parse_string_literal(input, i) {
+                return Some(AssertionExpression::Stub {
+                    command,
+                    predicate: StubPredicate::CalledWith { value },
+                });
+            }
+            if let Some((value, _)) = parse_regex_literal(input, i) {
+                return Some(AssertionExpression::Stub {
+                    command,
+                    predicate: StubPredicate::CalledMatching { value },
+                });
+            }
+            collector.add_error(
+                "Expected quoted arguments or regex literal after \"called with\"".to_string(),
+                line,
+            );
+            return None;
+        }
+    }
+
+    if match_word(input, i, "call_count") {
+        let Some((operator, end_index)) = parse_comparison_operator(input, i + 10) else {
+            collector.add_error(
+                "Expected comparison operator after \"call_count\"".to_string(),
+                line,
+            );
+            return None;
+        };
+        let Some((value, _)) = parse_unsigned(input, end_index) else {
+            collector.add_error(
+                "Expected number of calls after comparison operator".to_string(),
+                line,
+            );
+            return None;
+        };
+
+        return Some(AssertionExpression::Stub {
+            command,
+            predicate: StubPredicate::CallCount { operator, value },
+        });
+    }
+
+    if match_word(input, i, "stdin") {
+        let predicate = parse_output_predicate(input, i + 5, "stdin", line, collector)?;
+        return Some(AssertionExpression::Stub {
+            command,
+            predicate: StubPredicate::Stdin(predicate),
+        });
+    }
+
+    collector.add_error(
+        format!(
+            "Expected predicate (called, called with, call_count, stdin) after \"stub {}\"",
+            command
+        ),
+        line,
+    );
+    None
+}
+
+fn parse_http_assertion(
+    input: &str,
+    start_index: usize,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    // http request "<METHOD> <path>" <predicate>
+    let mut i = skip_whitespace(input, start_index);
+    if !match_word(input, i, "request") {
+        collector.add_error(
+            "Expected \"request\" after \"http\", as in http request \"GET /v1/items\" received"
+                .to_string(),
+            line,
+        );
+        return None;
+    }
+    i = skip_whitespace(input, i + 7);
+
+    let Some((spec, end_index)) = parse_string_literal(input, i) else {
+        collector.add_error(
+            "Expected quoted method and path after \"http request\"".to_string(),
+            line,
+        );
+        return None;
+    };
+    let Some(request) = parse_http_request_spec(&spec.value) else {
+        collector.add_error(
+            format!(
+                "Invalid HTTP request: {}. Expected a method and path such as \"POST /v1/items\"",
+                spec.raw
+            ),
+            line,
+        );
+        return None;
+    };
+    i = skip_whitespace(input, end_index);
+
+    if match_word(input, i, "received") && skip_whitespace(input, i + 8) >= input.len() {
+        return Some(AssertionExpression::Http {
+            request,
+            predicate: HttpPredicate::Received,
+        });
+    }
+
+    if match_word(input, i, "count") {
+        let Some((operator, end_index)) = parse_comparison_operator(input, i + 5) else {
+            collector.add_error(
+                "Expected comparison operator after \"count\"".to_string(),
+                line,
+            );
+            return None;
+        };
+        let Some((value, _)) = parse_unsigned(input, end_index) else {
+            collector.add_error(
+                "Expected number of requests after comparison operator".to_string(),
+                line,
+            );
+            return None;
+        };
+
+        return Some(AssertionExpression::Http {
+            request,
+            predicate: HttpPredicate::Count { operator, value },
+        });
+    }
+
+    if match_word(input, i, "body") {
+        let predicate = parse_output_predicate(input, i + 4, "body", line, collector)?;
+        return Some(AssertionExpression::Http {
+            request,
+            predicate: HttpPredicate::Body(predicate),
+        });
+    }
+
+    collector.add_error(
+        format!(
+            "Expected predicate (received, count, body) after \"http request {}\"",
+            spec.raw
+        ),
+        line,
+    );
+    None
+}
+
+fn parse_file_assertion(
+    input: &str,
+    line: usize,
+    collector: &mut ParseErrorCollector,
+) -> Option<AssertionExpression> {
+    // file "path" <predicate>
+    let mut i = 4; // After "file"
+    i = skip_whitespace(input, i);
+
+    let Some((path, end_index)) = parse_string_literal(input, i) else {
+        collector.add_error("Expected quoted file path after \"file\"".to_string(), line);
+        return None;
+    };
+    i = skip_whitespace(input, end_index);
+
+    // Parse predicate
+    if match_word(input, i, "exists") {
+        return Some(AssertionExpression::File {
+            path,
+            predicate: FilePredicate::Exists,
+        });
+    }
+
+    if match_word(input, i, "contains") {
+        i += 8;
+        i = skip_whitespace(input, i);
+
+        let Some((string_lit, _)) = parse_string_literal(input, i) else {
+            collector.add_error(
+                "Expected quoted string after \"contains\"".to_string(),
+                line,
+            );
+            return None;
+        };
+
+        return Some(AssertionExpression::File {
+            path,
+            predicate: FilePredicate::Contains { value: string_lit },
+        });
+    }
+
+    if match_word(input, i, "matches") {
+        i += 7;
+        i = skip_whitespace(input, i);
+
+        let Some((regex_lit, _)) = parse_regex_literal(input, i) else {
+            collector.add_error("Expected regex literal after \"matches\"".to_string(), line);
+            return None;
+        };
+
+        return Some(AssertionExpression::File {
+            path,
+            predicate: FilePredicate::Matches { value: regex_lit },
+        });
+    }
+
+    if match_word(input, i, "sha256") {
+        i += 6;
+        let operator = match parse_comparison_operator(input, i) {
+            Some((ComparisonOperator::Equal, end_index)) => {
+                i = end_index;
+                StringComparisonOperator::Equal
+            }
+            Some((ComparisonOperator::NotEqual, end_index)) => {
+                i = end_index;
+                StringComparisonOperator::NotEqual
+            }
+            _ => {
+                collector.add_error("Expected == or != after \"sha256\"".to_string(), line);
+                return None;
+            }
+        };
+        i = skip_whitespace(input, i);
+
+        let Some((digest, _)) = parse_string_literal(input, i) else {
+            collector.add_error(
+                "Expected quoted SHA-256 digest after comparison operator".to_string(),
+                line,
+            );
+            return None;
+        };
+        if digest.value.len() != 64 || !digest.value.chars().all(|c| c.is_ascii_hexdigit()) {
+            collector.add_error(
+                format!(
+                    "Invalid SHA-256 digest: {}. Expected 64 hex digits",
+                    digest.raw
+                ),
+                line,
+            );
+            return None;
+        }
+
+        return Some(AssertionExpression::File {
+            path,
+            predicate: FilePredicate::Sha256 {
+                operator,
+                value: digest,
+            },
+        });
+    }
+
+    // Check for == or !=
+    if let Some((op, end_index)) = parse_comparison_operator(input, i) {
+        if matches!(op, ComparisonOperator::Equal | ComparisonOperator::NotEqual) {
+            i = skip_whitespace(input, end_index);
+
+            let Some((string_lit, _)) = parse_string_literal(input, i) else {
+                collector.add_error(
+                    "Expected quoted string after comparison operator".to_string(),
+                    line,
+                );
+                return None;
+            };
+
+            let string_op = match op {
+                ComparisonOperator::Equal => StringComparisonOperator::Equal,
+                ComparisonOperator::NotEqual => StringComparisonOperator::NotEqual,
+                _ => unreachable!(),
+            };
+
+            return Some(AssertionExpression::File {
+                path,
+                predicate: FilePredicate::Equals {
+                    operator: string_op,
+                    value: string_lit,
+                },
+            });
+        }
+    }
+
+    collector.add_error(
+        "Expected predicate (exists, contains, matches, sha256, ==, !=) after file path"
+            .to_string(),
+        line,
+    );
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_test_name(name: &str) -> bool {
+        let content = format!("TEST \"{}\"", name);
+        let mut collector = ParseErrorCollector::new("test.hone".to_string());
+        parse_test(&content, 1, &mut collector).is_some()
+    }
+
+    #[test]
+    fn test_names_accept_any_characters() {
+        assert!(parse_test_name("simple"));
+        assert!(parse_test_name("Test123"));
+        assert!(parse_test_name("my test name"));
+        assert!(parse_test_name("test-with-dashes"));
+        assert!(parse_test_name("test_with_underscores"));
+        assert!(parse_test_name("test with equals = sign"));
+        assert!(parse_test_name("test with 'single quotes'"));
+        assert!(parse_test_name("test: with colon"));
+        assert!(parse_test_name("is this valid?"));
+        assert!(parse_test_name("test with @ symbol"));
+        assert!(parse_test_name("test with # hash"));
+        assert!(parse_test_name("test with $ dollar"));
+        assert!(parse_test_name("test with * asterisk"));
+        assert!(parse_test_name("test with | pipe"));
+        assert!(parse_test_name("émojis 🎉 work too"));
+    }
+
+    #[test]
+    fn test_empty_test_name_rejected() {
+        let content = r#"TEST ""
+RUN echo hello
+ASSERT stdout contains "hello""#;
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Empty test name should produce an error"
+                );
+                assert!(
+                    file.errors
+                        .iter()
+                        .any(|e| e.message.contains("cannot be empty")),
+                    "Error message should mention empty test name"
+                );
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_whitespace_only_test_name_allowed() {
+        // Whitespace-only names should be allowed (for user flexibility)
+        assert!(parse_test_name(" "));
+        assert!(parse_test_name("   "));
+    }
+
+    #[test]
+    fn test_error_node_in_ast() {
+        let content = "TEST \"valid test\"\nINVALID LINE\nRUN echo hello";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                // Should have errors in the file
+                assert!(!file.errors.is_empty());
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Unknown statement")));
+
+                // Should also have error nodes in AST
+                let error_nodes: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter(|n| matches!(n, ASTNode::Error(_)))
+                    .collect();
+                assert!(
+                    !error_nodes.is_empty(),
+                    "Expected error nodes in AST for invalid syntax"
+                );
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiple_errors_collected() {
+        let content = "INVALID1\nINVALID2\nTEST \"test\"\nINVALID3";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert_eq!(
+                    file.errors.len(),
+                    3,
+                    "Expected 3 errors for 3 invalid lines"
+                );
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pragma_env_rejects_invalid_key_starting_with_number() {
+        let content = "#!env: 123ABC=value";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for invalid env key"
+                );
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Invalid environment variable name")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pragma_env_rejects_key_with_hyphen() {
+        let content = "#!env: MY-VAR=value";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for invalid env key with hyphen"
+                );
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Invalid environment variable name")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pragma_env_rejects_empty_key() {
+        let content = "#!env: =value";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(!file.errors.is_empty(), "Expected error for empty env key");
+                assert!(file.errors.iter().any(|e| e.message.contains("empty key")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pragma_env_accepts_valid_key() {
+        let content = "#!env: MY_VAR_123=value";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    file.errors.is_empty(),
+                    "Expected no errors for valid env key"
+                );
+                let pragmas: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| match n {
+                        ASTNode::Pragma(p) => Some(p),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(pragmas.len(), 1);
+                assert_eq!(pragmas[0].key.as_ref().unwrap(), "MY_VAR_123");
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pragma_env_accepts_underscore_prefix() {
+        let content = "#!env: _PRIVATE_VAR=value";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    file.errors.is_empty(),
+                    "Expected no errors for underscore-prefixed env key"
+                );
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pragma_env_accepts_empty_value() {
+        // Empty values are valid in shell (export FOO= sets FOO to empty string)
+        let content = "#!env: MY_VAR=";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    file.errors.is_empty(),
+                    "Expected no errors for empty env value"
+                );
+                let pragmas: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| {
+                        if let ASTNode::Pragma(p) = n {
+                            Some(p)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                assert_eq!(pragmas.len(), 1);
+                assert_eq!(pragmas[0].value, "");
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_env_accepts_empty_value() {
+        // ENV statement with empty value should be valid
+        let content = "ENV MY_VAR=\nTEST \"test\"";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    file.errors.is_empty(),
+                    "Expected no errors for empty env value"
+                );
+                let envs: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| {
+                        if let ASTNode::Env(e) = n {
+                            Some(e)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                assert_eq!(envs.len(), 1);
+                assert_eq!(envs[0].key, "MY_VAR");
+                assert_eq!(envs[0].value, "");
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_run_names_rejected() {
+        let content = r#"TEST "test with duplicates"
+RUN build: echo first
+RUN build: echo second
+ASSERT stdout contains "first""#;
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for duplicate RUN names"
+                );
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Duplicate RUN name")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_names_reset_between_tests() {
+        let content = r#"TEST "first test"
+RUN build: echo first
+ASSERT stdout contains "first"
+
+TEST "second test"
+RUN build: echo second
+ASSERT stdout contains "second""#;
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    file.errors.is_empty(),
+                    "Expected no errors - same RUN name in different tests should be allowed: {:?}",
+                    file.errors
+                );
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_run_command_rejected() {
+        // "RUN " with just whitespace gets trimmed to "RUN" which is Unknown
+        // This tests the lexer behavior
+        let content = r#"TEST "empty run"
+RUN 
+ASSERT exit_code == 0"#;
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for empty RUN command"
+                );
+                // "RUN" alone becomes Unknown statement since it doesn't match "RUN "
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Unknown statement")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_named_run_empty_command_rejected() {
+        let content = r#"TEST "named empty run"
+RUN build: 
+ASSERT exit_code == 0"#;
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for named RUN with empty command"
+                );
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Empty command")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_exit_code_overflow_error_message() {
+        let content = r#"TEST "overflow test"
+RUN true
+ASSERT exit_code == 9999999999999999999"#;
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for overflowing exit code"
+                );
+                assert!(
+                    file.errors.iter().any(|e| e.message.contains("too large")),
+                    "Error message should mention value is too large"
+                );
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_exit_code_rejects_relational_operators() {
+        // exit_code only supports == and != operators, not <, <=, >, >=
+        let operators = ["<", "<=", ">", ">="];
+        for op in operators {
+            let content = format!(
+                r#"TEST "relational op test"
+RUN true
+ASSERT exit_code {} 0"#,
+                op
+            );
+            let result = parse_file(&content, "test.hone");
+
+            match result {
+                ParseResult::Success { file } => {
+                    assert!(
+                        !file.errors.is_empty(),
+                        "Expected error for exit_code with {} operator",
+                        op
+                    );
+                    assert!(
+                        file.errors.iter().any(|e| e.message.contains("== or !=")),
+                        "Error message should indicate only == or != are allowed for operator {}",
+                        op
+                    );
+                }
+                ParseResult::Failure { .. } => {
+                    panic!("Parser should always return Success with errors embedded");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stdout_rejects_relational_operators() {
+        // stdout only supports ==, !=, contains, matches - not <, <=, >, >=
+        let operators = ["<", "<=", ">", ">="];
+        for op in operators {
+            let content = format!(
+                r#"TEST "relational op test"
+RUN echo hello
+ASSERT stdout {} "hello""#,
+                op
+            );
+            let result = parse_file(&content, "test.hone");
+
+            match result {
+                ParseResult::Success { file } => {
+                    assert!(
+                        !file.errors.is_empty(),
+                        "Expected error for stdout with {} operator",
+                        op
+                    );
+                    assert!(
+                        file.errors
+                            .iter()
+                            .any(|e| e.message.contains("contains, matches, ==, !=")),
+                        "Error message should indicate valid predicates for operator {}",
+                        op
+                    );
+                }
+                ParseResult::Failure { .. } => {
+                    panic!("Parser should always return Success with errors embedded");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stderr_rejects_relational_operators() {
+        // stderr only supports ==, !=, contains, matches - not <, <=, >, >=
+        let operators = ["<", "<=", ">", ">="];
+        for op in operators {
+            let content = format!(
+                r#"TEST "relational op test"
+RUN echo hello
+ASSERT stderr {} "hello""#,
+                op
+            );
+            let result = parse_file(&content, "test.hone");
+
+            match result {
+                ParseResult::Success { file } => {
+                    assert!(
+                        !file.errors.is_empty(),
+                        "Expected error for stderr with {} operator",
+                        op
+                    );
+                    assert!(
+                        file.errors
+                            .iter()
+                            .any(|e| e.message.contains("contains, matches, ==, !=")),
+                        "Error message should indicate valid predicates for operator {}",
+                        op
+                    );
+                }
+                ParseResult::Failure { .. } => {
+                    panic!("Parser should always return Success with errors embedded");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_malformed_test_name_produces_error() {
+        // Test with unclosed string
+        let content = "TEST \"unclosed string";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for malformed TEST name"
+                );
+                assert!(
+                    file.errors
+                        .iter()
+                        .any(|e| e.message.contains("Expected quoted string after TEST")),
+                    "Error message should indicate string parsing failed"
+                );
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_test_without_name_produces_error() {
+        // Test with no string at all
+        let content = "TEST ";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for TEST without name"
+                );
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pragma_missing_colon_produces_warning() {
+        // Pragma without colon should produce a warning (e.g., "#!shell /bin/bash" instead of "#!shell: /bin/bash")
+        let content = "#!shell /bin/bash\nTEST \"test\"";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.warnings.is_empty(),
+                    "Expected warning for pragma missing colon"
+                );
+                assert!(file
+                    .warnings
+                    .iter()
+                    .any(|w| w.message.contains("Invalid pragma syntax")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
     }
 
-    // Check for == or !=
-    if let Some((op, end_index)) = parse_comparison_operator(input, i) {
-        if matches!(op, ComparisonOperator::Equal | ComparisonOperator::NotEqual) {
-            i = skip_whitespace(input, end_index);
+    #[test]
+    fn test_bare_pragma_produces_warning() {
+        // Bare "#!" should produce a warning
+        let content = "#!\nTEST \"test\"";
+        let result = parse_file(content, "test.hone");
 
-            let Some((string_lit, _)) = parse_string_literal(input, i) else {
-                collector.add_error(
-                    "Expected quoted string after comparison operator".to_string(),
-                    line,
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.warnings.is_empty(),
+                    "Expected warning for bare pragma"
                 );
-                return None;
-            };
-
-            let string_op = match op {
-                ComparisonOperator::Equal => StringComparisonOperator::Equal,
-                ComparisonOperator::NotEqual => StringComparisonOperator::NotEqual,
-                _ => unreachable!(),
-            };
-
-            return Some(AssertionExpression::File {
-                path,
-                predicate: FilePredicate::Equals {
-                    operator: string_op,
-                    value: string_lit,
-                },
-            });
+                assert!(file
+                    .warnings
+                    .iter()
+                    .any(|w| w.message.contains("Invalid pragma syntax")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
         }
     }
 
-    collector.add_error(
-        "Expected predicate (exists, contains, matches, ==, !=) after file path".to_string(),
-        line,
-    );
-    None
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_env_missing_equals_produces_error() {
+        // ENV without equals sign should produce an error
+        let content = "ENV FOO\nTEST \"test\"";
+        let result = parse_file(content, "test.hone");
 
-    fn parse_test_name(name: &str) -> bool {
-        let content = format!("TEST \"{}\"", name);
-        let mut collector = ParseErrorCollector::new("test.hone".to_string());
-        parse_test(&content, 1, &mut collector).is_some()
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for ENV missing equals sign"
+                );
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Invalid ENV syntax")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
     }
 
     #[test]
-    fn test_names_accept_any_characters() {
-        assert!(parse_test_name("simple"));
-        assert!(parse_test_name("Test123"));
-        assert!(parse_test_name("my test name"));
-        assert!(parse_test_name("test-with-dashes"));
-        assert!(parse_test_name("test_with_underscores"));
-        assert!(parse_test_name("test with equals = sign"));
-        assert!(parse_test_name("test with 'single quotes'"));
-        assert!(parse_test_name("test: with colon"));
-        assert!(parse_test_name("is this valid?"));
-        assert!(parse_test_name("test with @ symbol"));
-        assert!(parse_test_name("test with # hash"));
-        assert!(parse_test_name("test with $ dollar"));
-        assert!(parse_test_name("test with * asterisk"));
-        assert!(parse_test_name("test with | pipe"));
-        assert!(parse_test_name("émojis 🎉 work too"));
+    fn test_env_rejects_invalid_key_starting_with_number() {
+        let content = "ENV 123ABC=value\nTEST \"test\"";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for invalid env key starting with number"
+                );
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Invalid environment variable name")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
     }
 
     #[test]
-    fn test_empty_test_name_rejected() {
-        let content = r#"TEST ""
-RUN echo hello
-ASSERT stdout contains "hello""#;
+    fn test_env_rejects_key_with_hyphen() {
+        let content = "ENV MY-VAR=value\nTEST \"test\"";
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
                     !file.errors.is_empty(),
-                    "Empty test name should produce an error"
-                );
-                assert!(
-                    file.errors
-                        .iter()
-                        .any(|e| e.message.contains("cannot be empty")),
-                    "Error message should mention empty test name"
+                    "Expected error for env key with hyphen"
                 );
+                assert!(file
+                    .errors
+                    .iter()
+                    .any(|e| e.message.contains("Invalid environment variable name")));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should return Success with errors embedded");
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
 
     #[test]
-    fn test_whitespace_only_test_name_allowed() {
-        // Whitespace-only names should be allowed (for user flexibility)
-        assert!(parse_test_name(" "));
-        assert!(parse_test_name("   "));
+    fn test_env_rejects_empty_key() {
+        let content = "ENV =value\nTEST \"test\"";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(!file.errors.is_empty(), "Expected error for empty env key");
+                assert!(file.errors.iter().any(|e| e.message.contains("empty key")));
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
     }
 
     #[test]
-    fn test_error_node_in_ast() {
-        let content = "TEST \"valid test\"\nINVALID LINE\nRUN echo hello";
+    fn test_env_accepts_valid_key() {
+        let content = "ENV MY_VAR_123=value\nTEST \"test\"\nRUN echo $MY_VAR_123";
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
-                // Should have errors in the file
-                assert!(!file.errors.is_empty());
-                assert!(file
-                    .errors
+                assert!(
+                    file.errors.is_empty(),
+                    "Expected no errors for valid env key"
+                );
+                let envs: Vec<_> = file
+                    .nodes
                     .iter()
-                    .any(|e| e.message.contains("Unknown statement")));
+                    .filter_map(|n| {
+                        if let ASTNode::Env(e) = n {
+                            Some(e)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                assert_eq!(envs.len(), 1);
+                assert_eq!(envs[0].key, "MY_VAR_123");
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
 
-                // Should also have error nodes in AST
-                let error_nodes: Vec<_> = file
+    #[test]
+    fn test_env_accepts_underscore_prefix() {
+        let content = "ENV _PRIVATE=value\nTEST \"test\"\nRUN echo $_PRIVATE";
+        let result = parse_file(content, "test.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(
+                    file.errors.is_empty(),
+                    "Expected no errors for underscore-prefixed env key"
+                );
+                let envs: Vec<_> = file
                     .nodes
                     .iter()
-                    .filter(|n| matches!(n, ASTNode::Error(_)))
+                    .filter_map(|n| {
+                        if let ASTNode::Env(e) = n {
+                            Some(e)
+                        } else {
+                            None
+                        }
+                    })
                     .collect();
+                assert_eq!(envs.len(), 1);
+                assert_eq!(envs[0].key, "_PRIVATE");
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_file_empty() {
+        let result = parse_file("", "empty.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert!(file.nodes.is_empty(), "Empty file should have no nodes");
+                assert!(file.pragmas.is_empty(), "Empty file should have no pragmas");
+                assert!(file.errors.is_empty(), "Empty file should have no errors");
                 assert!(
-                    !error_nodes.is_empty(),
-                    "Expected error nodes in AST for invalid syntax"
+                    file.warnings.is_empty(),
+                    "Empty file should have no warnings"
                 );
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_multiple_errors_collected() {
-        let content = "INVALID1\nINVALID2\nTEST \"test\"\nINVALID3";
-        let result = parse_file(content, "test.hone");
+    fn test_parse_file_only_pragmas() {
+        let content = "#!shell: /bin/bash\n#!timeout: 5s\n#!env: FOO=bar";
+        let result = parse_file(content, "pragmas.hone");
+
+        match result {
+            ParseResult::Success { file } => {
+                assert_eq!(file.pragmas.len(), 3, "Should have 3 pragmas");
+                assert!(file.errors.is_empty(), "Should have no errors");
+                // Pragmas are also added to nodes
+                let pragma_nodes = file
+                    .nodes
+                    .iter()
+                    .filter(|n| matches!(n, ASTNode::Pragma(_)))
+                    .count();
+                assert_eq!(pragma_nodes, 3, "Should have 3 pragma nodes");
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success");
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_file_only_comments() {
+        let content = "# Comment 1\n# Comment 2\n# Comment 3";
+        let result = parse_file(content, "comments.hone");
 
         match result {
             ParseResult::Success { file } => {
-                assert_eq!(
-                    file.errors.len(),
-                    3,
-                    "Expected 3 errors for 3 invalid lines"
-                );
+                assert!(file.pragmas.is_empty(), "Should have no pragmas");
+                assert!(file.errors.is_empty(), "Should have no errors");
+                let comment_nodes = file
+                    .nodes
+                    .iter()
+                    .filter(|n| matches!(n, ASTNode::Comment(_)))
+                    .count();
+                assert_eq!(comment_nodes, 3, "Should have 3 comment nodes");
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_pragma_env_rejects_invalid_key_starting_with_number() {
-        let content = "#!env: 123ABC=value";
-        let result = parse_file(content, "test.hone");
+    fn test_parse_file_only_whitespace() {
+        let content = "   \n\n   \n\t\t\n";
+        let result = parse_file(content, "whitespace.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for invalid env key"
+                    file.nodes.is_empty(),
+                    "Whitespace-only file should have no nodes"
+                );
+                assert!(
+                    file.errors.is_empty(),
+                    "Whitespace-only file should have no errors"
                 );
-                assert!(file
-                    .errors
-                    .iter()
-                    .any(|e| e.message.contains("Invalid environment variable name")));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_pragma_env_rejects_key_with_hyphen() {
-        let content = "#!env: MY-VAR=value";
+    fn test_file_predicate_error_mentions_matches() {
+        // When an invalid file predicate is used, the error message should list ALL valid predicates
+        let content = r#"TEST "file predicate test"
+RUN echo hello > test.txt
+ASSERT file "test.txt" invalid_predicate"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
                     !file.errors.is_empty(),
-                    "Expected error for invalid env key with hyphen"
+                    "Expected error for invalid file predicate"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("matches"),
+                    "Error message should mention 'matches' predicate. Got: {}",
+                    error_msg
                 );
-                assert!(file
-                    .errors
-                    .iter()
-                    .any(|e| e.message.contains("Invalid environment variable name")));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -894,14 +3140,25 @@ ASSERT stdout contains "hello""#;
     }
 
     #[test]
-    fn test_pragma_env_rejects_empty_key() {
-        let content = "#!env: =value";
+    fn test_exit_code_missing_number_error() {
+        // exit_code with operator but no number should produce a clear error
+        let content = r#"TEST "missing number"
+RUN true
+ASSERT exit_code == "#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
-                assert!(!file.errors.is_empty(), "Expected error for empty env key");
-                assert!(file.errors.iter().any(|e| e.message.contains("empty key")));
+                assert!(
+                    !file.errors.is_empty(),
+                    "Expected error for missing number after exit_code operator"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("Expected number"),
+                    "Error should mention expected number. Got: {}",
+                    error_msg
+                );
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -910,26 +3167,25 @@ ASSERT stdout contains "hello""#;
     }
 
     #[test]
-    fn test_pragma_env_accepts_valid_key() {
-        let content = "#!env: MY_VAR_123=value";
+    fn test_exit_code_missing_operator_error() {
+        // exit_code without operator should produce a clear error
+        let content = r#"TEST "missing operator"
+RUN true
+ASSERT exit_code 0"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
-                    file.errors.is_empty(),
-                    "Expected no errors for valid env key"
+                    !file.errors.is_empty(),
+                    "Expected error for missing operator in exit_code assertion"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("==") && error_msg.contains("!="),
+                    "Error should mention valid operators (== or !=). Got: {}",
+                    error_msg
                 );
-                let pragmas: Vec<_> = file
-                    .nodes
-                    .iter()
-                    .filter_map(|n| match n {
-                        ASTNode::Pragma(p) => Some(p),
-                        _ => None,
-                    })
-                    .collect();
-                assert_eq!(pragmas.len(), 1);
-                assert_eq!(pragmas[0].key.as_ref().unwrap(), "MY_VAR_123");
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -938,15 +3194,24 @@ ASSERT stdout contains "hello""#;
     }
 
     #[test]
-    fn test_pragma_env_accepts_underscore_prefix() {
-        let content = "#!env: _PRIVATE_VAR=value";
+    fn test_duration_missing_operator_error() {
+        // duration without operator should produce a clear error
+        let content = r#"TEST "missing operator"
+RUN sleep 0.1
+ASSERT duration 200ms"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
-                    file.errors.is_empty(),
-                    "Expected no errors for underscore-prefixed env key"
+                    !file.errors.is_empty(),
+                    "Expected error for missing operator in duration assertion"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("comparison operator"),
+                    "Error should mention comparison operator. Got: {}",
+                    error_msg
                 );
             }
             ParseResult::Failure { .. } => {
@@ -956,30 +3221,25 @@ ASSERT stdout contains "hello""#;
     }
 
     #[test]
-    fn test_pragma_env_accepts_empty_value() {
-        // Empty values are valid in shell (export FOO= sets FOO to empty string)
-        let content = "#!env: MY_VAR=";
+    fn test_duration_missing_value_error() {
+        // duration with operator but no value should produce a clear error
+        let content = r#"TEST "missing value"
+RUN sleep 0.1
+ASSERT duration < "#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
-                    file.errors.is_empty(),
-                    "Expected no errors for empty env value"
+                    !file.errors.is_empty(),
+                    "Expected error for missing value after duration operator"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("duration value"),
+                    "Error should mention expected duration value. Got: {}",
+                    error_msg
                 );
-                let pragmas: Vec<_> = file
-                    .nodes
-                    .iter()
-                    .filter_map(|n| {
-                        if let ASTNode::Pragma(p) = n {
-                            Some(p)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                assert_eq!(pragmas.len(), 1);
-                assert_eq!(pragmas[0].value, "");
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -988,31 +3248,25 @@ ASSERT stdout contains "hello""#;
     }
 
     #[test]
-    fn test_env_accepts_empty_value() {
-        // ENV statement with empty value should be valid
-        let content = "ENV MY_VAR=\nTEST \"test\"";
+    fn test_output_contains_missing_string_error() {
+        // stdout contains without string should produce a clear error
+        let content = r#"TEST "missing string"
+RUN echo hello
+ASSERT stdout contains"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
-                    file.errors.is_empty(),
-                    "Expected no errors for empty env value"
+                    !file.errors.is_empty(),
+                    "Expected error for missing string after 'contains'"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("quoted string") && error_msg.contains("contains"),
+                    "Error should mention expected quoted string after contains. Got: {}",
+                    error_msg
                 );
-                let envs: Vec<_> = file
-                    .nodes
-                    .iter()
-                    .filter_map(|n| {
-                        if let ASTNode::Env(e) = n {
-                            Some(e)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                assert_eq!(envs.len(), 1);
-                assert_eq!(envs[0].key, "MY_VAR");
-                assert_eq!(envs[0].value, "");
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1021,23 +3275,25 @@ ASSERT stdout contains "hello""#;
     }
 
     #[test]
-    fn test_duplicate_run_names_rejected() {
-        let content = r#"TEST "test with duplicates"
-RUN build: echo first
-RUN build: echo second
-ASSERT stdout contains "first""#;
+    fn test_output_matches_missing_regex_error() {
+        // stdout matches without regex should produce a clear error
+        let content = r#"TEST "missing regex"
+RUN echo hello
+ASSERT stdout matches"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
                     !file.errors.is_empty(),
-                    "Expected error for duplicate RUN names"
+                    "Expected error for missing regex after 'matches'"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("regex") && error_msg.contains("matches"),
+                    "Error should mention expected regex literal after matches. Got: {}",
+                    error_msg
                 );
-                assert!(file
-                    .errors
-                    .iter()
-                    .any(|e| e.message.contains("Duplicate RUN name")));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1046,22 +3302,24 @@ ASSERT stdout contains "first""#;
     }
 
     #[test]
-    fn test_run_names_reset_between_tests() {
-        let content = r#"TEST "first test"
-RUN build: echo first
-ASSERT stdout contains "first"
-
-TEST "second test"
-RUN build: echo second
-ASSERT stdout contains "second""#;
+    fn test_output_equals_missing_string_error() {
+        // stdout == without string should produce a clear error
+        let content = r#"TEST "missing string"
+RUN echo hello
+ASSERT stdout == "#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
-                    file.errors.is_empty(),
-                    "Expected no errors - same RUN name in different tests should be allowed: {:?}",
-                    file.errors
+                    !file.errors.is_empty(),
+                    "Expected error for missing string after comparison operator"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("quoted string"),
+                    "Error should mention expected quoted string. Got: {}",
+                    error_msg
                 );
             }
             ParseResult::Failure { .. } => {
@@ -1071,25 +3329,25 @@ ASSERT stdout contains "second""#;
     }
 
     #[test]
-    fn test_empty_run_command_rejected() {
-        // "RUN " with just whitespace gets trimmed to "RUN" which is Unknown
-        // This tests the lexer behavior
-        let content = r#"TEST "empty run"
-RUN 
-ASSERT exit_code == 0"#;
+    fn test_file_missing_path_error() {
+        // file assertion without path should produce a clear error
+        let content = r#"TEST "missing path"
+RUN touch test.txt
+ASSERT file exists"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
                     !file.errors.is_empty(),
-                    "Expected error for empty RUN command"
+                    "Expected error for missing file path"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("quoted file path"),
+                    "Error should mention expected quoted file path. Got: {}",
+                    error_msg
                 );
-                // "RUN" alone becomes Unknown statement since it doesn't match "RUN "
-                assert!(file
-                    .errors
-                    .iter()
-                    .any(|e| e.message.contains("Unknown statement")));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1098,22 +3356,25 @@ ASSERT exit_code == 0"#;
     }
 
     #[test]
-    fn test_named_run_empty_command_rejected() {
-        let content = r#"TEST "named empty run"
-RUN build: 
-ASSERT exit_code == 0"#;
+    fn test_file_contains_missing_string_error() {
+        // file contains without string should produce a clear error
+        let content = r#"TEST "missing string"
+RUN echo hello > test.txt
+ASSERT file "test.txt" contains"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
                     !file.errors.is_empty(),
-                    "Expected error for named RUN with empty command"
+                    "Expected error for missing string after file contains"
+                );
+                let error_msg = &file.errors[0].message;
+                assert!(
+                    error_msg.contains("quoted string") && error_msg.contains("contains"),
+                    "Error should mention expected quoted string after contains. Got: {}",
+                    error_msg
                 );
-                assert!(file
-                    .errors
-                    .iter()
-                    .any(|e| e.message.contains("Empty command")));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1122,21 +3383,24 @@ ASSERT exit_code == 0"#;
     }
 
     #[test]
-    fn test_exit_code_overflow_error_message() {
-        let content = r#"TEST "overflow test"
-RUN true
-ASSERT exit_code == 9999999999999999999"#;
+    fn test_file_matches_missing_regex_error() {
+        // file matches without regex should produce a clear error
+        let content = r#"TEST "missing regex"
+RUN echo hello > test.txt
+ASSERT file "test.txt" matches"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
                 assert!(
                     !file.errors.is_empty(),
-                    "Expected error for overflowing exit code"
+                    "Expected error for missing regex after file matches"
                 );
+                let error_msg = &file.errors[0].message;
                 assert!(
-                    file.errors.iter().any(|e| e.message.contains("too large")),
-                    "Error message should mention value is too large"
+                    error_msg.contains("regex") && error_msg.contains("matches"),
+                    "Error should mention expected regex literal. Got: {}",
+                    error_msg
                 );
             }
             ParseResult::Failure { .. } => {
@@ -1146,191 +3410,170 @@ ASSERT exit_code == 9999999999999999999"#;
     }
 
     #[test]
-    fn test_exit_code_rejects_relational_operators() {
-        // exit_code only supports == and != operators, not <, <=, >, >=
-        let operators = ["<", "<=", ">", ">="];
-        for op in operators {
-            let content = format!(
-                r#"TEST "relational op test"
-RUN true
-ASSERT exit_code {} 0"#,
-                op
-            );
-            let result = parse_file(&content, "test.hone");
-
-            match result {
-                ParseResult::Success { file } => {
-                    assert!(
-                        !file.errors.is_empty(),
-                        "Expected error for exit_code with {} operator",
-                        op
-                    );
-                    assert!(
-                        file.errors.iter().any(|e| e.message.contains("== or !=")),
-                        "Error message should indicate only == or != are allowed for operator {}",
-                        op
-                    );
-                }
-                ParseResult::Failure { .. } => {
-                    panic!("Parser should always return Success with errors embedded");
-                }
-            }
-        }
-    }
+    fn test_named_target_assertion_stdout() {
+        let content = r#"TEST "named target"
+RUN build: echo "hello"
+ASSERT build.stdout contains "hello""#;
+        let result = parse_file(content, "test.hone");
 
-    #[test]
-    fn test_stdout_rejects_relational_operators() {
-        // stdout only supports ==, !=, contains, matches - not <, <=, >, >=
-        let operators = ["<", "<=", ">", ">="];
-        for op in operators {
-            let content = format!(
-                r#"TEST "relational op test"
-RUN echo hello
-ASSERT stdout {} "hello""#,
-                op
-            );
-            let result = parse_file(&content, "test.hone");
+        match result {
+            ParseResult::Success { file } => {
+                assert!(file.errors.is_empty(), "Expected no errors");
+                let assert_nodes: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| {
+                        if let ASTNode::Assert(a) = n {
+                            Some(a)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                assert_eq!(assert_nodes.len(), 1);
 
-            match result {
-                ParseResult::Success { file } => {
-                    assert!(
-                        !file.errors.is_empty(),
-                        "Expected error for stdout with {} operator",
-                        op
-                    );
-                    assert!(
-                        file.errors
-                            .iter()
-                            .any(|e| e.message.contains("contains, matches, ==, !=")),
-                        "Error message should indicate valid predicates for operator {}",
-                        op
-                    );
-                }
-                ParseResult::Failure { .. } => {
-                    panic!("Parser should always return Success with errors embedded");
+                if let AssertionExpression::Output {
+                    target, selector, ..
+                } = &assert_nodes[0].expression
+                {
+                    assert_eq!(target.as_deref(), Some("build"));
+                    assert!(matches!(selector, OutputSelector::Stdout));
+                } else {
+                    panic!("Expected Output assertion expression");
                 }
             }
-        }
-    }
-
-    #[test]
-    fn test_stderr_rejects_relational_operators() {
-        // stderr only supports ==, !=, contains, matches - not <, <=, >, >=
-        let operators = ["<", "<=", ">", ">="];
-        for op in operators {
-            let content = format!(
-                r#"TEST "relational op test"
-RUN echo hello
-ASSERT stderr {} "hello""#,
-                op
-            );
-            let result = parse_file(&content, "test.hone");
-
-            match result {
-                ParseResult::Success { file } => {
-                    assert!(
-                        !file.errors.is_empty(),
-                        "Expected error for stderr with {} operator",
-                        op
-                    );
-                    assert!(
-                        file.errors
-                            .iter()
-                            .any(|e| e.message.contains("contains, matches, ==, !=")),
-                        "Error message should indicate valid predicates for operator {}",
-                        op
-                    );
-                }
-                ParseResult::Failure { .. } => {
-                    panic!("Parser should always return Success with errors embedded");
-                }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_malformed_test_name_produces_error() {
-        // Test with unclosed string
-        let content = "TEST \"unclosed string";
+    fn test_named_target_assertion_exit_code() {
+        let content = r#"TEST "named target exit"
+RUN build: echo "hello"
+ASSERT build.exit_code == 0"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for malformed TEST name"
-                );
-                assert!(
-                    file.errors
-                        .iter()
-                        .any(|e| e.message.contains("Expected quoted string after TEST")),
-                    "Error message should indicate string parsing failed"
-                );
+                assert!(file.errors.is_empty(), "Expected no errors");
+                let assert_nodes: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| {
+                        if let ASTNode::Assert(a) = n {
+                            Some(a)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                assert_eq!(assert_nodes.len(), 1);
+
+                if let AssertionExpression::ExitCode { target, predicate } =
+                    &assert_nodes[0].expression
+                {
+                    assert_eq!(target.as_deref(), Some("build"));
+                    assert_eq!(predicate.value, 0);
+                } else {
+                    panic!("Expected ExitCode assertion expression");
+                }
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_test_without_name_produces_error() {
-        // Test with no string at all
-        let content = "TEST ";
+    fn test_named_target_with_hyphen() {
+        let content = r#"TEST "hyphenated target"
+RUN my-build: echo "test"
+ASSERT my-build.stdout contains "test""#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for TEST without name"
-                );
+                assert!(file.errors.is_empty(), "Expected no errors");
+                let assert_nodes: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| {
+                        if let ASTNode::Assert(a) = n {
+                            Some(a)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                assert_eq!(assert_nodes.len(), 1);
+
+                if let AssertionExpression::Output { target, .. } = &assert_nodes[0].expression {
+                    assert_eq!(target.as_deref(), Some("my-build"));
+                } else {
+                    panic!("Expected Output assertion expression");
+                }
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_pragma_missing_colon_produces_warning() {
-        // Pragma without colon should produce a warning (e.g., "#!shell /bin/bash" instead of "#!shell: /bin/bash")
-        let content = "#!shell /bin/bash\nTEST \"test\"";
+    fn test_named_target_duration() {
+        let content = r#"TEST "duration target"
+RUN fast: echo "quick"
+ASSERT fast.duration < 100ms"#;
         let result = parse_file(content, "test.hone");
 
         match result {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.warnings.is_empty(),
-                    "Expected warning for pragma missing colon"
-                );
-                assert!(file
-                    .warnings
+                assert!(file.errors.is_empty(), "Expected no errors");
+                let assert_nodes: Vec<_> = file
+                    .nodes
                     .iter()
-                    .any(|w| w.message.contains("Invalid pragma syntax")));
+                    .filter_map(|n| {
+                        if let ASTNode::Assert(a) = n {
+                            Some(a)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                assert_eq!(assert_nodes.len(), 1);
+
+                if let AssertionExpression::Duration { target, .. } = &assert_nodes[0].expression {
+                    assert_eq!(target.as_deref(), Some("fast"));
+                } else {
+                    panic!("Expected Duration assertion expression");
+                }
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_bare_pragma_produces_warning() {
-        // Bare "#!" should produce a warning
-        let content = "#!\nTEST \"test\"";
-        let result = parse_file(content, "test.hone");
+    fn test_timeout_pragma_rejects_zero() {
+        let input = r#"#! timeout: 0s
+TEST "test"
+RUN echo hello
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
                 assert!(
-                    !file.warnings.is_empty(),
-                    "Expected warning for bare pragma"
+                    !file.errors.is_empty(),
+                    "Should have parse error for 0s timeout"
+                );
+                assert!(
+                    file.errors[0].message.contains("too small"),
+                    "Error message should mention 'too small': {}",
+                    file.errors[0].message
                 );
-                assert!(file
-                    .warnings
-                    .iter()
-                    .any(|w| w.message.contains("Invalid pragma syntax")));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1339,21 +3582,23 @@ ASSERT stderr {} "hello""#,
     }
 
     #[test]
-    fn test_env_missing_equals_produces_error() {
-        // ENV without equals sign should produce an error
-        let content = "ENV FOO\nTEST \"test\"";
-        let result = parse_file(content, "test.hone");
+    fn test_timeout_pragma_rejects_near_zero_ms() {
+        let input = r#"#! timeout: 0.5ms
+TEST "test"
+RUN echo hello
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
                 assert!(
                     !file.errors.is_empty(),
-                    "Expected error for ENV missing equals sign"
+                    "Should have parse error for 0.5ms timeout"
+                );
+                assert!(
+                    file.errors[0].message.contains("too small"),
+                    "Error message should mention 'too small': {}",
+                    file.errors[0].message
                 );
-                assert!(file
-                    .errors
-                    .iter()
-                    .any(|e| e.message.contains("Invalid ENV syntax")));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1362,89 +3607,82 @@ ASSERT stderr {} "hello""#,
     }
 
     #[test]
-    fn test_env_rejects_invalid_key_starting_with_number() {
-        let content = "ENV 123ABC=value\nTEST \"test\"";
-        let result = parse_file(content, "test.hone");
+    fn test_timeout_pragma_accepts_1ms() {
+        let input = r#"#! timeout: 1ms
+TEST "test"
+RUN echo hello
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for invalid env key starting with number"
-                );
-                assert!(file
-                    .errors
-                    .iter()
-                    .any(|e| e.message.contains("Invalid environment variable name")));
+                assert!(file.errors.is_empty(), "Should accept 1ms timeout");
+                assert_eq!(file.pragmas.len(), 1);
+                assert_eq!(file.pragmas[0].value, "1ms");
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_env_rejects_key_with_hyphen() {
-        let content = "ENV MY-VAR=value\nTEST \"test\"";
-        let result = parse_file(content, "test.hone");
+    fn test_timeout_pragma_accepts_valid_seconds() {
+        let input = r#"#! timeout: 30s
+TEST "test"
+RUN echo hello
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for env key with hyphen"
-                );
-                assert!(file
-                    .errors
-                    .iter()
-                    .any(|e| e.message.contains("Invalid environment variable name")));
+                assert!(file.errors.is_empty(), "Should accept 30s timeout");
+                assert_eq!(file.pragmas.len(), 1);
+                assert_eq!(file.pragmas[0].value, "30s");
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_env_rejects_empty_key() {
-        let content = "ENV =value\nTEST \"test\"";
-        let result = parse_file(content, "test.hone");
+    fn test_timeout_statement_parsed() {
+        let input = r#"TEST "slow build"
+TIMEOUT 5s
+RUN make
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(!file.errors.is_empty(), "Expected error for empty env key");
-                assert!(file.errors.iter().any(|e| e.message.contains("empty key")));
+                assert!(
+                    file.errors.is_empty(),
+                    "Unexpected errors: {:?}",
+                    file.errors
+                );
+                let timeout = file.nodes.iter().find_map(|n| match n {
+                    ASTNode::Timeout(t) => Some(t),
+                    _ => None,
+                });
+                let timeout = timeout.expect("Expected a TIMEOUT node");
+                assert_eq!(timeout.duration.raw, "5s");
+                assert_eq!(timeout.line, 2);
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_env_accepts_valid_key() {
-        let content = "ENV MY_VAR_123=value\nTEST \"test\"\nRUN echo $MY_VAR_123";
-        let result = parse_file(content, "test.hone");
+    fn test_timeout_statement_rejects_invalid_value() {
+        let input = r#"TEST "test"
+TIMEOUT soon
+RUN echo hello
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    file.errors.is_empty(),
-                    "Expected no errors for valid env key"
-                );
-                let envs: Vec<_> = file
-                    .nodes
-                    .iter()
-                    .filter_map(|n| {
-                        if let ASTNode::Env(e) = n {
-                            Some(e)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                assert_eq!(envs.len(), 1);
-                assert_eq!(envs[0].key, "MY_VAR_123");
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0].message.contains("Invalid timeout format"));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1453,72 +3691,105 @@ ASSERT stderr {} "hello""#,
     }
 
     #[test]
-    fn test_env_accepts_underscore_prefix() {
-        let content = "ENV _PRIVATE=value\nTEST \"test\"\nRUN echo $_PRIVATE";
-        let result = parse_file(content, "test.hone");
+    fn test_run_inline_timeout() {
+        let input = r#"TEST "test"
+RUN build: cargo build --release TIMEOUT 180s
+RUN echo TIMEOUT
+RUN grep -c TIMEOUT log.txt
+RUN echo "wait TIMEOUT 5s"
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
                 assert!(
                     file.errors.is_empty(),
-                    "Expected no errors for underscore-prefixed env key"
+                    "Unexpected errors: {:?}",
+                    file.errors
                 );
-                let envs: Vec<_> = file
+                let runs: Vec<_> = file
                     .nodes
                     .iter()
-                    .filter_map(|n| {
-                        if let ASTNode::Env(e) = n {
-                            Some(e)
-                        } else {
-                            None
-                        }
+                    .filter_map(|n| match n {
+                        ASTNode::Run(r) => Some(r),
+                        _ => None,
                     })
                     .collect();
-                assert_eq!(envs.len(), 1);
-                assert_eq!(envs[0].key, "_PRIVATE");
+                assert_eq!(runs[0].command, "cargo build --release");
+                assert_eq!(runs[0].name.as_deref(), Some("build"));
+                assert_eq!(
+                    runs[0].timeout.as_ref().map(|d| d.raw.as_str()),
+                    Some("180s")
+                );
+                assert_eq!(runs[1].command, "echo TIMEOUT");
+                assert!(runs[1].timeout.is_none());
+                // Not followed by a duration, or quoted: part of the command
+                assert_eq!(runs[2].command, "grep -c TIMEOUT log.txt");
+                assert!(runs[2].timeout.is_none());
+                assert_eq!(runs[3].command, "echo \"wait TIMEOUT 5s\"");
+                assert!(runs[3].timeout.is_none());
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_parse_file_empty() {
-        let result = parse_file("", "empty.hone");
+    fn test_in_shell_quotes() {
+        let command = r#"echo "a 'b" 'c "d' \"e f"#;
+        assert!(!in_shell_quotes(command, 4));
+        assert!(in_shell_quotes(command, command.find("'b").unwrap()));
+        assert!(in_shell_quotes(command, command.find("\"d").unwrap()));
+        assert!(!in_shell_quotes(command, command.find(" f").unwrap()));
+        assert!(in_shell_quotes("sh -c 'sleep 9 TIMEOUT 5s", 14));
+    }
 
-        match result {
+    #[test]
+    fn test_run_inline_timeout_rejects_zero() {
+        let input = r#"TEST "test"
+RUN sleep 1 TIMEOUT 0ms
+"#;
+
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(file.nodes.is_empty(), "Empty file should have no nodes");
-                assert!(file.pragmas.is_empty(), "Empty file should have no pragmas");
-                assert!(file.errors.is_empty(), "Empty file should have no errors");
-                assert!(
-                    file.warnings.is_empty(),
-                    "Empty file should have no warnings"
-                );
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0].message.contains("too small"));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
 
     #[test]
-    fn test_parse_file_only_pragmas() {
-        let content = "#!shell: /bin/bash\n#!timeout: 5s\n#!env: FOO=bar";
-        let result = parse_file(content, "pragmas.hone");
+    fn test_test_duration_assertion_parsed() {
+        let input = r#"TEST "test"
+RUN sleep 0
+ASSERT test.duration < 2s
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert_eq!(file.pragmas.len(), 3, "Should have 3 pragmas");
-                assert!(file.errors.is_empty(), "Should have no errors");
-                // Pragmas are also added to nodes
-                let pragma_nodes = file
+                assert!(
+                    file.errors.is_empty(),
+                    "Unexpected errors: {:?}",
+                    file.errors
+                );
+                let assert_node = file
                     .nodes
                     .iter()
-                    .filter(|n| matches!(n, ASTNode::Pragma(_)))
-                    .count();
-                assert_eq!(pragma_nodes, 3, "Should have 3 pragma nodes");
+                    .find_map(|n| match n {
+                        ASTNode::Assert(a) => Some(a),
+                        _ => None,
+                    })
+                    .unwrap();
+                match &assert_node.expression {
+                    AssertionExpression::TestDuration { predicate } => {
+                        assert_eq!(predicate.operator, ComparisonOperator::LessThan);
+                        assert_eq!(predicate.value.raw, "2s");
+                    }
+                    other => panic!("Expected TestDuration, got {:?}", other),
+                }
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success");
@@ -1527,69 +3798,88 @@ ASSERT stderr {} "hello""#,
     }
 
     #[test]
-    fn test_parse_file_only_comments() {
-        let content = "# Comment 1\n# Comment 2\n# Comment 3";
-        let result = parse_file(content, "comments.hone");
+    fn test_unknown_test_level_assertion_rejected() {
+        let input = r#"TEST "test"
+RUN true
+ASSERT test.exit_code == 0
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(file.pragmas.is_empty(), "Should have no pragmas");
-                assert!(file.errors.is_empty(), "Should have no errors");
-                let comment_nodes = file
-                    .nodes
-                    .iter()
-                    .filter(|n| matches!(n, ASTNode::Comment(_)))
-                    .count();
-                assert_eq!(comment_nodes, 3, "Should have 3 comment nodes");
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0]
+                    .message
+                    .contains("Unknown test-level assertion"));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
 
     #[test]
-    fn test_parse_file_only_whitespace() {
-        let content = "   \n\n   \n\t\t\n";
-        let result = parse_file(content, "whitespace.hone");
+    fn test_run_named_test_takes_test_target() {
+        let input = r#"TEST "named test"
+RUN test: true
+ASSERT test.duration < 1s
+ASSERT test.exit_code == 0
 
-        match result {
+TEST "no RUN named test"
+RUN true
+ASSERT test.duration < 1s
+ASSERT test.exit_code == 0
+"#;
+
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    file.nodes.is_empty(),
-                    "Whitespace-only file should have no nodes"
-                );
-                assert!(
-                    file.errors.is_empty(),
-                    "Whitespace-only file should have no errors"
-                );
+                let expressions: Vec<&AssertionExpression> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        ASTNode::Assert(assert) => Some(&assert.expression),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(expressions.len(), 3);
+                assert!(matches!(
+                    expressions[0],
+                    AssertionExpression::Duration { target: Some(target), .. } if target == "test"
+                ));
+                assert!(matches!(
+                    expressions[1],
+                    AssertionExpression::ExitCode { target: Some(target), .. } if target == "test"
+                ));
+                assert!(matches!(
+                    expressions[2],
+                    AssertionExpression::TestDuration { .. }
+                ));
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0]
+                    .message
+                    .starts_with("Unknown test-level assertion"));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
 
     #[test]
-    fn test_file_predicate_error_mentions_matches() {
-        // When an invalid file predicate is used, the error message should list ALL valid predicates
-        let content = r#"TEST "file predicate test"
-RUN echo hello > test.txt
-ASSERT file "test.txt" invalid_predicate"#;
-        let result = parse_file(content, "test.hone");
+    fn test_budget_pragma() {
+        let input = "#! budget: 30s\n#! budget: soon\n";
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for invalid file predicate"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("matches"),
-                    "Error message should mention 'matches' predicate. Got: {}",
-                    error_msg
-                );
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0].message.contains("Invalid budget format"));
+                assert_eq!(file.errors[0].line, 2);
+                match &file.nodes[0] {
+                    ASTNode::Pragma(p) => {
+                        assert_eq!(p.pragma_type, PragmaType::Budget);
+                        assert_eq!(p.value, "30s");
+                    }
+                    other => panic!("Expected pragma, got {:?}", other),
+                }
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1598,25 +3888,21 @@ ASSERT file "test.txt" invalid_predicate"#;
     }
 
     #[test]
-    fn test_exit_code_missing_number_error() {
-        // exit_code with operator but no number should produce a clear error
-        let content = r#"TEST "missing number"
-RUN true
-ASSERT exit_code == "#;
-        let result = parse_file(content, "test.hone");
+    fn test_hermetic_pragma() {
+        let input = "#! hermetic: false\n#! hermetic: yes\n";
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing number after exit_code operator"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("Expected number"),
-                    "Error should mention expected number. Got: {}",
-                    error_msg
-                );
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0].message.contains("Invalid hermetic pragma"));
+                assert_eq!(file.errors[0].line, 2);
+                match &file.nodes[0] {
+                    ASTNode::Pragma(p) => {
+                        assert_eq!(p.pragma_type, PragmaType::Hermetic);
+                        assert_eq!(p.value, "false");
+                    }
+                    other => panic!("Expected pragma, got {:?}", other),
+                }
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1625,25 +3911,57 @@ ASSERT exit_code == "#;
     }
 
     #[test]
-    fn test_exit_code_missing_operator_error() {
-        // exit_code without operator should produce a clear error
-        let content = r#"TEST "missing operator"
-RUN true
-ASSERT exit_code 0"#;
-        let result = parse_file(content, "test.hone");
+    fn test_stub_statement() {
+        let input = r#"TEST "stubs"
+STUB kubectl
+STUB kubectl STDOUT "pod-a\n" EXIT 1 STDERR 'warn'
+STUB git SCRIPT "echo \"git $1\""
+STUB ./bin/tool
+STUB curl EXIT 256
+STUB curl STDOUT "a" STDOUT "b"
+STUB curl SCRIPT "true" EXIT 0
+STUB curl --silent
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing operator in exit_code assertion"
+                let stubs: Vec<&StubNode> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        ASTNode::Stub(stub) => Some(stub),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(stubs.len(), 3);
+                assert_eq!(
+                    stubs[0].behavior,
+                    StubBehavior::Canned {
+                        stdout: String::new(),
+                        stderr: String::new(),
+                        exit_code: 0,
+                    }
                 );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("==") && error_msg.contains("!="),
-                    "Error should mention valid operators (== or !=). Got: {}",
-                    error_msg
+                assert_eq!(
+                    stubs[1].behavior,
+                    StubBehavior::Canned {
+                        stdout: "pod-a\n".to_string(),
+                        stderr: "warn".to_string(),
+                        exit_code: 1,
+                    }
+                );
+                assert_eq!(
+                    stubs[2].behavior,
+                    StubBehavior::Script("echo \"git $1\"".to_string())
                 );
+
+                let messages: Vec<&str> = file.errors.iter().map(|e| e.message.as_str()).collect();
+                assert_eq!(messages.len(), 5);
+                assert!(messages[0].starts_with("Invalid STUB command name: ./bin/tool"));
+                assert!(messages[1].starts_with("Invalid STUB exit code"));
+                assert_eq!(messages[2], "STDOUT may only be given once per STUB");
+                assert!(messages[3].starts_with("SCRIPT can't be combined"));
+                assert!(messages[4].starts_with("Unexpected text in STUB: --silent"));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1652,25 +3970,70 @@ ASSERT exit_code 0"#;
     }
 
     #[test]
-    fn test_duration_missing_operator_error() {
-        // duration without operator should produce a clear error
-        let content = r#"TEST "missing operator"
-RUN sleep 0.1
-ASSERT duration 200ms"#;
-        let result = parse_file(content, "test.hone");
+    fn test_stub_assertions_parsed() {
+        let input = r#"TEST "stubs"
+ASSERT stub kubectl called
+ASSERT stub kubectl called with "get pods"
+ASSERT stub kubectl called with /^apply /
+ASSERT stub kubectl call_count == 2
+ASSERT stub kubectl stdin contains "kind: Pod"
+ASSERT stub.stdout contains "x"
+ASSERT stub kubectl called twice
+ASSERT stub kubectl call_count == many
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
+                let expressions: Vec<&AssertionExpression> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        ASTNode::Assert(assert) => Some(&assert.expression),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(expressions.len(), 6);
+                let predicates: Vec<&StubPredicate> = expressions[..5]
+                    .iter()
+                    .map(|expression| match expression {
+                        AssertionExpression::Stub { command, predicate } => {
+                            assert_eq!(command, "kubectl");
+                            predicate
+                        }
+                        other => panic!("Expected stub assertion, got {:?}", other),
+                    })
+                    .collect();
+                assert_eq!(predicates[0], &StubPredicate::Called);
                 assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing operator in duration assertion"
+                    matches!(predicates[1], StubPredicate::CalledWith { value } if value.value == "get pods")
                 );
-                let error_msg = &file.errors[0].message;
                 assert!(
-                    error_msg.contains("comparison operator"),
-                    "Error should mention comparison operator. Got: {}",
-                    error_msg
+                    matches!(predicates[2], StubPredicate::CalledMatching { value } if value.pattern == "^apply ")
+                );
+                assert_eq!(
+                    predicates[3],
+                    &StubPredicate::CallCount {
+                        operator: ComparisonOperator::Equal,
+                        value: 2
+                    }
                 );
+                assert!(matches!(
+                    predicates[4],
+                    StubPredicate::Stdin(OutputPredicate::Contains { .. })
+                ));
+                // A RUN named stub is still a RUN target
+                assert!(matches!(
+                    expressions[5],
+                    AssertionExpression::Output { target: Some(target), .. } if target == "stub"
+                ));
+
+                assert_eq!(file.errors.len(), 2);
+                assert!(file.errors[0]
+                    .message
+                    .starts_with("Expected predicate (called"));
+                assert!(file.errors[1]
+                    .message
+                    .starts_with("Expected number of calls"));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1679,25 +4042,124 @@ ASSERT duration 200ms"#;
     }
 
     #[test]
-    fn test_duration_missing_value_error() {
-        // duration with operator but no value should produce a clear error
-        let content = r#"TEST "missing value"
-RUN sleep 0.1
-ASSERT duration < "#;
-        let result = parse_file(content, "test.hone");
+    fn test_http_statement() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("fixtures")).unwrap();
+        std::fs::write(dir.path().join("fixtures/items.json"), "[{\"id\": 1}]\n").unwrap();
+        let main = dir.path().join("api.hone");
+        std::fs::write(
+            &main,
+            r#"TEST "routes"
+HTTP GET /v1/items
+HTTP POST /v1/items STATUS 201 HEADER "Content-Type: application/json" BODY '{"id": 2}'
+HTTP GET /v1/items/1 FILE "fixtures/items.json"
+HTTP get /v1/items
+HTTP GET v1/items
+HTTP GET /v1/items STATUS 700
+HTTP GET /v1/items BODY "a" FILE "fixtures/items.json"
+HTTP GET /v1/items HEADER "no colon"
+HTTP GET /v1/items HEADER "Content-Length: 3"
+HTTP GET /v1/items FILE "missing.json"
+HTTP GET /v1/items REPLY "x"
+"#,
+        )
+        .unwrap();
+
+        let file = parse_path(&main);
+        let routes: Vec<&HttpNode> = file
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                ASTNode::Http(http) => Some(http),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(routes.len(), 3);
+        assert_eq!(routes[0].request.method, "GET");
+        assert_eq!(routes[0].request.path, "/v1/items");
+        assert_eq!(routes[0].status, 200);
+        assert!(routes[0].headers.is_empty());
+        assert!(routes[0].body.is_empty());
+        assert_eq!(routes[1].status, 201);
+        assert_eq!(
+            routes[1].headers,
+            vec![("Content-Type".to_string(), "application/json".to_string())]
+        );
+        assert_eq!(routes[1].body, b"{\"id\": 2}");
+        assert_eq!(routes[2].body, b"[{\"id\": 1}]\n");
+
+        let messages: Vec<&str> = file.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 8);
+        assert!(messages[0].starts_with("Invalid HTTP route: get /v1/items"));
+        assert!(messages[1].starts_with("Invalid HTTP route: GET v1/items"));
+        assert!(messages[2].starts_with("Invalid HTTP status"));
+        assert_eq!(
+            messages[3],
+            "Only one of BODY or FILE may be given per HTTP"
+        );
+        assert!(messages[4].starts_with("Invalid HTTP header: \"no colon\""));
+        assert!(messages[5].starts_with("HEADER can't set Content-Length"));
+        assert!(messages[6].starts_with("Cannot read HTTP body file missing.json"));
+        assert!(messages[7].starts_with("Unexpected text in HTTP: REPLY"));
+    }
 
-        match result {
+    #[test]
+    fn test_http_assertions_parsed() {
+        let input = r#"TEST "requests"
+ASSERT http request "POST /v1/items" received
+ASSERT http request "GET /v1/items?page=2" count >= 1
+ASSERT http request "POST /v1/items" body matches /"name":\s*"a"/
+ASSERT http.stdout contains "x"
+ASSERT http request "/v1/items" received
+ASSERT http request "POST /v1/items" sent
+ASSERT http "POST /v1/items" received
+"#;
+
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing value after duration operator"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("duration value"),
-                    "Error should mention expected duration value. Got: {}",
-                    error_msg
-                );
+                let expressions: Vec<&AssertionExpression> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        ASTNode::Assert(assert) => Some(&assert.expression),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(expressions.len(), 4);
+                let AssertionExpression::Http { request, predicate } = expressions[0] else {
+                    panic!("Expected http assertion, got {:?}", expressions[0]);
+                };
+                assert_eq!(request.method, "POST");
+                assert_eq!(request.path, "/v1/items");
+                assert_eq!(predicate, &HttpPredicate::Received);
+                assert!(matches!(
+                    expressions[1],
+                    AssertionExpression::Http {
+                        request,
+                        predicate: HttpPredicate::Count {
+                            operator: ComparisonOperator::GreaterThanOrEqual,
+                            value: 1
+                        }
+                    } if request.path == "/v1/items?page=2"
+                ));
+                assert!(matches!(
+                    expressions[2],
+                    AssertionExpression::Http {
+                        predicate: HttpPredicate::Body(OutputPredicate::Matches { .. }),
+                        ..
+                    }
+                ));
+                // A RUN named http is still a RUN target
+                assert!(matches!(
+                    expressions[3],
+                    AssertionExpression::Output { target: Some(target), .. } if target == "http"
+                ));
+
+                let messages: Vec<&str> = file.errors.iter().map(|e| e.message.as_str()).collect();
+                assert_eq!(messages.len(), 3);
+                assert!(messages[0].starts_with("Invalid HTTP request: \"/v1/items\""));
+                assert!(messages[1].starts_with("Expected predicate (received, count, body)"));
+                assert!(messages[2].starts_with("Expected \"request\" after \"http\""));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1706,52 +4168,61 @@ ASSERT duration < "#;
     }
 
     #[test]
-    fn test_output_contains_missing_string_error() {
-        // stdout contains without string should produce a clear error
-        let content = r#"TEST "missing string"
-RUN echo hello
-ASSERT stdout contains"#;
-        let result = parse_file(content, "test.hone");
+    fn test_resource_assertions_parsed() {
+        let input = r#"TEST "test"
+RUN build: cargo build
+ASSERT max_rss < 50MB
+ASSERT build.cpu_time <= 1.5s
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
                 assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing string after 'contains'"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("quoted string") && error_msg.contains("contains"),
-                    "Error should mention expected quoted string after contains. Got: {}",
-                    error_msg
+                    file.errors.is_empty(),
+                    "Unexpected errors: {:?}",
+                    file.errors
                 );
+                let asserts: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| match n {
+                        ASTNode::Assert(a) => Some(&a.expression),
+                        _ => None,
+                    })
+                    .collect();
+                match asserts[0] {
+                    AssertionExpression::MaxRss { target, predicate } => {
+                        assert!(target.is_none());
+                        assert_eq!(predicate.operator, ComparisonOperator::LessThan);
+                        assert_eq!(predicate.value.unit, MemoryUnit::Megabytes);
+                    }
+                    other => panic!("Expected MaxRss, got {:?}", other),
+                }
+                match asserts[1] {
+                    AssertionExpression::CpuTime { target, predicate } => {
+                        assert_eq!(target.as_deref(), Some("build"));
+                        assert_eq!(predicate.value.raw, "1.5s");
+                    }
+                    other => panic!("Expected CpuTime, got {:?}", other),
+                }
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_output_matches_missing_regex_error() {
-        // stdout matches without regex should produce a clear error
-        let content = r#"TEST "missing regex"
-RUN echo hello
-ASSERT stdout matches"#;
-        let result = parse_file(content, "test.hone");
+    fn test_max_rss_requires_memory_unit() {
+        let input = r#"TEST "test"
+RUN true
+ASSERT max_rss < 50
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing regex after 'matches'"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("regex") && error_msg.contains("matches"),
-                    "Error should mention expected regex literal after matches. Got: {}",
-                    error_msg
-                );
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0].message.contains("Expected memory size"));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1760,25 +4231,65 @@ ASSERT stdout matches"#;
     }
 
     #[test]
-    fn test_output_equals_missing_string_error() {
-        // stdout == without string should produce a clear error
-        let content = r#"TEST "missing string"
-RUN echo hello
-ASSERT stdout == "#;
-        let result = parse_file(content, "test.hone");
+    fn test_run_repeat_modifier() {
+        let input = r#"TEST "test"
+RUN startup: mycli --version REPEAT 20
+RUN build: make REPEAT 3 TIMEOUT 10s
+RUN check: make TIMEOUT 10s REPEAT 3
+RUN ./bench REPEAT x
+RUN echo "REPEAT 3"
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
                 assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing string after comparison operator"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("quoted string"),
-                    "Error should mention expected quoted string. Got: {}",
-                    error_msg
+                    file.errors.is_empty(),
+                    "Unexpected errors: {:?}",
+                    file.errors
                 );
+                let runs: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| match n {
+                        ASTNode::Run(r) => Some(r),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(runs[0].command, "mycli --version");
+                assert_eq!(runs[0].repeat, Some(20));
+                assert!(runs[0].timeout.is_none());
+                // Not followed by a number, or quoted: part of the command
+                assert_eq!(runs[3].command, "./bench REPEAT x");
+                assert_eq!(runs[4].command, "echo \"REPEAT 3\"");
+                for run in &runs[3..] {
+                    assert!(run.repeat.is_none());
+                }
+                for run in &runs[1..3] {
+                    assert_eq!(run.command, "make");
+                    assert_eq!(run.repeat, Some(3));
+                    assert_eq!(run.timeout.as_ref().map(|d| d.raw.as_str()), Some("10s"));
+                }
+            }
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success");
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_repeat_rejects_invalid_counts() {
+        let input = r#"TEST "test"
+RUN a: true REPEAT 0
+RUN b: true REPEAT 20000
+RUN c: true REPEAT 2 REPEAT 3
+"#;
+
+        match parse_file(input, "test.hone") {
+            ParseResult::Success { file } => {
+                assert_eq!(file.errors.len(), 3);
+                assert!(file.errors[0].message.contains("Invalid REPEAT count"));
+                assert!(file.errors[1].message.contains("Invalid REPEAT count"));
+                assert!(file.errors[2].message.contains("only be given once"));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1787,25 +4298,50 @@ ASSERT stdout == "#;
     }
 
     #[test]
-    fn test_file_missing_path_error() {
-        // file assertion without path should produce a clear error
-        let content = r#"TEST "missing path"
-RUN touch test.txt
-ASSERT file exists"#;
-        let result = parse_file(content, "test.hone");
+    fn test_duration_statistic_assertions() {
+        let input = r#"TEST "test"
+RUN startup: mycli --version REPEAT 20
+ASSERT startup.duration.p95 < 200ms
+ASSERT duration.median < 100ms
+ASSERT duration.p50 < 100ms
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing file path"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("quoted file path"),
-                    "Error should mention expected quoted file path. Got: {}",
-                    error_msg
-                );
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0]
+                    .message
+                    .contains("Unknown duration statistic: p50"));
+
+                let asserts: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| match n {
+                        ASTNode::Assert(a) => Some(&a.expression),
+                        _ => None,
+                    })
+                    .collect();
+                match asserts[0] {
+                    AssertionExpression::DurationStat {
+                        target,
+                        statistic,
+                        predicate,
+                    } => {
+                        assert_eq!(target.as_deref(), Some("startup"));
+                        assert_eq!(*statistic, DurationStatistic::P95);
+                        assert_eq!(predicate.value.raw, "200ms");
+                    }
+                    other => panic!("Expected DurationStat, got {:?}", other),
+                }
+                match asserts[1] {
+                    AssertionExpression::DurationStat {
+                        target, statistic, ..
+                    } => {
+                        assert!(target.is_none());
+                        assert_eq!(*statistic, DurationStatistic::Median);
+                    }
+                    other => panic!("Expected DurationStat, got {:?}", other),
+                }
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1814,52 +4350,96 @@ ASSERT file exists"#;
     }
 
     #[test]
-    fn test_file_contains_missing_string_error() {
-        // file contains without string should produce a clear error
-        let content = r#"TEST "missing string"
-RUN echo hello > test.txt
-ASSERT file "test.txt" contains"#;
-        let result = parse_file(content, "test.hone");
+    fn test_byte_assertions_parsed() {
+        let input = r#"TEST "test"
+RUN archive: gzip -c data.txt
+ASSERT stdout.bytes starts_with hex"1f8b08"
+ASSERT archive.stdout.length > 20
+ASSERT stderr.bytes != hex"00 ff"
+ASSERT file "data.txt" sha256 == "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
                 assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing string after file contains"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("quoted string") && error_msg.contains("contains"),
-                    "Error should mention expected quoted string after contains. Got: {}",
-                    error_msg
+                    file.errors.is_empty(),
+                    "Unexpected errors: {:?}",
+                    file.errors
                 );
+                let asserts: Vec<_> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|n| match n {
+                        ASTNode::Assert(a) => Some(&a.expression),
+                        _ => None,
+                    })
+                    .collect();
+                match asserts[0] {
+                    AssertionExpression::Bytes {
+                        target,
+                        selector,
+                        predicate: BytesPredicate::StartsWith { value },
+                    } => {
+                        assert!(target.is_none());
+                        assert_eq!(*selector, OutputSelector::Stdout);
+                        assert_eq!(value.bytes, vec![0x1f, 0x8b, 0x08]);
+                    }
+                    other => panic!("Expected Bytes, got {:?}", other),
+                }
+                match asserts[1] {
+                    AssertionExpression::Length {
+                        target, predicate, ..
+                    } => {
+                        assert_eq!(target.as_deref(), Some("archive"));
+                        assert_eq!(predicate.operator, ComparisonOperator::GreaterThan);
+                        assert_eq!(predicate.value, 20);
+                    }
+                    other => panic!("Expected Length, got {:?}", other),
+                }
+                match asserts[2] {
+                    AssertionExpression::Bytes {
+                        selector,
+                        predicate: BytesPredicate::Equals { operator, value },
+                        ..
+                    } => {
+                        assert_eq!(*selector, OutputSelector::Stderr);
+                        assert_eq!(*operator, StringComparisonOperator::NotEqual);
+                        assert_eq!(value.bytes, vec![0x00, 0xff]);
+                    }
+                    other => panic!("Expected Bytes, got {:?}", other),
+                }
+                assert!(matches!(
+                    asserts[3],
+                    AssertionExpression::File {
+                        predicate: FilePredicate::Sha256 { .. },
+                        ..
+                    }
+                ));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success with errors embedded");
+                panic!("Parser should always return Success");
             }
         }
     }
 
     #[test]
-    fn test_file_matches_missing_regex_error() {
-        // file matches without regex should produce a clear error
-        let content = r#"TEST "missing regex"
-RUN echo hello > test.txt
-ASSERT file "test.txt" matches"#;
-        let result = parse_file(content, "test.hone");
+    fn test_byte_assertion_errors() {
+        let input = r#"TEST "test"
+RUN true
+ASSERT stdout.bytes == "1f8b"
+ASSERT stdout.bytes == hex"1f8"
+ASSERT stdout.length == big
+ASSERT file "a.bin" sha256 == "abc123"
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Expected error for missing regex after file matches"
-                );
-                let error_msg = &file.errors[0].message;
-                assert!(
-                    error_msg.contains("regex") && error_msg.contains("matches"),
-                    "Error should mention expected regex literal. Got: {}",
-                    error_msg
-                );
+                let messages: Vec<_> = file.errors.iter().map(|e| e.message.as_str()).collect();
+                assert_eq!(messages.len(), 4, "{:?}", messages);
+                assert!(messages[0].contains("Expected hex literal"));
+                assert!(messages[1].contains("Expected hex literal"));
+                assert!(messages[2].contains("Expected byte count"));
+                assert!(messages[3].contains("Invalid SHA-256 digest"));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -1868,170 +4448,203 @@ ASSERT file "test.txt" matches"#;
     }
 
     #[test]
-    fn test_named_target_assertion_stdout() {
-        let content = r#"TEST "named target"
-RUN build: echo "hello"
-ASSERT build.stdout contains "hello""#;
-        let result = parse_file(content, "test.hone");
+    fn test_max_output_pragma_and_partial_assertions() {
+        let input = r#"#! max_output: 1KB
+#! max_output: lots
+TEST "test"
+RUN head -c 5000 /dev/zero
+ASSERT stdout contains "abc" PARTIAL
+ASSERT stdout.length == 5000
+ASSERT exit_code == 0 PARTIAL
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(file.errors.is_empty(), "Expected no errors");
-                let assert_nodes: Vec<_> = file
+                assert_eq!(file.pragmas.len(), 1);
+                assert_eq!(file.pragmas[0].pragma_type, PragmaType::MaxOutput);
+                assert_eq!(file.pragmas[0].value, "1KB");
+
+                let asserts: Vec<_> = file
                     .nodes
                     .iter()
-                    .filter_map(|n| {
-                        if let ASTNode::Assert(a) = n {
-                            Some(a)
-                        } else {
-                            None
-                        }
+                    .filter_map(|n| match n {
+                        ASTNode::Assert(a) => Some(a),
+                        _ => None,
                     })
                     .collect();
-                assert_eq!(assert_nodes.len(), 1);
-
-                if let AssertionExpression::Output {
-                    target, selector, ..
-                } = &assert_nodes[0].expression
-                {
-                    assert_eq!(target.as_deref(), Some("build"));
-                    assert!(matches!(selector, OutputSelector::Stdout));
-                } else {
-                    panic!("Expected Output assertion expression");
-                }
+                assert_eq!(asserts.len(), 2);
+                assert!(asserts[0].partial);
+                assert!(matches!(
+                    &asserts[0].expression,
+                    AssertionExpression::Output { predicate: OutputPredicate::Contains { value }, .. }
+                        if value.value == "abc"
+                ));
+                assert!(!asserts[1].partial);
+
+                let messages: Vec<_> = file.errors.iter().map(|e| e.message.as_str()).collect();
+                assert_eq!(messages.len(), 2, "{:?}", messages);
+                assert!(messages[0].contains("Invalid max_output size"));
+                assert!(messages[1].contains("PARTIAL only applies"));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
 
     #[test]
-    fn test_named_target_assertion_exit_code() {
-        let content = r#"TEST "named target exit"
-RUN build: echo "hello"
-ASSERT build.exit_code == 0"#;
-        let result = parse_file(content, "test.hone");
+    fn test_shells_pragma() {
+        let input = r#"#! shells: bash,zsh , dash
+#! shells: bash,,zsh
+TEST "test"
+RUN true
+"#;
 
-        match result {
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(file.errors.is_empty(), "Expected no errors");
-                let assert_nodes: Vec<_> = file
-                    .nodes
-                    .iter()
-                    .filter_map(|n| {
-                        if let ASTNode::Assert(a) = n {
-                            Some(a)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                assert_eq!(assert_nodes.len(), 1);
-
-                if let AssertionExpression::ExitCode { target, predicate } =
-                    &assert_nodes[0].expression
-                {
-                    assert_eq!(target.as_deref(), Some("build"));
-                    assert_eq!(predicate.value, 0);
-                } else {
-                    panic!("Expected ExitCode assertion expression");
-                }
+                assert_eq!(file.pragmas.len(), 1);
+                assert_eq!(file.pragmas[0].pragma_type, PragmaType::Shells);
+                assert_eq!(file.pragmas[0].value, "bash, zsh, dash");
+                assert_eq!(file.errors.len(), 1);
+                assert!(file.errors[0].message.contains("Invalid shells pragma"));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
 
     #[test]
-    fn test_named_target_with_hyphen() {
-        let content = r#"TEST "hyphenated target"
-RUN my-build: echo "test"
-ASSERT my-build.stdout contains "test""#;
-        let result = parse_file(content, "test.hone");
+    fn test_parameterized_test() {
+        let input = r#"TEST "convert {fmt}" WITH fmt IN json, yaml, "to ml"
+RUN convert --to "$fmt"
 
-        match result {
+TEST "{fmt} uses {ext}" WITH fmt, ext IN (json, .json), ('yaml', ".yml, .yaml")
+RUN true
+"#;
+
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(file.errors.is_empty(), "Expected no errors");
-                let assert_nodes: Vec<_> = file
+                assert!(file.errors.is_empty(), "{:?}", file.errors);
+                let tests: Vec<&TestNode> = file
                     .nodes
                     .iter()
-                    .filter_map(|n| {
-                        if let ASTNode::Assert(a) = n {
-                            Some(a)
-                        } else {
-                            None
-                        }
+                    .filter_map(|node| match node {
+                        ASTNode::Test(test) => Some(test),
+                        _ => None,
                     })
                     .collect();
-                assert_eq!(assert_nodes.len(), 1);
 
-                if let AssertionExpression::Output { target, .. } = &assert_nodes[0].expression {
-                    assert_eq!(target.as_deref(), Some("my-build"));
-                } else {
-                    panic!("Expected Output assertion expression");
-                }
+                let single = tests[0].params.as_ref().unwrap();
+                assert_eq!(tests[0].name, "convert {fmt}");
+                assert_eq!(single.names, vec!["fmt"]);
+                assert_eq!(single.rows, vec![vec!["json"], vec!["yaml"], vec!["to ml"]]);
+                assert_eq!(
+                    single.expand_name(&tests[0].name, &single.rows[2]),
+                    "convert to ml"
+                );
+
+                let table = tests[1].params.as_ref().unwrap();
+                assert_eq!(table.names, vec!["fmt", "ext"]);
+                assert_eq!(
+                    table.rows,
+                    vec![vec!["json", ".json"], vec!["yaml", ".yml, .yaml"]]
+                );
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
 
     #[test]
-    fn test_named_target_duration() {
-        let content = r#"TEST "duration target"
-RUN fast: echo "quick"
-ASSERT fast.duration < 100ms"#;
-        let result = parse_file(content, "test.hone");
+    fn test_test_modifiers() {
+        let input = r#"SKIP TEST "skipped"
+RUN true
 
-        match result {
+ONLY TEST "focused {n}" WITH n IN 1, 2
+
+TODO TEST "later"
+
+TEST "plain"
+"#;
+
+        match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(file.errors.is_empty(), "Expected no errors");
-                let assert_nodes: Vec<_> = file
+                assert!(file.errors.is_empty(), "{:?}", file.errors);
+                let tests: Vec<(&str, Option<TestModifier>)> = file
                     .nodes
                     .iter()
-                    .filter_map(|n| {
-                        if let ASTNode::Assert(a) = n {
-                            Some(a)
-                        } else {
-                            None
-                        }
+                    .filter_map(|node| match node {
+                        ASTNode::Test(test) => Some((test.name.as_str(), test.modifier)),
+                        _ => None,
                     })
                     .collect();
-                assert_eq!(assert_nodes.len(), 1);
 
-                if let AssertionExpression::Duration { target, .. } = &assert_nodes[0].expression {
-                    assert_eq!(target.as_deref(), Some("fast"));
-                } else {
-                    panic!("Expected Duration assertion expression");
-                }
+                assert_eq!(
+                    tests,
+                    vec![
+                        ("skipped", Some(TestModifier::Skip)),
+                        ("focused {n}", Some(TestModifier::Only)),
+                        ("later", Some(TestModifier::Todo)),
+                        ("plain", None),
+                    ]
+                );
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
 
     #[test]
-    fn test_timeout_pragma_rejects_zero() {
-        let input = r#"#! timeout: 0s
-TEST "test"
-RUN echo hello
+    fn test_skip_if_conditions() {
+        let input = r#"#! require: git,  jq
+TEST "conditional"
+SKIP IF missing docker
+SKIP IF unset DATABASE_URL
+SKIP IF env CI == "true"
+SKIP IF uname != Linux
+SKIP IF os == Linux
+SKIP IF env CI
+RUN true
 "#;
 
         match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Should have parse error for 0s timeout"
-                );
-                assert!(
-                    file.errors[0].message.contains("too small"),
-                    "Error message should mention 'too small': {}",
-                    file.errors[0].message
+                assert_eq!(file.pragmas[0].pragma_type, PragmaType::Require);
+                assert_eq!(file.pragmas[0].value, "git, jq");
+
+                let conditions: Vec<&SkipCondition> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        ASTNode::SkipIf(skip_if) => Some(&skip_if.condition),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(
+                    conditions,
+                    vec![
+                        &SkipCondition::Missing("docker".to_string()),
+                        &SkipCondition::Unset("DATABASE_URL".to_string()),
+                        &SkipCondition::Env {
+                            name: "CI".to_string(),
+                            operator: StringComparisonOperator::Equal,
+                            value: "true".to_string(),
+                        },
+                        &SkipCondition::Uname {
+                            operator: StringComparisonOperator::NotEqual,
+                            value: "Linux".to_string(),
+                        },
+                    ]
                 );
+
+                assert_eq!(file.errors.len(), 2);
+                assert!(file.errors[0]
+                    .message
+                    .contains("Invalid SKIP IF condition: os == Linux"));
+                assert_eq!(file.errors[1].line, 8);
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -2040,23 +4653,39 @@ RUN echo hello
     }
 
     #[test]
-    fn test_timeout_pragma_rejects_near_zero_ms() {
-        let input = r#"#! timeout: 0.5ms
-TEST "test"
-RUN echo hello
+    fn test_test_tags() {
+        let input = r#"TEST "fast" @smoke @network-free @smoke
+TEST "convert {fmt}" @slow WITH fmt IN json, yaml
+TEST "bad tag" @no!pe
+TEST "reserved" @not
+TEST "stray" slow
 "#;
 
         match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(
-                    !file.errors.is_empty(),
-                    "Should have parse error for 0.5ms timeout"
-                );
-                assert!(
-                    file.errors[0].message.contains("too small"),
-                    "Error message should mention 'too small': {}",
-                    file.errors[0].message
-                );
+                let tests: Vec<&TestNode> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        ASTNode::Test(test) => Some(test),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(tests.len(), 3);
+                assert_eq!(tests[0].tags, vec!["smoke", "network-free"]);
+                assert_eq!(tests[1].tags, vec!["slow"]);
+                assert_eq!(tests[1].params.as_ref().unwrap().rows.len(), 2);
+                assert_eq!(tests[2].name, "stray");
+                assert!(tests[2].tags.is_empty());
+
+                let messages: Vec<&str> = file.errors.iter().map(|e| e.message.as_str()).collect();
+                assert_eq!(messages.len(), 2);
+                assert!(messages[0].starts_with("Invalid tag: @no!pe"));
+                assert!(messages[1].contains("operator in --tag expressions"));
+                assert_eq!(file.warnings.len(), 1);
+                assert!(file.warnings[0]
+                    .message
+                    .starts_with("Ignoring text after TEST name: slow"));
             }
             ParseResult::Failure { .. } => {
                 panic!("Parser should always return Success with errors embedded");
@@ -2065,40 +4694,245 @@ RUN echo hello
     }
 
     #[test]
-    fn test_timeout_pragma_accepts_1ms() {
-        let input = r#"#! timeout: 1ms
-TEST "test"
-RUN echo hello
-"#;
+    fn test_parameterized_test_errors() {
+        let cases = [
+            (
+                r#"TEST "a {fmt}" WITH fmt json"#,
+                "Expected WITH <name> IN <values>",
+            ),
+            (
+                r#"TEST "a {fmt}" WITH 1fmt IN json"#,
+                "Invalid parameter name",
+            ),
+            (
+                r#"TEST "a {x}" WITH x, x IN (1, 2)"#,
+                "Duplicate parameter name",
+            ),
+            (
+                r#"TEST "a {fmt}" WITH fmt IN json,"#,
+                "Empty parameter value",
+            ),
+            (
+                r#"TEST "a {a}" WITH a, b IN (1, 2), (3)"#,
+                "has 1 value(s) but WITH declares 2",
+            ),
+            (
+                r#"TEST "a {format}" WITH fmt IN json"#,
+                "Unknown parameter {format}",
+            ),
+            (
+                r#"TEST "a {a}" WITH a, b IN (1, 2), (1, 3)"#,
+                "More than one parameter row is named \"a 1\"",
+            ),
+        ];
 
-        match parse_file(input, "test.hone") {
-            ParseResult::Success { file } => {
-                assert!(file.errors.is_empty(), "Should accept 1ms timeout");
-                assert_eq!(file.pragmas.len(), 1);
-                assert_eq!(file.pragmas[0].value, "1ms");
-            }
-            ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+        for (input, expected) in cases {
+            match parse_file(input, "test.hone") {
+                ParseResult::Success { file } => {
+                    assert_eq!(file.errors.len(), 1, "{}", input);
+                    assert!(
+                        file.errors[0].message.contains(expected),
+                        "{}: {}",
+                        input,
+                        file.errors[0].message
+                    );
+                }
+                ParseResult::Failure { .. } => {
+                    panic!("Parser should always return Success with errors embedded");
+                }
             }
         }
     }
 
     #[test]
-    fn test_timeout_pragma_accepts_valid_seconds() {
-        let input = r#"#! timeout: 30s
-TEST "test"
-RUN echo hello
+    fn test_retries_pragma_and_retry_statement() {
+        let input = r#"#! retries: 2
+#! retries: many
+TEST "flaky daemon"
+RETRY 3
+RETRY 11
+RUN true
 "#;
 
         match parse_file(input, "test.hone") {
             ParseResult::Success { file } => {
-                assert!(file.errors.is_empty(), "Should accept 30s timeout");
                 assert_eq!(file.pragmas.len(), 1);
-                assert_eq!(file.pragmas[0].value, "30s");
+                assert_eq!(file.pragmas[0].pragma_type, PragmaType::Retries);
+                assert_eq!(file.pragmas[0].value, "2");
+
+                let retries: Vec<u32> = file
+                    .nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        ASTNode::Retry(retry) => Some(retry.count),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(retries, vec![3]);
+
+                assert_eq!(file.errors.len(), 2);
+                assert!(file.errors[0]
+                    .message
+                    .contains("Invalid retries count: many"));
+                assert!(file.errors[1].message.contains("Invalid RETRY count: 11"));
             }
             ParseResult::Failure { .. } => {
-                panic!("Parser should always return Success");
+                panic!("Parser should always return Success with errors embedded");
+            }
+        }
+    }
+
+    fn parse_path(path: &std::path::Path) -> ParsedFile {
+        let content = std::fs::read_to_string(path).unwrap();
+        match parse_file(&content, &path.to_string_lossy()) {
+            ParseResult::Success { file } => file,
+            ParseResult::Failure { .. } => {
+                panic!("Parser should always return Success with errors embedded");
             }
         }
     }
+
+    #[test]
+    fn test_import_resolves_relative_to_importing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("common")).unwrap();
+        std::fs::create_dir_all(dir.path().join("suite")).unwrap();
+        std::fs::write(
+            dir.path().join("common/setup.hone"),
+            "#! timeout: 5s\nENV MODE=test\nRUN mkdir -p out\n",
+        )
+        .unwrap();
+        let main = dir.path().join("suite/main.hone");
+        std::fs::write(
+            &main,
+            "IMPORT \"../common/setup.hone\"\nTEST \"uses setup\"\nRUN echo $MODE\n",
+        )
+        .unwrap();
+
+        let file = parse_path(&main);
+        assert!(file.errors.is_empty(), "{:?}", file.errors);
+        assert_eq!(file.pragmas.len(), 1);
+        assert_eq!(file.pragmas[0].pragma_type, PragmaType::Timeout);
+
+        let ASTNode::Import(import) = &file.nodes[0] else {
+            panic!("expected an IMPORT node, got {:?}", file.nodes[0]);
+        };
+        assert_eq!(import.path, "../common/setup.hone");
+        assert_eq!(import.line, 1);
+        assert_eq!(import.nodes.len(), 3);
+        assert_eq!(import.nodes[2].line(), 3);
+        assert!(std::path::Path::new(&import.resolved).ends_with("suite/../common/setup.hone"));
+    }
+
+    #[test]
+    fn test_import_errors_keep_their_file_and_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = dir.path().join("shared.hone");
+        std::fs::write(&shared, "RUN echo ok\nASSERT nonsense\nTEST \"nested\"\n").unwrap();
+        let main = dir.path().join("main.hone");
+        std::fs::write(
+            &main,
+            "# setup\nIMPORT shared.hone\nIMPORT 'missing.hone'\n",
+        )
+        .unwrap();
+
+        let file = parse_path(&main);
+        let errors: Vec<(String, usize)> = file
+            .errors
+            .iter()
+            .map(|e| {
+                let name = std::path::Path::new(&e.filename).file_name().unwrap();
+                (name.to_string_lossy().to_string(), e.line)
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("main.hone".to_string(), 2),
+                ("shared.hone".to_string(), 2),
+                ("shared.hone".to_string(), 3),
+                ("main.hone".to_string(), 3),
+            ]
+        );
+        assert_eq!(
+            file.errors[0].message,
+            "Imported file shared.hone has 2 errors"
+        );
+        assert!(file.errors[2]
+            .message
+            .starts_with("TEST blocks can't be imported"));
+        assert!(file.errors[3]
+            .message
+            .starts_with("Cannot read imported file missing.hone"));
+    }
+
+    #[test]
+    fn test_imported_run_names_are_known_to_the_importer() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("shared.hone"), "RUN test: echo shared\n").unwrap();
+        let main = dir.path().join("main.hone");
+        std::fs::write(
+            &main,
+            "TEST \"t\"\nIMPORT shared.hone\nASSERT test.exit_code == 0\nRUN test: true\n",
+        )
+        .unwrap();
+
+        let file = parse_path(&main);
+        let ASTNode::Assert(assert) = &file.nodes[2] else {
+            panic!("expected an ASSERT node, got {:?}", file.nodes[2]);
+        };
+        assert!(matches!(
+            &assert.expression,
+            AssertionExpression::ExitCode { target: Some(target), .. } if target == "test"
+        ));
+        let messages: Vec<&str> = file.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].starts_with("Duplicate RUN name: \"test\""));
+    }
+
+    #[test]
+    fn test_import_cycle_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.hone");
+        std::fs::write(&a, "IMPORT \"b.hone\"\nRUN echo a\n").unwrap();
+        std::fs::write(dir.path().join("b.hone"), "IMPORT \"a.hone\"\n").unwrap();
+
+        let file = parse_path(&a);
+        let messages: Vec<&str> = file.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Imported file b.hone has 1 error",
+                "Import cycle: a.hone -> b.hone -> a.hone",
+            ]
+        );
+        assert!(file.errors[1].filename.ends_with("b.hone"));
+    }
+
+    #[test]
+    fn test_env_file_pragma() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".env.test"), "CI=true\n").unwrap();
+        std::fs::write(dir.path().join(".env.bad"), "CI=true\noops\n").unwrap();
+        let main = dir.path().join("main.hone");
+        std::fs::write(
+            &main,
+            "#! env_file: .env.test\n#! env_file: \".env.bad\"\n#! env_file: .env.missing\n",
+        )
+        .unwrap();
+
+        let file = parse_path(&main);
+        assert_eq!(file.pragmas.len(), 1);
+        assert_eq!(file.pragmas[0].pragma_type, PragmaType::EnvFile);
+        assert_eq!(
+            file.pragmas[0].value,
+            dir.path().join(".env.test").to_string_lossy()
+        );
+
+        assert_eq!(file.errors.len(), 2);
+        assert_eq!(file.errors[0].line, 2);
+        assert!(file.errors[0].message.starts_with("Invalid env file"));
+        assert!(file.errors[0].message.contains(".env.bad:2"));
+        assert!(file.errors[1].message.starts_with("Cannot read env file"));
+    }
 }
@@ -0,0 +1,393 @@
+use crate::runner::sentinel::{generate_shell_wrapper, CONTROL_FD, SENTINEL_PREFIX};
+use std::path::Path;
+
+/// What hone needs to know to drive a particular shell: how to start it, and
+/// the syntax of each command it sends besides the user's own.
+///
+/// Every message a shell sends back goes to the control channel on
+/// `CONTROL_FD` as a single line, so stdout carries only command output.
+pub trait ShellAdapter: Send + Sync {
+    /// Name used in error messages
+    fn name(&self) -> &'static str;
+
+    /// Flags that start the shell without reading the user's rc files.
+    fn startup_flags(&self) -> &'static [&'static str];
+
+    /// Environment set before the shell starts, on top of the file's `env` pragmas.
+    fn startup_env(&self) -> &'static [(&'static str, &'static str)] {
+        &[("PS1", "")]
+    }
+
+    /// Writes `marker` as a line once the shell has run everything sent before it.
+    fn readiness_probe(&self, marker: &str) -> String;
+
+    /// Writes `marker` followed by the shell's working directory as a line.
+    fn cwd_query(&self, marker: &str) -> String;
+
+    /// Exports `key` with the literal `value`, without any expansion.
+    fn export_env(&self, key: &str, value: &str) -> String;
+
+    /// Puts the directory `dir` in front of the shell's current PATH.
+    fn prepend_path(&self, dir: &str) -> String;
+
+    /// Runs `command` with stderr written to `stderr_path`, then writes the
+    /// sentinel for `run_id` with the command's exit code. With a
+    /// `rusage_helper`, the command's peak memory is reported first where the
+    /// shell supports it; see [`crate::runner::resources::report_rusage`].
+    fn wrapper(
+        &self,
+        command: &str,
+        run_id: &str,
+        stderr_path: &str,
+        rusage_helper: Option<&str>,
+    ) -> String;
+}
+
+/// Shells with POSIX `sh` syntax: bash, zsh, sh, dash and ksh.
+pub struct PosixShell {
+    name: &'static str,
+    flags: &'static [&'static str],
+}
+
+pub struct Fish;
+
+pub struct Nushell;
+
+static BASH: PosixShell = PosixShell {
+    name: "bash",
+    flags: &["--norc", "--noprofile"],
+};
+static ZSH: PosixShell = PosixShell {
+    name: "zsh",
+    flags: &["--no-rcs"],
+};
+// Non-interactive sh, dash and ksh don't read rc files
+static SH: PosixShell = PosixShell {
+    name: "sh",
+    flags: &[],
+};
+static DASH: PosixShell = PosixShell {
+    name: "dash",
+    flags: &[],
+};
+static KSH: PosixShell = PosixShell {
+    name: "ksh",
+    flags: &[],
+};
+
+const SUPPORTED_SHELLS: &str = "bash, zsh, sh, dash, ksh, fish, nu";
+
+/// Finds the adapter for a shell by its executable name.
+pub fn shell_adapter(shell_path: &str) -> Result<&'static dyn ShellAdapter, String> {
+    let shell_name = Path::new(shell_path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+
+    match shell_name {
+        "bash" => Ok(&BASH),
+        "zsh" => Ok(&ZSH),
+        "sh" => Ok(&SH),
+        "dash" => Ok(&DASH),
+        "ksh" | "ksh93" | "mksh" => Ok(&KSH),
+        "fish" => Ok(&Fish),
+        "nu" => Ok(&Nushell),
+        _ => Err(format!(
+            "Unsupported shell: {}. Supported shells are {}; choose one with --shell or a `#! shell:` pragma",
+            shell_path, SUPPORTED_SHELLS
+        )),
+    }
+}
+
+pub(crate) fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Nushell raw string, which takes its content literally. Uses enough `#`s
+/// that the content can't end it early.
+fn nu_quote(value: &str) -> String {
+    let mut hashes = String::from("#");
+    while value.contains(&format!("'{}", hashes)) {
+        hashes.push('#');
+    }
+    format!("r{}'{}'{}", hashes, value, hashes)
+}
+
+impl ShellAdapter for PosixShell {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn startup_flags(&self) -> &'static [&'static str] {
+        self.flags
+    }
+
+    fn readiness_probe(&self, marker: &str) -> String {
+        format!("printf '%s\\n' {} >&{}", posix_quote(marker), CONTROL_FD)
+    }
+
+    fn cwd_query(&self, marker: &str) -> String {
+        format!(
+            "printf '%s%s\\n' {} \"$PWD\" >&{}",
+            posix_quote(marker),
+            CONTROL_FD
+        )
+    }
+
+    fn export_env(&self, key: &str, value: &str) -> String {
+        format!("export {}={}", key, posix_quote(value))
+    }
+
+    fn prepend_path(&self, dir: &str) -> String {
+        format!("export PATH={}:\"$PATH\"", posix_quote(dir))
+    }
+
+    fn wrapper(
+        &self,
+        command: &str,
+        run_id: &str,
+        stderr_path: &str,
+        rusage_helper: Option<&str>,
+    ) -> String {
+        generate_shell_wrapper(command, run_id, stderr_path, rusage_helper)
+    }
+}
+
+impl ShellAdapter for Fish {
+    fn name(&self) -> &'static str {
+        "fish"
+    }
+
+    fn startup_flags(&self) -> &'static [&'static str] {
+        &["--no-config"]
+    }
+
+    fn readiness_probe(&self, marker: &str) -> String {
+        format!("printf '%s\\n' {} >&{}", fish_quote(marker), CONTROL_FD)
+    }
+
+    fn cwd_query(&self, marker: &str) -> String {
+        format!(
+            "printf '%s%s\\n' {} \"$PWD\" >&{}",
+            fish_quote(marker),
+            CONTROL_FD
+        )
+    }
+
+    fn export_env(&self, key: &str, value: &str) -> String {
+        format!("set -gx {} {}", key, fish_quote(value))
+    }
+
+    fn prepend_path(&self, dir: &str) -> String {
+        format!("set -gx PATH {} $PATH", fish_quote(dir))
+    }
+
+    fn wrapper(
+        &self,
+        command: &str,
+        run_id: &str,
+        stderr_path: &str,
+        _rusage_helper: Option<&str>,
+    ) -> String {
+        // `begin ... end` keeps the command in the current shell, like `{ ...; }`
+        [
+            "begin".to_string(),
+            command.to_string(),
+            format!(
+                "end 2> {} {}>&- < /dev/null",
+                fish_quote(stderr_path),
+                CONTROL_FD
+            ),
+            "set HONE_EC $status".to_string(),
+            format!(
+                "printf '{}\\x1f%s\\x1f%d\\x1f%s\\n' {} $HONE_EC (date +%s%3N) >&{}",
+                SENTINEL_PREFIX,
+                fish_quote(run_id),
+                CONTROL_FD
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+/// Nushell can't redirect to a numbered descriptor, so it writes control
+/// lines through `/dev/fd`. For the same reason commands keep `CONTROL_FD`
+/// open while they run.
+impl ShellAdapter for Nushell {
+    fn name(&self) -> &'static str {
+        "nu"
+    }
+
+    fn startup_flags(&self) -> &'static [&'static str] {
+        &["--no-config-file", "--no-history"]
+    }
+
+    fn startup_env(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("PROMPT_COMMAND", ""),
+            ("PROMPT_COMMAND_RIGHT", ""),
+            ("PROMPT_INDICATOR", ""),
+            ("PROMPT_MULTILINE_INDICATOR", ""),
+        ]
+    }
+
+    fn readiness_probe(&self, marker: &str) -> String {
+        format!(
+            "$\"({})\\n\" | save --append /dev/fd/{}",
+            nu_quote(marker),
+            CONTROL_FD
+        )
+    }
+
+    fn cwd_query(&self, marker: &str) -> String {
+        format!(
+            "$\"({})($env.PWD)\\n\" | save --append /dev/fd/{}",
+            nu_quote(marker),
+            CONTROL_FD
+        )
+    }
+
+    fn export_env(&self, key: &str, value: &str) -> String {
+        format!("$env.{} = {}", key, nu_quote(value))
+    }
+
+    fn prepend_path(&self, dir: &str) -> String {
+        format!("$env.PATH = ($env.PATH | prepend {})", nu_quote(dir))
+    }
+
+    fn wrapper(
+        &self,
+        command: &str,
+        run_id: &str,
+        stderr_path: &str,
+        _rusage_helper: Option<&str>,
+    ) -> String {
+        // `--env` keeps `cd` and `$env` changes, like running in the current shell.
+        // Nushell has no `<`, so /dev/null is piped in as the command's input.
+        [
+            format!(
+                "open --raw /dev/null | do --env -i {{ {} }} e> {}",
+                command,
+                nu_quote(stderr_path)
+            ),
+            "let hone_ec = $env.LAST_EXIT_CODE".to_string(),
+            format!(
+                "$\"{}\\u{{1f}}({})\\u{{1f}}($hone_ec)\\u{{1f}}(date now | format date '%s%3f')\\n\" | save --append /dev/fd/{}",
+                SENTINEL_PREFIX,
+                nu_quote(run_id),
+                CONTROL_FD
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_adapter_by_executable_name() {
+        assert_eq!(shell_adapter("/bin/bash").unwrap().name(), "bash");
+        assert_eq!(shell_adapter("/usr/bin/dash").unwrap().name(), "dash");
+        assert_eq!(shell_adapter("ksh93").unwrap().name(), "ksh");
+        assert_eq!(
+            shell_adapter("/opt/homebrew/bin/fish").unwrap().name(),
+            "fish"
+        );
+        assert_eq!(shell_adapter("nu").unwrap().name(), "nu");
+        assert_eq!(
+            shell_adapter("/bin/zsh").unwrap().startup_flags(),
+            &["--no-rcs"]
+        );
+    }
+
+    #[test]
+    fn test_shell_adapter_rejects_unknown_shells() {
+        let error = shell_adapter("/bin/tcsh").err().unwrap();
+        assert!(error.contains("Unsupported shell: /bin/tcsh"));
+        assert!(error.contains(SUPPORTED_SHELLS));
+    }
+
+    #[test]
+    fn test_export_env_values_are_literal() {
+        let value = "it's $HOME";
+        assert_eq!(BASH.export_env("A", value), r#"export A='it'\''s $HOME'"#);
+        assert_eq!(Fish.export_env("A", value), r#"set -gx A 'it\'s $HOME'"#);
+        assert_eq!(
+            Nushell.export_env("A", value),
+            r#"$env.A = r#'it's $HOME'#"#
+        );
+    }
+
+    #[test]
+    fn test_prepend_path_keeps_current_path() {
+        assert_eq!(
+            BASH.prepend_path("/tmp/it's"),
+            r#"export PATH='/tmp/it'\''s':"$PATH""#
+        );
+        assert_eq!(Fish.prepend_path("/tmp/a"), "set -gx PATH '/tmp/a' $PATH");
+        assert_eq!(
+            Nushell.prepend_path("/tmp/a"),
+            "$env.PATH = ($env.PATH | prepend r#'/tmp/a'#)"
+        );
+    }
+
+    #[test]
+    fn test_nu_quote_outlasts_content() {
+        assert_eq!(nu_quote("a'#b"), "r##'a'#b'##");
+    }
+
+    #[test]
+    fn test_wrappers_close_or_write_control_fd() {
+        let fish = Fish.wrapper("echo hi", "run-1", "/tmp/stderr", None);
+        assert!(fish.starts_with("begin\necho hi\nend 2> '/tmp/stderr' 3>&-"));
+        assert!(fish.ends_with(">&3"));
+
+        let nu = Nushell.wrapper("echo hi", "run-1", "/tmp/stderr", None);
+        assert!(
+            nu.starts_with("open --raw /dev/null | do --env -i { echo hi } e> r#'/tmp/stderr'#")
+        );
+        assert!(nu.contains("__HONE__\\u{1f}(r#'run-1'#)"));
+        assert!(nu.ends_with("save --append /dev/fd/3"));
+    }
+
+    #[tokio::test]
+    async fn test_posix_adapters_drive_a_session() {
+        use crate::runner::shell::{create_shell_config, ShellSession};
+
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let cwd = dir.path().to_string_lossy().into_owned();
+
+        for shell in ["/bin/sh", "bash"] {
+            let config =
+                create_shell_config(&[], "adapter.hone", &cwd, Some(shell), &Default::default());
+            let mut session = ShellSession::new(config);
+            session.start().await.expect("shell should start");
+
+            let vars = [("GREETING".to_string(), "it's $HOME".to_string())];
+            session.set_env_vars(&vars).await.unwrap();
+            let result = session
+                .run(
+                    "mkdir -p sub && cd sub && echo \"$GREETING\"",
+                    None,
+                    5000,
+                    false,
+                )
+                .await
+                .expect("command should run");
+            assert_eq!(result.stdout, "it's $HOME", "{}", shell);
+            assert!(
+                session.get_cwd().await.unwrap().ends_with("/sub"),
+                "{}",
+                shell
+            );
+
+            session.stop().await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use crate::runner::reporter::{AssertionOutput, CommandRun, FileResult, Status, TestResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+const BASELINE_VERSION: u32 = 1;
+
+/// How much slower than its baseline a RUN must get, besides the
+/// `--max-regression` percentage, to count as a regression. Keeps a RUN
+/// going from 2ms to 3ms from failing as 50% slower.
+pub const MIN_REGRESSION_MS: u64 = 10;
+
+/// Timings of every RUN from a previous test run, saved with `--save-baseline`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    /// Keyed by `file › test › run`, see [`run_keys`]
+    pub runs: BTreeMap<String, BaselineRun>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineRun {
+    /// Median iteration time for a RUN with `REPEAT`, otherwise its duration
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rss_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time_ms: Option<u64>,
+}
+
+/// Result of comparing a test run against a baseline, shown by `TextFormatter`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineComparison {
+    pub name: String,
+    pub max_regression: Option<f64>,
+    pub rows: Vec<BaselineRow>,
+    /// RUNs in this test run that the baseline has no timing for
+    pub unmatched: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineRow {
+    pub run: String,
+    pub baseline_ms: u64,
+    pub current_ms: u64,
+    pub change_percent: f64,
+    pub regressed: bool,
+}
+
+/// Parses a `--max-regression` value such as `20%` or `12.5`.
+pub fn parse_percentage(value: &str) -> Result<f64, String> {
+    let number = value.trim().trim_end_matches('%');
+    match number.parse::<f64>() {
+        Ok(percent) if percent.is_finite() && percent >= 0.0 => Ok(percent),
+        _ => Err(format!(
+            "invalid percentage '{}': expected a non-negative number such as 20%",
+            value
+        )),
+    }
+}
+
+/// Validates a baseline name so it can be used as a file name.
+pub fn parse_baseline_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "invalid baseline name '{}': use letters, digits, '-', '_' and '.'",
+            name
+        ))
+    }
+}
+
+pub fn baseline_path(root: &Path, name: &str) -> PathBuf {
+    root.join(".hone")
+        .join("baselines")
+        .join(format!("{}.json", name))
+}
+
+fn run_label(run: &CommandRun) -> &str {
+    run.name.as_deref().unwrap_or(&run.command)
+}
+
+/// Keys for the RUNs of a TEST. An unnamed RUN that repeats an earlier
+/// command in the same TEST gets its occurrence appended (`#2`, `#3`, ...).
+fn run_keys(file: &str, test: &TestResult) -> Vec<String> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    test.runs
+        .iter()
+        .map(|run| {
+            let label = run_label(run);
+            let count = seen.entry(label).or_default();
+            *count += 1;
+            if *count == 1 {
+                format!("{} › {} › {}", file, test.name, label)
+            } else {
+                format!("{} › {} › {} #{}", file, test.name, label, count)
+            }
+        })
+        .collect()
+}
+
+fn run_duration_ms(run: &CommandRun) -> u64 {
+    run.bench
+        .as_ref()
+        .map(|stats| stats.median_ms)
+        .unwrap_or(run.duration_ms)
+}
+
+/// RUNs that started a command; RUNs that timed out or lost their shell have
+/// no meaningful timing.
+fn completed_runs(files: &[FileResult]) -> impl Iterator<Item = (String, &CommandRun)> {
+    files.iter().flat_map(|file| {
+        file.tests.iter().flat_map(move |test| {
+            run_keys(&file.label(), test)
+                .into_iter()
+                .zip(&test.runs)
+                .filter(|(_, run)| run.exit_code >= 0)
+        })
+    })
+}
+
+impl Baseline {
+    pub fn from_results(files: &[FileResult]) -> Self {
+        let runs = completed_runs(files)
+            .map(|(key, run)| {
+                let timing = BaselineRun {
+                    duration_ms: run_duration_ms(run),
+                    max_rss_bytes: run.resources.and_then(|r| r.max_rss_bytes),
+                    cpu_time_ms: run.resources.map(|r| r.cpu_time_ms()),
+                };
+                (key, timing)
+            })
+            .collect();
+
+        Self {
+            version: BASELINE_VERSION,
+            runs,
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!(
+                "Baseline not found: {} (create it with --save-baseline)",
+                path.display()
+            ),
+            _ => anyhow::anyhow!("Failed to read baseline {}: {}", path.display(), e),
+        })?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid baseline {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Compares every completed RUN against this baseline. With a
+    /// `max_regression`, RUNs more than that percentage and more than
+    /// `MIN_REGRESSION_MS` slower get a failed assertion and their TEST is
+    /// marked failed.
+    pub fn compare(
+        &self,
+        name: &str,
+        files: &mut [FileResult],
+        max_regression: Option<f64>,
+    ) -> BaselineComparison {
+        let mut rows = Vec::new();
+        let mut unmatched = 0;
+
+        for file in files.iter_mut() {
+            let label = file.label();
+            for test in &mut file.tests {
+                let keys = run_keys(&label, test);
+                for (key, run) in keys.into_iter().zip(&mut test.runs) {
+                    if run.exit_code < 0 {
+                        continue;
+                    }
+                    let Some(previous) = self.runs.get(&key) else {
+                        unmatched += 1;
+                        continue;
+                    };
+
+                    let current_ms = run_duration_ms(run);
+                    let change_percent = percent_change(previous.duration_ms, current_ms);
+                    let slower_ms = current_ms.saturating_sub(previous.duration_ms);
+                    let regressed = max_regression.is_some_and(|limit| change_percent > limit)
+                        && slower_ms > MIN_REGRESSION_MS;
+
+                    if regressed {
+                        let limit = max_regression.unwrap_or_default();
+                        run.status = Status::Failed;
+                        run.assertions.push(AssertionOutput {
+                            line: run.line,
+                            expression: format!("baseline \"{}\"", name),
+                            status: Status::Failed,
+                            expected: Some(format!(
+                                "at most {}% or {}ms slower than {}ms",
+                                limit, MIN_REGRESSION_MS, previous.duration_ms
+                            )),
+                            actual: Some(format!("{}ms ({:+.1}%)", current_ms, change_percent)),
+                        });
+                        test.status = Status::Failed;
+                    }
+
+                    rows.push(BaselineRow {
+                        run: key,
+                        baseline_ms: previous.duration_ms,
+                        current_ms,
+                        change_percent,
+                        regressed,
+                    });
+                }
+            }
+        }
+
+        BaselineComparison {
+            name: name.to_string(),
+            max_regression,
+            rows,
+            unmatched,
+        }
+    }
+}
+
+/// Relative change from `baseline_ms` to `current_ms`. A zero baseline is
+/// treated as 1ms so sub-millisecond RUNs don't divide by zero.
+fn percent_change(baseline_ms: u64, current_ms: u64) -> f64 {
+    let baseline = baseline_ms.max(1) as f64;
+    (current_ms as f64 - baseline) / baseline * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_run(name: &str, duration_ms: u64) -> CommandRun {
+        CommandRun {
+            name: Some(name.to_string()),
+            command: "true".to_string(),
+            line: 2,
+            status: Status::Passed,
+            duration_ms,
+            exit_code: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+            assertions: vec![],
+            resources: None,
+            bench: None,
+            stdout_truncated: None,
+            stderr_truncated: None,
+        }
+    }
+
+    fn make_file(runs: Vec<CommandRun>) -> FileResult {
+        FileResult {
+            file: "cli.hone".to_string(),
+            shell: "/bin/sh".to_string(),
+            matrix: false,
+            iteration: None,
+            env: Default::default(),
+            error: None,
+            tests: vec![TestResult {
+                name: "startup".to_string(),
+                line: 1,
+                status: Status::Passed,
+                duration_ms: 0,
+                runs,
+                assertions: vec![],
+                message: None,
+                attempts: vec![],
+                tags: vec![],
+                not_run: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_parse_percentage() {
+        assert_eq!(parse_percentage("20%"), Ok(20.0));
+        assert_eq!(parse_percentage("12.5"), Ok(12.5));
+        assert!(parse_percentage("-5%").is_err());
+        assert!(parse_percentage("fast").is_err());
+    }
+
+    #[test]
+    fn test_parse_baseline_name() {
+        assert!(parse_baseline_name("main").is_ok());
+        assert!(parse_baseline_name("v1.2_rc-1").is_ok());
+        assert!(parse_baseline_name("../main").is_err());
+        assert!(parse_baseline_name("a/b").is_err());
+        assert!(parse_baseline_name("").is_err());
+    }
+
+    #[test]
+    fn test_from_results_skips_runs_that_did_not_complete() {
+        let mut timed_out = make_run("hang", 0);
+        timed_out.exit_code = -1;
+        let baseline =
+            Baseline::from_results(&[make_file(vec![make_run("version", 40), timed_out])]);
+
+        assert_eq!(baseline.runs.len(), 1);
+        assert_eq!(
+            baseline.runs["cli.hone › startup › version"].duration_ms,
+            40
+        );
+    }
+
+    #[test]
+    fn test_repeated_unnamed_runs_get_separate_keys() {
+        let unnamed = |duration_ms| CommandRun {
+            name: None,
+            ..make_run("", duration_ms)
+        };
+        let baseline = Baseline::from_results(&[make_file(vec![unnamed(10), unnamed(300)])]);
+
+        assert_eq!(baseline.runs["cli.hone › startup › true"].duration_ms, 10);
+        assert_eq!(
+            baseline.runs["cli.hone › startup › true #2"].duration_ms,
+            300
+        );
+
+        let mut files = vec![make_file(vec![unnamed(10), unnamed(300)])];
+        let comparison = baseline.compare("main", &mut files, Some(20.0));
+        assert_eq!(comparison.rows.len(), 2);
+        assert!(comparison.rows.iter().all(|row| !row.regressed));
+    }
+
+    #[test]
+    fn test_compare_flags_regressions_over_limit() {
+        let baseline = Baseline::from_results(&[make_file(vec![
+            make_run("version", 100),
+            make_run("help", 100),
+        ])]);
+        let mut files = vec![make_file(vec![
+            make_run("version", 110),
+            make_run("help", 150),
+            make_run("new", 5),
+        ])];
+
+        let comparison = baseline.compare("main", &mut files, Some(20.0));
+
+        assert_eq!(comparison.rows.len(), 2);
+        assert_eq!(comparison.unmatched, 1);
+        assert!(!comparison.rows[0].regressed);
+        assert!(comparison.rows[1].regressed);
+        assert_eq!(comparison.rows[1].change_percent, 50.0);
+
+        let test = &files[0].tests[0];
+        assert_eq!(test.status, Status::Failed);
+        assert_eq!(test.runs[0].status, Status::Passed);
+        assert_eq!(test.runs[1].status, Status::Failed);
+        assert_eq!(
+            test.runs[1].assertions[0].actual.as_deref(),
+            Some("150ms (+50.0%)")
+        );
+    }
+
+    #[test]
+    fn test_compare_ignores_small_absolute_changes() {
+        let baseline = Baseline::from_results(&[make_file(vec![
+            make_run("version", 2),
+            make_run("help", 20),
+        ])]);
+        let mut files = vec![make_file(vec![
+            make_run("version", 6),
+            make_run("help", 31),
+        ])];
+
+        let comparison = baseline.compare("main", &mut files, Some(20.0));
+
+        assert!(!comparison.rows[0].regressed);
+        assert!(comparison.rows[1].regressed);
+        assert_eq!(files[0].tests[0].runs[0].status, Status::Passed);
+    }
+
+    #[test]
+    fn test_compare_without_limit_only_reports() {
+        let baseline = Baseline::from_results(&[make_file(vec![make_run("version", 100)])]);
+        let mut files = vec![make_file(vec![make_run("version", 300)])];
+
+        let comparison = baseline.compare("main", &mut files, None);
+
+        assert!(!comparison.rows[0].regressed);
+        assert_eq!(files[0].tests[0].status, Status::Passed);
+    }
+
+    #[test]
+    fn test_baseline_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = baseline_path(dir.path(), "main");
+        let baseline = Baseline::from_results(&[make_file(vec![make_run("version", 40)])]);
+
+        baseline.save(&path).unwrap();
+        assert_eq!(Baseline::load(&path).unwrap(), baseline);
+        assert!(Baseline::load(&baseline_path(dir.path(), "missing")).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::parser::ast::DurationStatistic;
+use serde::Serialize;
+
+/// Duration statistics over the iterations of a RUN with `REPEAT`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchStats {
+    /// Iterations the statistics are over
+    pub iterations: usize,
+    /// Iterations asked for; more than `iterations` when repetition stopped early
+    pub requested: usize,
+    pub min_ms: u64,
+    pub median_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+impl BenchStats {
+    /// Returns `None` when there are no samples.
+    pub fn from_samples(samples: &[u64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+
+        let n = sorted.len();
+        let median_ms = if n.is_multiple_of(2) {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2
+        } else {
+            sorted[n / 2]
+        };
+
+        Some(Self {
+            iterations: n,
+            requested: n,
+            min_ms: *sorted.first()?,
+            median_ms,
+            p95_ms: nearest_rank(&sorted, 95),
+            max_ms: *sorted.last()?,
+        })
+    }
+
+    pub fn get(&self, statistic: DurationStatistic) -> u64 {
+        match statistic {
+            DurationStatistic::Min => self.min_ms,
+            DurationStatistic::Median => self.median_ms,
+            DurationStatistic::P95 => self.p95_ms,
+            DurationStatistic::Max => self.max_ms,
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty samples: the smallest sample
+/// that at least `percentile`% of samples are less than or equal to.
+fn nearest_rank(sorted: &[u64], percentile: usize) -> u64 {
+    let rank = (percentile * sorted.len()).div_ceil(100);
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_samples_empty() {
+        assert!(BenchStats::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn test_from_samples_single() {
+        let stats = BenchStats::from_samples(&[42]).unwrap();
+        assert_eq!(stats.iterations, 1);
+        assert_eq!(stats.min_ms, 42);
+        assert_eq!(stats.median_ms, 42);
+        assert_eq!(stats.p95_ms, 42);
+        assert_eq!(stats.max_ms, 42);
+    }
+
+    #[test]
+    fn test_from_samples_unsorted() {
+        let stats = BenchStats::from_samples(&[30, 10, 20]).unwrap();
+        assert_eq!(stats.min_ms, 10);
+        assert_eq!(stats.median_ms, 20);
+        assert_eq!(stats.max_ms, 30);
+    }
+
+    #[test]
+    fn test_median_of_even_count_averages_middle_samples() {
+        let stats = BenchStats::from_samples(&[10, 20, 30, 40]).unwrap();
+        assert_eq!(stats.median_ms, 25);
+    }
+
+    #[test]
+    fn test_p95_uses_nearest_rank() {
+        let samples: Vec<u64> = (1..=20).collect();
+        let stats = BenchStats::from_samples(&samples).unwrap();
+        assert_eq!(stats.p95_ms, 19);
+        assert_eq!(stats.get(DurationStatistic::P95), 19);
+
+        let samples: Vec<u64> = (1..=100).collect();
+        assert_eq!(BenchStats::from_samples(&samples).unwrap().p95_ms, 95);
+    }
+}
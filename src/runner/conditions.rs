@@ -0,0 +1,190 @@
+use crate::parser::ast::{PragmaNode, PragmaType, SkipCondition, StringComparisonOperator};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Where `#! require:` and `SKIP IF` conditions are evaluated: the shell's PATH
+/// and `#! env:` pragmas on top of the environment hone was started in.
+/// Conditions are checked before a TEST's shell session starts.
+pub struct ConditionContext<'a> {
+    pub env: &'a HashMap<String, String>,
+}
+
+impl ConditionContext<'_> {
+    fn var(&self, name: &str) -> Option<String> {
+        self.env
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    }
+
+    fn on_path(&self, command: &str) -> bool {
+        if command.contains('/') {
+            return is_executable(Path::new(command));
+        }
+        self.var("PATH")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .any(|dir| is_executable(&Path::new(dir).join(command)))
+    }
+
+    /// Commands from `#! require:` pragmas that are not on PATH.
+    pub fn missing_commands(&self, pragmas: &[PragmaNode]) -> Vec<String> {
+        pragmas
+            .iter()
+            .filter(|p| p.pragma_type == PragmaType::Require)
+            .flat_map(|p| p.value.split(','))
+            .map(str::trim)
+            .filter(|command| !self.on_path(command))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Why the TEST is skipped, if the condition holds.
+    pub fn skip_reason(&self, condition: &SkipCondition) -> Option<String> {
+        match condition {
+            SkipCondition::Missing(command) => {
+                (!self.on_path(command)).then(|| format!("{} is not on PATH", command))
+            }
+            SkipCondition::Unset(name) => self
+                .var(name)
+                .is_none()
+                .then(|| format!("{} is unset", name)),
+            SkipCondition::Env {
+                name,
+                operator,
+                value,
+            } => {
+                let actual = self.var(name);
+                compare(actual.as_deref(), operator, value).then(|| match actual {
+                    Some(actual) => format!("{} is \"{}\"", name, actual),
+                    None => format!("{} is unset", name),
+                })
+            }
+            SkipCondition::Uname { operator, value } => {
+                let actual = uname();
+                compare(Some(actual), operator, value).then(|| format!("uname is \"{}\"", actual))
+            }
+        }
+    }
+}
+
+fn compare(actual: Option<&str>, operator: &StringComparisonOperator, expected: &str) -> bool {
+    match operator {
+        StringComparisonOperator::Equal => actual == Some(expected),
+        StringComparisonOperator::NotEqual => actual != Some(expected),
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Kernel name as printed by `uname -s`, e.g. `Linux` or `Darwin`.
+fn uname() -> &'static str {
+    static UNAME: OnceLock<String> = OnceLock::new();
+    UNAME.get_or_init(|| {
+        std::process::Command::new("uname")
+            .arg("-s")
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| std::env::consts::OS.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_missing_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = dir.path().join("hone-tool");
+        std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&tool, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+            .unwrap();
+
+        let env = make_env(&[("PATH", &dir.path().to_string_lossy())]);
+        let context = ConditionContext { env: &env };
+        let pragma = PragmaNode {
+            pragma_type: PragmaType::Require,
+            key: None,
+            value: "hone-tool, hone-missing".to_string(),
+            line: 1,
+            raw: "#! require: hone-tool, hone-missing".to_string(),
+        };
+
+        assert_eq!(context.missing_commands(&[pragma]), vec!["hone-missing"]);
+        assert_eq!(
+            context.skip_reason(&SkipCondition::Missing("hone-missing".to_string())),
+            Some("hone-missing is not on PATH".to_string())
+        );
+        assert_eq!(
+            context.skip_reason(&SkipCondition::Missing("hone-tool".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_env_conditions() {
+        let env = make_env(&[("HONE_TEST_MODE", "ci")]);
+        let context = ConditionContext { env: &env };
+
+        assert_eq!(
+            context.skip_reason(&SkipCondition::Unset("HONE_TEST_UNSET_VAR".to_string())),
+            Some("HONE_TEST_UNSET_VAR is unset".to_string())
+        );
+        assert_eq!(
+            context.skip_reason(&SkipCondition::Unset("HONE_TEST_MODE".to_string())),
+            None
+        );
+        assert_eq!(
+            context.skip_reason(&SkipCondition::Env {
+                name: "HONE_TEST_MODE".to_string(),
+                operator: StringComparisonOperator::Equal,
+                value: "ci".to_string(),
+            }),
+            Some("HONE_TEST_MODE is \"ci\"".to_string())
+        );
+        assert_eq!(
+            context.skip_reason(&SkipCondition::Env {
+                name: "HONE_TEST_MODE".to_string(),
+                operator: StringComparisonOperator::NotEqual,
+                value: "ci".to_string(),
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_uname_condition() {
+        let env = HashMap::new();
+        let context = ConditionContext { env: &env };
+
+        let equal = SkipCondition::Uname {
+            operator: StringComparisonOperator::Equal,
+            value: uname().to_string(),
+        };
+        assert!(context.skip_reason(&equal).is_some());
+
+        let not_equal = SkipCondition::Uname {
+            operator: StringComparisonOperator::NotEqual,
+            value: uname().to_string(),
+        };
+        assert!(context.skip_reason(&not_equal).is_none());
+    }
+}
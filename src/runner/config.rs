@@ -0,0 +1,460 @@
+use crate::parser::lexer::parse_duration;
+use crate::runner::reporter::OutputFormat;
+use crate::runner::shell::timeout_to_ms;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+pub const CONFIG_FILE: &str = "hone.toml";
+
+/// Project settings from a `hone.toml`, found in the current directory or the
+/// nearest parent that has one. CLI flags override pragmas, which override the
+/// most specific `[directories]` section, which overrides the top-level settings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectConfig {
+    /// Directory of the `hone.toml`; relative paths in it are resolved from here
+    pub root: PathBuf,
+    /// Patterns run when none are given on the command line
+    pub patterns: Vec<String>,
+    pub output_format: Option<OutputFormat>,
+    /// Replaces `.hone/runs` next to each test file as the place RUN artifacts go
+    pub artifact_dir: Option<PathBuf>,
+    /// Files a report is also written to after each run, besides the console output
+    pub reporters: Vec<(OutputFormat, PathBuf)>,
+    pub defaults: FileSettings,
+    /// Overrides for files under each directory, least specific first
+    pub directories: Vec<(PathBuf, FileSettings)>,
+}
+
+/// Settings a `hone.toml` applies to a test file, underneath its pragmas.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileSettings {
+    pub shell: Option<String>,
+    pub timeout_ms: Option<u64>,
+    pub env: Vec<(String, String)>,
+    /// Globs naming host environment variables the shell inherits
+    pub pass_env: Vec<String>,
+    pub hermetic: Option<bool>,
+}
+
+impl FileSettings {
+    /// Layers `other` on top: its shell, timeout and hermetic setting win, and
+    /// its env vars are applied after these.
+    fn merge(&mut self, other: &FileSettings) {
+        if other.shell.is_some() {
+            self.shell = other.shell.clone();
+        }
+        if other.timeout_ms.is_some() {
+            self.timeout_ms = other.timeout_ms;
+        }
+        if other.hermetic.is_some() {
+            self.hermetic = other.hermetic;
+        }
+        self.env.extend(other.env.iter().cloned());
+        self.pass_env.extend(other.pass_env.iter().cloned());
+    }
+}
+
+impl ProjectConfig {
+    /// Loads the `hone.toml` in `start` or its nearest ancestor, if there is one.
+    pub fn discover(start: &Path) -> anyhow::Result<Option<Self>> {
+        let Some(path) = start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+        else {
+            return Ok(None);
+        };
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        let root = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        Self::parse(&content, &root)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(content: &str, root: &Path) -> Result<Self, String> {
+        let document: DocumentMut = content.parse().map_err(|e| format!("{}", e))?;
+        let table = document.as_table();
+
+        let mut config = ProjectConfig {
+            root: root.to_path_buf(),
+            defaults: parse_settings(
+                table,
+                "",
+                &[
+                    "patterns",
+                    "output_format",
+                    "artifact_dir",
+                    "reporters",
+                    "directories",
+                ],
+            )?,
+            ..Default::default()
+        };
+
+        for (key, item) in table.iter() {
+            match key {
+                "patterns" => {
+                    config.patterns = match item.as_str() {
+                        Some(pattern) => vec![pattern.to_string()],
+                        None => string_array(item, key)?,
+                    };
+                }
+                "output_format" => {
+                    config.output_format = Some(parse_output_format(as_str(item, key)?, key)?);
+                }
+                "artifact_dir" => {
+                    config.artifact_dir = Some(root.join(as_str(item, key)?));
+                }
+                "reporters" => {
+                    let reporters = item
+                        .as_table_like()
+                        .ok_or("reporters must be a table of format = \"path\"")?;
+                    for (format, path) in reporters.iter() {
+                        let name = format!("reporters.{}", format);
+                        let format = parse_output_format(format, "reporters")?;
+                        config
+                            .reporters
+                            .push((format, root.join(as_str(path, &name)?)));
+                    }
+                }
+                "directories" => {
+                    let directories = item
+                        .as_table_like()
+                        .ok_or("directories must be a table of directory sections")?;
+                    for (dir, section) in directories.iter() {
+                        let section = section
+                            .as_table_like()
+                            .ok_or_else(|| format!("directories.\"{}\" must be a table", dir))?;
+                        let prefix = format!("directories.\"{}\".", dir);
+                        config
+                            .directories
+                            .push((root.join(dir), parse_settings(section, &prefix, &[])?));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Deeper directories are more specific, so they are applied last
+        config
+            .directories
+            .sort_by_key(|(dir, _)| dir.components().count());
+        Ok(config)
+    }
+
+    /// Patterns from the config, relative to its directory.
+    pub fn resolved_patterns(&self) -> Vec<String> {
+        self.patterns
+            .iter()
+            .map(|pattern| self.root.join(pattern).to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Settings for a test file: the top-level ones with every matching
+    /// `[directories]` section layered on top.
+    pub fn settings_for(&self, filename: &str) -> FileSettings {
+        let file = std::path::absolute(filename).unwrap_or_else(|_| PathBuf::from(filename));
+        let mut settings = self.defaults.clone();
+        for (dir, overrides) in &self.directories {
+            let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.clone());
+            if file.starts_with(&dir) {
+                settings.merge(overrides);
+            }
+        }
+        settings
+    }
+
+    /// Directory that RUN artifacts of a test file go under. Under an
+    /// `artifact_dir` it mirrors the file's directory relative to the project
+    /// root, so files with the same name in different directories don't share one.
+    pub fn runs_dir(&self, filename: &str) -> PathBuf {
+        match &self.artifact_dir {
+            Some(dir) => {
+                let file = std::path::absolute(filename).unwrap_or_else(|_| filename.into());
+                let parent = file.parent().unwrap_or(Path::new(""));
+                let root = std::path::absolute(&self.root).unwrap_or_else(|_| self.root.clone());
+                let relative = parent
+                    .strip_prefix(&root)
+                    .or_else(|_| parent.strip_prefix("/"))
+                    .unwrap_or(parent);
+                dir.join(relative)
+            }
+            None => Path::new(filename)
+                .parent()
+                .unwrap_or(Path::new("."))
+                .join(".hone")
+                .join("runs"),
+        }
+    }
+
+    /// Directory that `.hone/baselines` lives in: the `hone.toml` directory,
+    /// or without one the deepest directory containing every test file.
+    pub fn baseline_root(&self, files: &[String]) -> PathBuf {
+        if !self.root.as_os_str().is_empty() {
+            return self.root.clone();
+        }
+        let mut dirs = files.iter().map(|file| {
+            let file = std::path::absolute(file).unwrap_or_else(|_| PathBuf::from(file));
+            file.parent().map(Path::to_path_buf).unwrap_or_default()
+        });
+        let Some(mut common) = dirs.next() else {
+            return PathBuf::from(".");
+        };
+        for dir in dirs {
+            while !dir.starts_with(&common) {
+                if !common.pop() {
+                    break;
+                }
+            }
+        }
+        common
+    }
+}
+
+/// Reads `shell`, `timeout`, `env`, `pass_env` and `hermetic` from a table,
+/// rejecting keys that are neither those nor in `other_keys`.
+fn parse_settings(
+    table: &dyn TableLike,
+    prefix: &str,
+    other_keys: &[&str],
+) -> Result<FileSettings, String> {
+    let mut settings = FileSettings::default();
+    for (key, item) in table.iter() {
+        let name = format!("{}{}", prefix, key);
+        match key {
+            "shell" => settings.shell = Some(as_str(item, &name)?.to_string()),
+            "timeout" => {
+                let value = as_str(item, &name)?;
+                settings.timeout_ms = match parse_duration(value, 0) {
+                    Some((duration, end)) if value[end..].trim().is_empty() => {
+                        Some(timeout_to_ms(&duration).max(1))
+                    }
+                    _ => {
+                        return Err(format!(
+                            "invalid {} '{}': expected <number>s or <number>ms",
+                            name, value
+                        ))
+                    }
+                };
+            }
+            "env" => {
+                let env = item
+                    .as_table_like()
+                    .ok_or_else(|| format!("{} must be a table of variables", name))?;
+                for (var, value) in env.iter() {
+                    let value = as_str(value, &format!("{}.{}", name, var))?;
+                    settings.env.push((var.to_string(), value.to_string()));
+                }
+            }
+            "pass_env" => {
+                for pattern in string_array(item, &name)? {
+                    parse_env_pattern(&pattern).map_err(|e| format!("invalid {}: {}", name, e))?;
+                    settings.pass_env.push(pattern);
+                }
+            }
+            "hermetic" => {
+                settings.hermetic = Some(
+                    item.as_bool()
+                        .ok_or_else(|| format!("{} must be true or false", name))?,
+                );
+            }
+            key if other_keys.contains(&key) => {}
+            _ => return Err(format!("unknown key '{}'", name)),
+        }
+    }
+    Ok(settings)
+}
+
+/// Checks a `--pass-env` glob such as `CI` or `AWS_*`.
+pub fn parse_env_pattern(pattern: &str) -> Result<String, String> {
+    glob::Pattern::new(pattern)
+        .map(|_| pattern.to_string())
+        .map_err(|e| format!("'{}' is not a valid pattern: {}", pattern, e))
+}
+
+/// Parses a `--env KEY=VAL` argument.
+pub fn parse_env_assignment(assignment: &str) -> Result<(String, String), String> {
+    let (key, value) = assignment
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VAL, got '{}'", assignment))?;
+    let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("invalid environment variable name '{}'", key));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+fn parse_output_format(value: &str, name: &str) -> Result<OutputFormat, String> {
+    match value {
+        "text" => Ok(OutputFormat::Text),
+        "json" => Ok(OutputFormat::Json),
+        other => Err(format!(
+            "invalid {} '{}': expected \"text\" or \"json\"",
+            name, other
+        )),
+    }
+}
+
+fn as_str<'a>(item: &'a Item, name: &str) -> Result<&'a str, String> {
+    item.as_str()
+        .ok_or_else(|| format!("{} must be a string", name))
+}
+
+fn string_array(item: &Item, name: &str) -> Result<Vec<String>, String> {
+    let error = || format!("{} must be a string or an array of strings", name);
+    item.as_array()
+        .ok_or_else(error)?
+        .iter()
+        .map(|value| value.as_str().map(String::from).ok_or_else(error))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = ProjectConfig::parse(
+            r#"
+patterns = ["tests/**/*.hone"]
+shell = "/bin/bash"
+timeout = "10s"
+output_format = "json"
+artifact_dir = "target/hone-runs"
+
+pass_env = ["CI"]
+reporters = { json = "target/hone.json" }
+
+[env]
+MODE = "test"
+
+[directories."tests/slow"]
+pass_env = ["RUST_*"]
+hermetic = false
+timeout = "120s"
+
+[directories."tests"]
+shell = "/bin/sh"
+env = { MODE = "integration" }
+"#,
+            Path::new("/project"),
+        )
+        .unwrap();
+
+        assert_eq!(config.resolved_patterns(), vec!["/project/tests/**/*.hone"]);
+        assert_eq!(config.output_format, Some(OutputFormat::Json));
+        assert_eq!(
+            config.reporters,
+            vec![(
+                OutputFormat::Json,
+                PathBuf::from("/project/target/hone.json")
+            )]
+        );
+        assert_eq!(
+            config.runs_dir("/project/tests/a.hone"),
+            PathBuf::from("/project/target/hone-runs/tests")
+        );
+        assert_eq!(
+            config.runs_dir("/project/tests/b/a.hone"),
+            PathBuf::from("/project/target/hone-runs/tests/b")
+        );
+        assert_eq!(
+            config.runs_dir("/elsewhere/a.hone"),
+            PathBuf::from("/project/target/hone-runs/elsewhere")
+        );
+        assert_eq!(config.defaults.timeout_ms, Some(10_000));
+
+        let slow = config.settings_for("/project/tests/slow/big.hone");
+        assert_eq!(slow.shell.as_deref(), Some("/bin/sh"));
+        assert_eq!(slow.timeout_ms, Some(120_000));
+        assert_eq!(
+            slow.env,
+            vec![
+                ("MODE".to_string(), "test".to_string()),
+                ("MODE".to_string(), "integration".to_string())
+            ]
+        );
+
+        assert_eq!(slow.pass_env, vec!["CI", "RUST_*"]);
+        assert_eq!(slow.hermetic, Some(false));
+
+        let other = config.settings_for("/project/examples/demo.hone");
+        assert_eq!(other, config.defaults);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let root = Path::new("/project");
+        assert!(ProjectConfig::parse("shel = \"bash\"", root)
+            .unwrap_err()
+            .contains("unknown key 'shel'"));
+        assert!(ProjectConfig::parse("timeout = \"soon\"", root)
+            .unwrap_err()
+            .contains("invalid timeout 'soon'"));
+        assert!(ProjectConfig::parse("output_format = \"xml\"", root).is_err());
+        assert!(ProjectConfig::parse("[reporters]\nxml = \"out.xml\"", root).is_err());
+        assert!(ProjectConfig::parse("reporters = [\"json\"]", root).is_err());
+        assert!(
+            ProjectConfig::parse("[directories.tests]\ncolor = true", root)
+                .unwrap_err()
+                .contains("directories.\"tests\".color")
+        );
+        assert!(ProjectConfig::parse("patterns = [1]", root).is_err());
+        assert!(ProjectConfig::parse("pass_env = [\"[\"]", root).is_err());
+        assert!(ProjectConfig::parse("hermetic = \"yes\"", root)
+            .unwrap_err()
+            .contains("hermetic must be true or false"));
+    }
+
+    #[test]
+    fn test_parse_env_arguments() {
+        assert_eq!(
+            parse_env_assignment("API_URL=http://x?a=b"),
+            Ok(("API_URL".to_string(), "http://x?a=b".to_string()))
+        );
+        assert_eq!(
+            parse_env_assignment("EMPTY="),
+            Ok(("EMPTY".to_string(), String::new()))
+        );
+        assert!(parse_env_assignment("NOVALUE").is_err());
+        assert!(parse_env_assignment("1X=1").is_err());
+        assert!(parse_env_pattern("AWS_*").is_ok());
+        assert!(parse_env_pattern("[").is_err());
+    }
+
+    #[test]
+    fn test_discover_searches_parent_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("tests").join("unit");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(ProjectConfig::discover(&nested).unwrap(), None);
+
+        std::fs::write(dir.path().join(CONFIG_FILE), "shell = \"/bin/sh\"\n").unwrap();
+        let config = ProjectConfig::discover(&nested).unwrap().unwrap();
+        assert_eq!(config.root, dir.path());
+        assert_eq!(config.defaults.shell.as_deref(), Some("/bin/sh"));
+    }
+
+    #[test]
+    fn test_baseline_root() {
+        let files = vec![
+            "/project/tests/cli/a.hone".to_string(),
+            "/project/tests/api/b.hone".to_string(),
+        ];
+        assert_eq!(
+            ProjectConfig::default().baseline_root(&files),
+            Path::new("/project/tests")
+        );
+        assert_eq!(
+            ProjectConfig::default().baseline_root(&files[..1]),
+            Path::new("/project/tests/cli")
+        );
+
+        let config = ProjectConfig::parse("", Path::new("/project")).unwrap();
+        assert_eq!(config.baseline_root(&files), Path::new("/project"));
+    }
+}
@@ -1,29 +1,77 @@
 use crate::assertions::{
+    bytes::{
+        evaluate_bytes_predicate, evaluate_length_predicate, get_output_bytes,
+        get_output_truncation, output_length, truncated_output,
+    },
     exitcode::evaluate_exit_code_predicate,
     filesystem::evaluate_file_predicate,
+    http::evaluate_http_predicate,
     output::{evaluate_output_predicate, get_output_value},
-    timing::evaluate_duration_predicate,
+    resources::evaluate_memory_predicate,
+    stub::evaluate_stub_predicate,
+    timing::{
+        duration_to_ms, evaluate_duration_predicate, evaluate_named_duration_predicate,
+        format_duration,
+    },
     AssertionResult,
 };
 use crate::parse_file;
-use crate::parser::ast::{ASTNode, AssertNode, ParseResult, RunNode};
+use crate::parser::ast::{
+    ASTNode, AssertNode, AssertionExpression, EnvNode, ParseResult, PragmaNode, PragmaType,
+    RunNode, TestModifier, TestNode,
+};
+use crate::runner::baseline::{baseline_path, Baseline};
+use crate::runner::bench::BenchStats;
+use crate::runner::conditions::ConditionContext;
+use crate::runner::config::ProjectConfig;
+use crate::runner::pool::SessionPool;
+use crate::runner::repeat::{iteration_artifact_dir, RepeatReport};
 use crate::runner::reporter::{
-    AssertionOutput, CommandRun, DefaultReporter, FileResult, JsonFormatter, OutputFormat,
-    OutputFormatter, Reporter, Status, Summary, TestFailure, TestResult, TestRunOutput,
-    TextFormatter,
+    print_flaky, shell_name, AssertionOutput, CommandRun, DefaultReporter, FileResult,
+    JsonFormatter, NotRun, OutputFormat, OutputFormatter, Reporter, Status, Summary, TestFailure,
+    TestResult, TestRunOutput, TextFormatter,
+};
+use crate::runner::resources::ResourceUsage;
+use crate::runner::shell::{
+    create_shell_config, pragma_shells, timeout_to_ms, RunError, RunResult, ShellConfig,
+    ShellSession,
 };
-use crate::runner::shell::{create_shell_config, RunResult, ShellSession};
+use crate::runner::tags::TagSelection;
 use regex::Regex;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Default)]
 pub struct RunnerOptions {
     pub shell: Option<String>,
+    /// Run every file once in each of these shells, overriding `shells` pragmas
+    pub shell_matrix: Option<Vec<String>>,
     pub verbose: bool,
     pub test_filter: Option<String>,
     pub output_format: OutputFormat,
+    /// Save per-RUN timings under `.hone/baselines/<name>.json` in the project root
+    pub save_baseline: Option<String>,
+    /// Compare per-RUN timings against a saved baseline
+    pub baseline: Option<String>,
+    /// Fail RUNs more than this many percent slower than the baseline
+    pub max_regression: Option<f64>,
+    /// Run every file this many times, reporting pass and fail counts per TEST
+    pub repeat: Option<usize>,
+    /// Repeat until an iteration fails, up to `repeat` times if set
+    pub until_failure: bool,
+    /// Fail instead of running when any TEST is marked ONLY
+    pub forbid_only: bool,
+    /// Run only TESTs whose tags match one of these expressions
+    pub tags: Vec<String>,
+    /// Leave out TESTs whose tags match any of these expressions
+    pub exclude_tags: Vec<String>,
+    /// Variables from `--env`, set over everything else
+    pub env: Vec<(String, String)>,
+    /// Globs from `--pass-env` naming host variables the shell inherits
+    pub pass_env: Vec<String>,
+    /// Settings from `hone.toml`, applied underneath each file's pragmas
+    pub project: ProjectConfig,
 }
 
 #[derive(Debug, Clone)]
@@ -67,10 +115,52 @@ struct TestBlock {
     nodes: Vec<ASTNode>,
 }
 
+/// Which limit a RUN's timeout came from, so failures can name it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TimeoutLimit {
+    File,
+    Test { line: usize },
+    Run,
+}
+
+impl TimeoutLimit {
+    fn describe(&self) -> String {
+        match self {
+            TimeoutLimit::File => "file limit".to_string(),
+            TimeoutLimit::Test { line } => format!("TEST limit set on line {}", line),
+            TimeoutLimit::Run => "RUN limit".to_string(),
+        }
+    }
+}
+
+fn effective_timeout(
+    run_node: &RunNode,
+    test_timeout: Option<(u64, usize)>,
+    file_timeout_ms: u64,
+) -> (u64, TimeoutLimit) {
+    if let Some(ref duration) = run_node.timeout {
+        (timeout_to_ms(duration), TimeoutLimit::Run)
+    } else if let Some((ms, line)) = test_timeout {
+        (ms, TimeoutLimit::Test { line })
+    } else {
+        (file_timeout_ms, TimeoutLimit::File)
+    }
+}
+
+fn describe_run_error(error: &RunError, limit: TimeoutLimit) -> String {
+    match error {
+        RunError::Timeout(_) => format!("{} ({})", error, limit.describe()),
+        _ => error.to_string(),
+    }
+}
+
 struct ExecuteResult {
     assertions_passed: usize,
     failure: Option<TestFailure>,
     test_result: Option<TestResult>,
+    /// Set when the shell timed out or died, so later TEST blocks can still
+    /// run in a fresh session instead of stopping the file.
+    session_lost: bool,
 }
 
 pub async fn run_tests(
@@ -97,8 +187,18 @@ pub async fn run_tests(
         None
     };
 
+    let tag_selection = TagSelection::new(&options.tags, &options.exclude_tags)
+        .map_err(|e| anyhow::anyhow!("Invalid tag expression: {}", e))?;
+
     let all_files = crate::runner::resolve_patterns(&patterns).await?;
 
+    // Load the baseline before running anything so a typo fails fast
+    let baseline_root = options.project.baseline_root(&all_files);
+    let baseline = match options.baseline {
+        Some(ref name) => Some(Baseline::load(&baseline_path(&baseline_root, name))?),
+        None => None,
+    };
+
     if all_files.is_empty() {
         if !is_json {
             reporter.on_warning(&format!(
@@ -112,6 +212,8 @@ pub async fn run_tests(
             .as_millis() as u64;
         let output = TestRunOutput {
             files: vec![],
+            baseline: None,
+            repeat: None,
             summary: Summary {
                 total_tests: 0,
                 passed: 0,
@@ -119,7 +221,9 @@ pub async fn run_tests(
                 pending: 0,
                 skipped: 0,
                 other: 0,
+                flaky: 0,
                 parse_errors: 0,
+                file_errors: 0,
                 duration_ms: start_time.elapsed().as_millis() as u64,
                 start_time: start_epoch,
                 stop_time: stop_epoch,
@@ -150,7 +254,6 @@ pub async fn run_tests(
     // Collect parse errors and valid files
     let mut valid_files = Vec::new();
     let mut parse_error_count = 0;
-    let mut total_assertions_count = 0;
 
     for result in parse_results {
         match result {
@@ -176,11 +279,8 @@ pub async fn run_tests(
                         }
                     }
 
-                    // Count assertions in this file
-                    let file_assertions = count_assertions(&parsed_file.nodes);
-                    total_assertions_count += file_assertions;
-
-                    valid_files.push((file, parsed_file.nodes));
+                    let shells = file_shells(&parsed_file.nodes, &options);
+                    valid_files.push((file, parsed_file.nodes, shells));
                 }
                 ParseResult::Failure { errors, warnings } => {
                     // Legacy path - should not be reached with new parser
@@ -202,27 +302,141 @@ pub async fn run_tests(
         }
     }
 
+    let only_tests: Vec<String> = valid_files
+        .iter()
+        .flat_map(|(file, ast, _)| {
+            only_test_lines(ast).map(move |line| format!("{}:{}", file, line))
+        })
+        .collect();
+    if options.forbid_only && !only_tests.is_empty() {
+        return Err(anyhow::anyhow!(
+            "ONLY TEST is not allowed with --forbid-only: {}",
+            only_tests.join(", ")
+        ));
+    }
+    // Any ONLY TEST focuses the run on the TESTs marked ONLY
+    let focused = !only_tests.is_empty();
+
     // Print header
     if !is_json && !valid_files.is_empty() {
-        TextFormatter::print_header(valid_files.len(), total_assertions_count);
+        let total_assertions_count = valid_files
+            .iter()
+            .map(|(_, ast, shells)| {
+                count_assertions(ast, focused, &tag_selection) * shells.len().max(1)
+            })
+            .sum();
+        let total_runs = valid_files
+            .iter()
+            .map(|(_, _, shells)| shells.len().max(1))
+            .sum();
+        TextFormatter::print_header(total_runs, total_assertions_count);
     }
 
     // Run each file sequentially
     let mut file_results = Vec::new();
 
-    for (file, ast) in valid_files {
-        let result = run_file(&ast, &file, &options, test_filter.as_ref(), &reporter).await?;
-        file_results.push(result.file_result);
+    let mut runs = Vec::new();
+    for (file, ast, shells) in &valid_files {
+        // A file in a shell matrix runs once per shell, each reported as its own file
+        let matrix = !shells.is_empty();
+        let run_shells: Vec<Option<&str>> = if matrix {
+            shells.iter().map(|shell| Some(shell.as_str())).collect()
+        } else {
+            vec![options.shell.as_deref()]
+        };
+
+        for shell in run_shells {
+            let file_run = FileRun {
+                filename: file,
+                shell,
+                matrix,
+                iteration: None,
+                focused,
+            };
+            runs.push((file_run, ast));
+        }
+    }
+
+    // With --repeat or --until-failure every file runs again in each
+    // iteration, in fresh sessions
+    let repeating = options.repeat.is_some() || options.until_failure;
+    let iterations = options
+        .repeat
+        .unwrap_or(if options.until_failure { usize::MAX } else { 1 });
+    let run_stamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let mut stopped_at_failure = false;
+    let mut repeat_report = repeating.then(RepeatReport::default);
+
+    for iteration in 1..=iterations {
+        if repeating && !is_json {
+            TextFormatter::print_iteration(iteration, options.repeat);
+        }
+        // Without --repeat there may be no end to the iterations, so only the
+        // last one's results are kept; the repeat report has the counts of all
+        if options.until_failure && options.repeat.is_none() {
+            file_results.clear();
+        }
+
+        let mut iteration_failed = false;
+        for (file_run, ast) in &runs {
+            let file_run = FileRun {
+                iteration: repeating.then_some((iteration, run_stamp.as_str())),
+                ..*file_run
+            };
+            let result = run_file(
+                ast,
+                file_run,
+                &options,
+                test_filter.as_ref(),
+                &tag_selection,
+                &reporter,
+            )
+            .await?;
+            iteration_failed |= !result.file_result.passed();
+            if let Some(report) = repeat_report.as_mut() {
+                report.add(&result.file_result, &run_stamp, &options.project);
+            }
+            file_results.push(result.file_result);
+        }
+
+        if options.until_failure && iteration_failed {
+            stopped_at_failure = true;
+            break;
+        }
+    }
+
+    let repeat_report = repeat_report.map(|report| report.finish(stopped_at_failure));
+
+    let baseline_comparison = match (&baseline, &options.baseline) {
+        (Some(baseline), Some(name)) => {
+            Some(baseline.compare(name, &mut file_results, options.max_regression))
+        }
+        _ => None,
+    };
+
+    if let Some(ref name) = options.save_baseline {
+        Baseline::from_results(&file_results).save(&baseline_path(&baseline_root, name))?;
     }
 
     // Build output
     let total_tests: usize = file_results.iter().map(|f| f.tests.len()).sum();
-    let passed_tests: usize = file_results
+    let count_status = |status: Status| {
+        file_results
+            .iter()
+            .flat_map(|f| &f.tests)
+            .filter(|t| t.status == status)
+            .count()
+    };
+    let passed_tests = count_status(Status::Passed);
+    let failed_tests = count_status(Status::Failed);
+    let skipped_tests = count_status(Status::Skipped);
+    let pending_tests = count_status(Status::Pending);
+    let file_errors = file_results.iter().filter(|f| f.error.is_some()).count();
+    let flaky_tests = file_results
         .iter()
         .flat_map(|f| &f.tests)
-        .filter(|t| t.status == Status::Passed)
+        .filter(|t| t.is_flaky())
         .count();
-    let failed_tests = total_tests - passed_tests;
     let stop_epoch = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -230,14 +444,18 @@ pub async fn run_tests(
 
     let output = TestRunOutput {
         files: file_results,
+        baseline: baseline_comparison,
+        repeat: repeat_report,
         summary: Summary {
             total_tests,
             passed: passed_tests,
             failed: failed_tests,
-            pending: 0,
-            skipped: 0,
+            pending: pending_tests,
+            skipped: skipped_tests,
             other: 0,
+            flaky: flaky_tests,
             parse_errors: parse_error_count,
+            file_errors,
             duration_ms: start_time.elapsed().as_millis() as u64,
             start_time: start_epoch,
             stop_time: stop_epoch,
@@ -255,17 +473,121 @@ pub async fn run_tests(
             println!("{}", TextFormatter.format(&output));
         }
     }
+    write_reports(&output, &options.project.reporters)?;
 
     Ok(output)
 }
 
+/// Writes the run's report to every file under `reporters` in `hone.toml`.
+fn write_reports(
+    output: &TestRunOutput,
+    reporters: &[(OutputFormat, PathBuf)],
+) -> anyhow::Result<()> {
+    for (format, path) in reporters {
+        let report = match format {
+            OutputFormat::Json => JsonFormatter.format(output),
+            OutputFormat::Text => strip_ansi_escapes::strip_str(TextFormatter.format(output)),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, format!("{}\n", report))
+            .map_err(|e| anyhow::anyhow!("Cannot write report {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Shells a file runs in when it is part of a shell matrix, from
+/// `--shell-matrix` or its `shells` pragma. Empty when it runs once, in the
+/// shell picked by `--shell` or its `shell` pragma.
+fn file_shells(ast: &[ASTNode], options: &RunnerOptions) -> Vec<String> {
+    if let Some(ref shells) = options.shell_matrix {
+        return shells.clone();
+    }
+    if options.shell.is_some() {
+        return Vec::new();
+    }
+    pragma_shells(&file_pragmas(ast))
+}
+
+/// Pragmas of the file, including ones from imported files.
+fn file_pragmas(ast: &[ASTNode]) -> Vec<PragmaNode> {
+    with_imports(ast, "")
+        .into_iter()
+        .filter_map(|(node, _)| match node {
+            ASTNode::Pragma(pragma) => Some(pragma.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Indexes into `nodes` of the RUNs a `max_rss` assertion refers to. Only those
+/// are run in a subshell that reports their peak memory.
+fn rss_asserted_runs(nodes: &[(&ASTNode, &str)]) -> HashSet<usize> {
+    let mut measured = HashSet::new();
+    let mut last_run = None;
+    let mut named_runs = HashMap::new();
+    for (index, (node, _)) in nodes.iter().enumerate() {
+        match node {
+            ASTNode::Run(run_node) => {
+                last_run = Some(index);
+                if let Some(name) = &run_node.name {
+                    named_runs.insert(name.as_str(), index);
+                }
+            }
+            ASTNode::Assert(assert_node) => {
+                if let AssertionExpression::MaxRss { target, .. } = &assert_node.expression {
+                    let run = match target {
+                        Some(name) => named_runs.get(name.as_str()).copied(),
+                        None => last_run,
+                    };
+                    measured.extend(run);
+                }
+            }
+            _ => {}
+        }
+    }
+    measured
+}
+
+/// Nodes with the contents of each IMPORT spliced in where it appears, paired
+/// with the file each one came from.
+fn with_imports<'a>(nodes: &'a [ASTNode], filename: &'a str) -> Vec<(&'a ASTNode, &'a str)> {
+    let mut flattened = Vec::new();
+    for node in nodes {
+        match node {
+            ASTNode::Import(import) => {
+                flattened.extend(with_imports(&import.nodes, &import.resolved));
+            }
+            node => flattened.push((node, filename)),
+        }
+    }
+    flattened
+}
+
+/// One run of a file, in a single shell.
+#[derive(Clone, Copy)]
+struct FileRun<'a> {
+    filename: &'a str,
+    /// Overrides the file's `shell` pragma
+    shell: Option<&'a str>,
+    /// Whether the file also runs in other shells
+    matrix: bool,
+    /// Iteration and run stamp, for a `--repeat` or `--until-failure` run
+    iteration: Option<(usize, &'a str)>,
+    /// Whether some TEST in the run is marked ONLY, so unmarked ones are skipped
+    focused: bool,
+}
+
 async fn run_file(
     ast: &[ASTNode],
-    filename: &str,
+    file_run: FileRun<'_>,
     options: &RunnerOptions,
     test_filter: Option<&TestFilter>,
+    tag_selection: &TagSelection,
     reporter: &impl Reporter,
 ) -> anyhow::Result<FileRunResult> {
+    let filename = file_run.filename;
     let is_json = options.output_format == OutputFormat::Json;
     let file_start = std::time::Instant::now();
     let cwd = Path::new(filename)
@@ -281,19 +603,33 @@ async fn run_file(
         .into_owned();
 
     // Extract pragmas
-    let pragmas: Vec<_> = ast
-        .iter()
-        .filter_map(|node| {
-            if let ASTNode::Pragma(pragma) = node {
-                Some(pragma.clone())
-            } else {
-                None
-            }
+    let pragmas = file_pragmas(ast);
+
+    // Create shell config from pragmas, on top of hone.toml settings
+    let mut settings = options.project.settings_for(filename);
+    settings.pass_env.extend(options.pass_env.iter().cloned());
+    let mut shell_config = create_shell_config(&pragmas, filename, &cwd, file_run.shell, &settings);
+    // --env wins over pragmas and hone.toml
+    for (name, value) in &options.env {
+        shell_config.passed_env.remove(name);
+        shell_config.env.insert(name.clone(), value.clone());
+    }
+    let runs_dir = options.project.runs_dir(filename);
+    let artifact_dir = file_run.iteration.map(|(iteration, run_stamp)| {
+        let shell = file_run.matrix.then(|| shell_name(&shell_config.shell));
+        iteration_artifact_dir(&runs_dir, filename, run_stamp, iteration, shell)
+    });
+    shell_config.artifact_dir = artifact_dir.clone().or_else(|| {
+        // Without an artifact_dir in hone.toml the session picks `.hone/runs` next to the file
+        options.project.artifact_dir.as_ref().map(|_| {
+            let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+            let stem = Path::new(filename)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("test");
+            runs_dir.join(format!("{}-{}", timestamp, stem))
         })
-        .collect();
-
-    // Create shell config from pragmas
-    let shell_config = create_shell_config(&pragmas, filename, &cwd, options.shell.as_deref());
+    });
 
     // Group nodes by TEST block
     let mut test_blocks = group_nodes_by_test(ast);
@@ -308,81 +644,272 @@ async fn run_file(
                 .unwrap_or(false)
         });
     }
+    if !tag_selection.is_empty() {
+        test_blocks.retain(|block| tag_selection.matches(&block_tags(block)));
+    }
 
     let mut total_assertions_passed = 0;
-    let mut failure: Option<TestFailure> = None;
+    let mut failures: Vec<TestFailure> = Vec::new();
     let mut test_results: Vec<TestResult> = Vec::new();
+    let mut flaky: Vec<(Vec<TestFailure>, usize)> = Vec::new();
+    let file_retries = file_retries(&pragmas);
 
-    for block in test_blocks {
-        let test_start = std::time::Instant::now();
+    // Decided before any session starts, so skipped TESTs never start a shell
+    let conditions = ConditionContext {
+        env: &shell_config.env,
+    };
+    let missing = conditions.missing_commands(&pragmas);
+    let not_run: Vec<_> = test_blocks
+        .iter()
+        .map(|block| {
+            not_run_status(block, file_run.focused)
+                .map(|(status, message)| (status, message.to_string(), NotRun::Marked))
+                .or_else(|| {
+                    skip_reason(block, &missing, &conditions).map(|reason| {
+                        let message = format!("Skipped: {}", reason);
+                        (Status::Skipped, message, NotRun::Condition(reason))
+                    })
+                })
+        })
+        .collect();
+    let sessions = not_run.iter().filter(|status| status.is_none()).count();
+    let mut pool = SessionPool::new(shell_config.clone(), sessions);
+
+    'tests: for (block, not_run) in test_blocks.into_iter().zip(not_run) {
         let test_line = block.test_node.as_ref().map(|n| n.line()).unwrap_or(1);
         let test_name = block.test_name.clone().unwrap_or_default();
+        if let Some((status, message, reason)) = not_run {
+            test_results.push(TestResult {
+                name: test_name,
+                line: test_line,
+                status,
+                duration_ms: 0,
+                runs: vec![],
+                assertions: vec![],
+                message: Some(message),
+                attempts: vec![],
+                tags: block_tags(&block),
+                not_run: Some(reason),
+            });
+            continue;
+        }
 
-        // Create a fresh shell session for each TEST block
-        let mut session = ShellSession::new(shell_config.clone());
+        let max_attempts = block_retries(&block).unwrap_or(file_retries) as usize + 1;
+        let mut failed_attempts: Vec<(TestResult, TestFailure)> = Vec::new();
 
-        match session.start().await {
-            Ok(_) => {}
-            Err(e) => {
-                failure = Some(TestFailure {
-                    filename: filename.to_string(),
-                    line: test_line,
-                    test_name: block.test_name.clone(),
-                    run_command: None,
-                    assertion: None,
-                    expected: None,
-                    actual: None,
-                    error: Some(format!("Failed to start shell: {}", e)),
-                });
-
-                test_results.push(TestResult {
-                    name: test_name,
-                    line: test_line,
-                    status: Status::Failed,
-                    duration_ms: test_start.elapsed().as_millis() as u64,
-                    runs: vec![],
-                });
-                break;
-            }
-        }
+        let result = loop {
+            let test_start = std::time::Instant::now();
+
+            // Each TEST block, and each retry of one, gets a fresh shell session;
+            // the following ones start while this one runs
+            let mut session = match pool.take().await {
+                Ok(session) => session,
+                Err(e) => {
+                    failures.push(TestFailure {
+                        filename: filename.to_string(),
+                        line: test_line,
+                        test_name: block.test_name.clone(),
+                        run_command: None,
+                        assertion: None,
+                        expected: None,
+                        actual: None,
+                        error: Some(format!("Failed to start shell: {}", e)),
+                        full_output: Vec::new(),
+                        shell: None,
+                        attempts: 1,
+                    });
+
+                    test_results.push(TestResult {
+                        name: test_name,
+                        line: test_line,
+                        status: Status::Failed,
+                        duration_ms: test_start.elapsed().as_millis() as u64,
+                        runs: vec![],
+                        assertions: vec![],
+                        message: None,
+                        attempts: vec![],
+                        tags: block_tags(&block),
+                        not_run: None,
+                    });
+                    break 'tests;
+                }
+            };
+
+            let result = execute_test_block(&block, &mut session, filename, reporter).await;
+            let _ = session.stop().await;
 
-        let result = execute_test_block(&block, &mut session, filename, reporter).await;
-        let _ = session.stop().await;
+            if failed_attempts.len() + 1 < max_attempts {
+                if let (Some(test_result), Some(failure)) = (&result.test_result, &result.failure) {
+                    failed_attempts.push((test_result.clone(), failure.clone()));
+                    continue;
+                }
+            }
+            break result;
+        };
 
         total_assertions_passed += result.assertions_passed;
+        let attempts_made = failed_attempts.len() + 1;
+        let (attempts, attempt_failures): (Vec<_>, Vec<_>) = failed_attempts.into_iter().unzip();
 
-        if let Some(test_result) = result.test_result {
+        if let Some(mut test_result) = result.test_result {
+            test_result.attempts = attempts;
+            if test_result.is_flaky() {
+                flaky.push((attempt_failures, max_attempts));
+            }
             test_results.push(test_result);
         }
 
-        if let Some(f) = result.failure {
-            failure = Some(f);
-            break;
+        if let Some(mut f) = result.failure {
+            f.attempts = attempts_made;
+            failures.push(f);
+            if !result.session_lost {
+                break;
+            }
         }
     }
 
+    pool.shutdown().await;
     let file_duration_ms = file_start.elapsed().as_millis() as u64;
 
+    let mut file_error = None;
+    if let Some((pragma, budget_ms)) = file_budget(&pragmas) {
+        if file_duration_ms > budget_ms {
+            let breakdown = budget_breakdown(&test_results);
+            failures.push(TestFailure {
+                filename: filename.to_string(),
+                line: pragma.line,
+                test_name: None,
+                run_command: None,
+                assertion: Some(pragma.raw.clone()),
+                expected: Some(format!("file to finish within {}", pragma.value)),
+                actual: Some(format_duration(file_duration_ms as f64)),
+                error: Some(breakdown.clone()),
+                full_output: Vec::new(),
+                shell: None,
+                attempts: 1,
+            });
+            file_error = Some(format!(
+                "File took {}, exceeding its budget of {}\n{}",
+                format_duration(file_duration_ms as f64),
+                pragma.value,
+                breakdown
+            ));
+        }
+    }
+
     let file_result = FileResult {
         file: filename.to_string(),
         shell: shell_config.shell.clone(),
         tests: test_results,
+        matrix: file_run.matrix,
+        iteration: file_run.iteration.map(|(iteration, _)| iteration),
+        env: reported_env(&shell_config),
+        error: file_error,
     };
 
-    if let Some(ref f) = failure {
-        reporter.on_failure(f);
+    // Only failing iterations keep their artifacts
+    if let Some(ref dir) = artifact_dir {
+        if failures.is_empty() {
+            let _ = tokio::fs::remove_dir_all(dir).await;
+        }
+    }
+
+    if !failures.is_empty() {
+        for f in &mut failures {
+            f.shell = file_run.matrix.then(|| shell_config.shell.clone());
+            reporter.on_failure(f);
+        }
     } else if !is_json {
-        TextFormatter::print_file_result(
-            &basename,
-            true,
-            total_assertions_passed,
-            file_duration_ms,
-        );
+        let label = if file_run.matrix {
+            format!("{} [{}]", basename, shell_name(&shell_config.shell))
+        } else {
+            basename
+        };
+        TextFormatter::print_file_result(&label, true, total_assertions_passed, file_duration_ms);
+    }
+
+    if !is_json {
+        if options.verbose {
+            TextFormatter::print_env(&file_result.env);
+        }
+        TextFormatter::print_not_run(&file_result);
+        for (attempt_failures, max_attempts) in &flaky {
+            print_flaky(attempt_failures, *max_attempts);
+        }
+        TextFormatter::print_bench_stats(&file_result);
     }
 
     Ok(FileRunResult { file_result })
 }
 
+/// Environment a file's shells started with, as shown in reports. Values of
+/// host variables passed through with `pass_env` may be secrets, so only
+/// their names are shown.
+fn reported_env(config: &ShellConfig) -> BTreeMap<String, String> {
+    config
+        .env
+        .iter()
+        .map(|(name, value)| {
+            let value = if config.passed_env.contains(name) {
+                "***".to_string()
+            } else {
+                value.clone()
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+/// Times a failed TEST is retried by default, from the `#! retries:` pragma.
+fn file_retries(pragmas: &[PragmaNode]) -> u32 {
+    pragmas
+        .iter()
+        .rev()
+        .find(|p| p.pragma_type == PragmaType::Retries)
+        .and_then(|p| p.value.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Retries set by a `RETRY` statement in the block, which override the file's.
+fn block_retries(block: &TestBlock) -> Option<u32> {
+    with_imports(&block.nodes, "")
+        .into_iter()
+        .rev()
+        .find_map(|(node, _)| match node {
+            ASTNode::Retry(retry) => Some(retry.count),
+            _ => None,
+        })
+}
+
+/// Returns the `#! budget:` pragma and its limit in milliseconds, if the file has one.
+/// Invalid values are reported by the parser, so they are ignored here.
+fn file_budget(pragmas: &[PragmaNode]) -> Option<(&PragmaNode, u64)> {
+    pragmas
+        .iter()
+        .rev()
+        .find(|p| p.pragma_type == PragmaType::Budget)
+        .and_then(|p| {
+            let (duration, _) = crate::parser::lexer::parse_duration(&p.value, 0)?;
+            Some((p, duration_to_ms(&duration) as u64))
+        })
+}
+
+/// Lists the slowest tests first so an over-budget file shows where the time went.
+fn budget_breakdown(tests: &[TestResult]) -> String {
+    let mut by_duration: Vec<&TestResult> = tests.iter().collect();
+    by_duration.sort_by_key(|t| std::cmp::Reverse(t.duration_ms));
+
+    let mut lines = vec!["Slowest tests:".to_string()];
+    for test in by_duration {
+        lines.push(format!(
+            "  {:>8}  \"{}\"",
+            format_duration(test.duration_ms as f64),
+            test.name
+        ));
+    }
+    lines.join("\n")
+}
+
 fn group_nodes_by_test(nodes: &[ASTNode]) -> Vec<TestBlock> {
     let mut blocks = Vec::new();
     let mut current_block = TestBlock::default();
@@ -391,12 +918,17 @@ fn group_nodes_by_test(nodes: &[ASTNode]) -> Vec<TestBlock> {
         match node {
             ASTNode::Test(test_node) => {
                 if current_block.test_name.is_some() || !current_block.nodes.is_empty() {
-                    blocks.push(std::mem::take(&mut current_block));
+                    blocks.extend(expand_params(std::mem::take(&mut current_block)));
                 }
                 current_block.test_name = Some(test_node.name.clone());
                 current_block.test_node = Some(node.clone());
             }
             ASTNode::Pragma(_) | ASTNode::Comment(_) => {}
+            // Imports of pragmas only shouldn't start an implicit block
+            ASTNode::Import(_)
+                if with_imports(std::slice::from_ref(node), "")
+                    .iter()
+                    .all(|(node, _)| matches!(node, ASTNode::Pragma(_) | ASTNode::Comment(_))) => {}
             _ => {
                 current_block.nodes.push(node.clone());
             }
@@ -404,19 +936,128 @@ fn group_nodes_by_test(nodes: &[ASTNode]) -> Vec<TestBlock> {
     }
 
     if current_block.test_name.is_some() || !current_block.nodes.is_empty() {
-        blocks.push(current_block);
+        blocks.extend(expand_params(current_block));
     }
 
     blocks
 }
 
-fn count_assertions(nodes: &[ASTNode]) -> usize {
-    nodes
+/// Turns a parameterized TEST into one block per row, named after the row
+/// and starting with an ENV for each parameter.
+fn expand_params(block: TestBlock) -> Vec<TestBlock> {
+    let Some(ASTNode::Test(TestNode {
+        name,
+        params: Some(params),
+        line,
+        ..
+    })) = &block.test_node
+    else {
+        return vec![block];
+    };
+
+    params
+        .rows
+        .iter()
+        .map(|row| {
+            let env_nodes = params.names.iter().zip(row).map(|(key, value)| {
+                ASTNode::Env(EnvNode {
+                    key: key.clone(),
+                    value: value.clone(),
+                    line: *line,
+                })
+            });
+            TestBlock {
+                test_name: Some(params.expand_name(name, row)),
+                test_node: block.test_node.clone(),
+                nodes: env_nodes.chain(block.nodes.iter().cloned()).collect(),
+            }
+        })
+        .collect()
+}
+
+fn block_tags(block: &TestBlock) -> Vec<String> {
+    match &block.test_node {
+        Some(ASTNode::Test(test)) => test.tags.clone(),
+        _ => vec![],
+    }
+}
+
+fn test_modifier(block: &TestBlock) -> Option<TestModifier> {
+    match &block.test_node {
+        Some(ASTNode::Test(test)) => test.modifier,
+        _ => None,
+    }
+}
+
+/// Lines of the TESTs marked `ONLY`.
+fn only_test_lines(nodes: &[ASTNode]) -> impl Iterator<Item = usize> + '_ {
+    nodes.iter().filter_map(|node| match node {
+        ASTNode::Test(test) if test.modifier == Some(TestModifier::Only) => Some(test.line),
+        _ => None,
+    })
+}
+
+/// Status and reason for a TEST that is reported without running.
+fn not_run_status(block: &TestBlock, focused: bool) -> Option<(Status, &'static str)> {
+    match test_modifier(block) {
+        Some(TestModifier::Todo) => Some((Status::Pending, "Marked TODO")),
+        Some(TestModifier::Skip) => Some((Status::Skipped, "Skipped with SKIP")),
+        Some(TestModifier::Only) => None,
+        None if focused => Some((
+            Status::Skipped,
+            "Skipped because other tests are marked ONLY",
+        )),
+        None => None,
+    }
+}
+
+/// Why a TEST is skipped by `#! require:` or its `SKIP IF` conditions, if it is.
+fn skip_reason(
+    block: &TestBlock,
+    missing: &[String],
+    conditions: &ConditionContext,
+) -> Option<String> {
+    if !missing.is_empty() {
+        return Some(format!(
+            "required commands not on PATH: {}",
+            missing.join(", ")
+        ));
+    }
+    with_imports(&block.nodes, "")
+        .into_iter()
+        .find_map(|(node, _)| match node {
+            ASTNode::SkipIf(skip_if) => conditions.skip_reason(&skip_if.condition),
+            _ => None,
+        })
+}
+
+/// Assertions in the TESTs that will run.
+fn count_assertions(nodes: &[ASTNode], focused: bool, tag_selection: &TagSelection) -> usize {
+    group_nodes_by_test(nodes)
         .iter()
-        .filter(|node| matches!(node, ASTNode::Assert(_)))
+        .filter(|block| tag_selection.matches(&block_tags(block)))
+        .filter(|block| not_run_status(block, focused).is_none())
+        .flat_map(|block| with_imports(&block.nodes, ""))
+        .filter(|(node, _)| matches!(node, ASTNode::Assert(_)))
         .count()
 }
 
+/// Exports the values of ENV statements not yet applied.
+async fn apply_env_vars(
+    session: &mut ShellSession,
+    pending: &mut Vec<(String, String)>,
+) -> Result<(), String> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+    session
+        .set_env_vars(pending)
+        .await
+        .map_err(|e| format!("Failed to set environment variables: {}", e))?;
+    pending.clear();
+    Ok(())
+}
+
 async fn execute_test_block(
     block: &TestBlock,
     session: &mut ShellSession,
@@ -434,17 +1075,58 @@ async fn execute_test_block(
     let mut run_results: HashMap<String, RunResult> = HashMap::new();
     let mut assertions_passed = 0;
     let mut pending_env_vars: Vec<(String, String)> = Vec::new();
+    let mut test_timeout: Option<(u64, usize)> = None;
+    let mut test_level_asserts: Vec<(&AssertNode, &str)> = Vec::new();
 
     // Track runs with their assertions
     let mut command_runs: Vec<CommandRun> = Vec::new();
     let mut current_run_assertions: Vec<AssertionOutput> = Vec::new();
 
-    for node in &block.nodes {
+    let nodes = with_imports(&block.nodes, filename);
+    let measured_runs = rss_asserted_runs(&nodes);
+
+    // Failures point at the file a node came from, which differs for imported nodes
+    for (index, &(node, filename)) in nodes.iter().enumerate() {
         match node {
             ASTNode::Env(env_node) => {
                 pending_env_vars.push((env_node.key.clone(), env_node.value.clone()));
             }
 
+            ASTNode::Timeout(timeout_node) => {
+                test_timeout = Some((timeout_to_ms(&timeout_node.duration), timeout_node.line));
+            }
+
+            ASTNode::Stub(stub_node) => {
+                // ENV statements before the STUB go first, so a PATH they set
+                // still gets the stub directory in front of it
+                let result = apply_env_vars(session, &mut pending_env_vars).await;
+                if let Err(e) = result.and(session.add_stub(stub_node).await) {
+                    return setup_failure(
+                        block,
+                        (filename, node.line()),
+                        e,
+                        command_runs,
+                        test_start,
+                        assertions_passed,
+                    );
+                }
+            }
+
+            ASTNode::Http(http_node) => {
+                // Likewise, an ENV HONE_HTTP_URL before the first HTTP is replaced
+                let result = apply_env_vars(session, &mut pending_env_vars).await;
+                if let Err(e) = result.and(session.add_http_route(http_node).await) {
+                    return setup_failure(
+                        block,
+                        (filename, node.line()),
+                        e,
+                        command_runs,
+                        test_start,
+                        assertions_passed,
+                    );
+                }
+            }
+
             ASTNode::Run(run_node) => {
                 // Finalize previous run if any
                 if let (Some(prev_result), Some(prev_node)) = (&last_run_result, last_run_node) {
@@ -466,41 +1148,30 @@ async fn execute_test_block(
                         stdout: prev_result.stdout.clone(),
                         stderr: prev_result.stderr.clone(),
                         assertions: std::mem::take(&mut current_run_assertions),
+                        resources: prev_result.resources,
+                        bench: prev_result.bench.clone(),
+                        stdout_truncated: prev_result.stdout_truncation.clone(),
+                        stderr_truncated: prev_result.stderr_truncation.clone(),
                     });
                 }
 
                 // Apply any pending env vars before the run
-                if !pending_env_vars.is_empty() {
-                    if let Err(e) = session.set_env_vars(&pending_env_vars).await {
-                        let test_result = TestResult {
-                            name: block.test_name.clone().unwrap_or_default(),
-                            line: block.test_node.as_ref().map(|n| n.line()).unwrap_or(1),
-                            status: Status::Failed,
-                            duration_ms: test_start.elapsed().as_millis() as u64,
-                            runs: command_runs,
-                        };
-                        return ExecuteResult {
-                            assertions_passed,
-                            failure: Some(TestFailure {
-                                filename: filename.to_string(),
-                                line: node.line(),
-                                test_name: block.test_name.clone(),
-                                run_command: None,
-                                assertion: None,
-                                expected: None,
-                                actual: None,
-                                error: Some(format!("Failed to set environment variables: {}", e)),
-                            }),
-                            test_result: Some(test_result),
-                        };
-                    }
-                    pending_env_vars.clear();
+                if let Err(e) = apply_env_vars(session, &mut pending_env_vars).await {
+                    return setup_failure(
+                        block,
+                        (filename, node.line()),
+                        e,
+                        command_runs,
+                        test_start,
+                        assertions_passed,
+                    );
                 }
 
-                match session
-                    .run(&run_node.command, run_node.name.as_deref())
-                    .await
-                {
+                let (timeout_ms, timeout_limit) =
+                    effective_timeout(run_node, test_timeout, session.config().timeout_ms);
+
+                let measure_rss = measured_runs.contains(&index);
+                match run_command(session, run_node, timeout_ms, measure_rss).await {
                     Ok(result) => {
                         if let Some(ref name) = run_node.name {
                             run_results.insert(name.clone(), result.clone());
@@ -509,6 +1180,8 @@ async fn execute_test_block(
                         last_run_node = Some(run_node);
                     }
                     Err(e) => {
+                        let message = describe_run_error(&e, timeout_limit);
+
                         // Add the failed run
                         command_runs.push(CommandRun {
                             name: run_node.name.clone(),
@@ -518,8 +1191,12 @@ async fn execute_test_block(
                             duration_ms: 0,
                             exit_code: -1,
                             stdout: String::new(),
-                            stderr: e.clone(),
+                            stderr: message.clone(),
                             assertions: vec![],
+                            resources: None,
+                            bench: None,
+                            stdout_truncated: None,
+                            stderr_truncated: None,
                         });
 
                         let test_result = TestResult {
@@ -528,6 +1205,11 @@ async fn execute_test_block(
                             status: Status::Failed,
                             duration_ms: test_start.elapsed().as_millis() as u64,
                             runs: command_runs,
+                            assertions: vec![],
+                            message: None,
+                            attempts: vec![],
+                            tags: block_tags(block),
+                            not_run: None,
                         };
 
                         return ExecuteResult {
@@ -540,14 +1222,28 @@ async fn execute_test_block(
                                 assertion: None,
                                 expected: None,
                                 actual: None,
-                                error: Some(e),
+                                error: Some(message),
+                                full_output: Vec::new(),
+                                shell: None,
+                                attempts: 1,
                             }),
                             test_result: Some(test_result),
+                            session_lost: e.is_fatal(),
                         };
                     }
                 }
             }
 
+            ASTNode::Assert(assert_node)
+                if matches!(
+                    assert_node.expression,
+                    AssertionExpression::TestDuration { .. }
+                ) =>
+            {
+                // Needs the whole block's duration, so evaluate once every RUN has finished
+                test_level_asserts.push((assert_node, filename));
+            }
+
             ASTNode::Assert(assert_node) => {
                 let result = evaluate_assertion(
                     assert_node,
@@ -592,6 +1288,10 @@ async fn execute_test_block(
                             stdout: prev_result.stdout.clone(),
                             stderr: prev_result.stderr.clone(),
                             assertions: std::mem::take(&mut current_run_assertions),
+                            resources: prev_result.resources,
+                            bench: prev_result.bench.clone(),
+                            stdout_truncated: prev_result.stdout_truncation.clone(),
+                            stderr_truncated: prev_result.stderr_truncation.clone(),
                         });
                     }
 
@@ -601,6 +1301,11 @@ async fn execute_test_block(
                         status: Status::Failed,
                         duration_ms: test_start.elapsed().as_millis() as u64,
                         runs: command_runs,
+                        assertions: vec![],
+                        message: None,
+                        attempts: vec![],
+                        tags: block_tags(block),
+                        not_run: None,
                     };
 
                     return ExecuteResult {
@@ -614,8 +1319,12 @@ async fn execute_test_block(
                             expected: Some(result.expected),
                             actual: Some(result.actual),
                             error: result.error,
+                            full_output: full_output_paths(last_run_result.as_ref()),
+                            shell: None,
+                            attempts: 1,
                         }),
                         test_result: Some(test_result),
+                        session_lost: false,
                     };
                 }
 
@@ -646,21 +1355,173 @@ async fn execute_test_block(
             stdout: prev_result.stdout.clone(),
             stderr: prev_result.stderr.clone(),
             assertions: current_run_assertions,
+            resources: prev_result.resources,
+            bench: prev_result.bench.clone(),
+            stdout_truncated: prev_result.stdout_truncation.clone(),
+            stderr_truncated: prev_result.stderr_truncation.clone(),
         });
     }
 
+    let test_duration_ms = test_start.elapsed().as_millis() as u64;
+    let mut test_assertions: Vec<AssertionOutput> = Vec::new();
+    let mut failure: Option<TestFailure> = None;
+
+    for (assert_node, filename) in test_level_asserts {
+        let AssertionExpression::TestDuration { predicate } = &assert_node.expression else {
+            continue;
+        };
+        let result = evaluate_duration_predicate(test_duration_ms, predicate);
+        let expected = format!("test.{}", result.expected);
+
+        test_assertions.push(AssertionOutput {
+            line: assert_node.line,
+            expression: assert_node.raw.clone(),
+            status: if result.passed {
+                Status::Passed
+            } else {
+                Status::Failed
+            },
+            expected: (!result.passed).then(|| expected.clone()),
+            actual: (!result.passed).then(|| result.actual.clone()),
+        });
+
+        if result.passed {
+            assertions_passed += 1;
+        } else if failure.is_none() {
+            failure = Some(TestFailure {
+                filename: filename.to_string(),
+                line: assert_node.line,
+                test_name: block.test_name.clone(),
+                run_command: None,
+                assertion: Some(assert_node.raw.clone()),
+                expected: Some(expected),
+                actual: Some(result.actual),
+                error: None,
+                full_output: Vec::new(),
+                shell: None,
+                attempts: 1,
+            });
+        }
+    }
+
     let test_result = TestResult {
         name: block.test_name.clone().unwrap_or_default(),
         line: block.test_node.as_ref().map(|n| n.line()).unwrap_or(1),
-        status: Status::Passed,
-        duration_ms: test_start.elapsed().as_millis() as u64,
+        status: if failure.is_some() {
+            Status::Failed
+        } else {
+            Status::Passed
+        },
+        duration_ms: test_duration_ms,
         runs: command_runs,
+        assertions: test_assertions,
+        message: None,
+        attempts: vec![],
+        tags: block_tags(block),
+        not_run: None,
     };
 
     ExecuteResult {
         assertions_passed,
-        failure: None,
+        failure,
         test_result: Some(test_result),
+        session_lost: false,
+    }
+}
+
+/// Result of a TEST that failed while preparing the session rather than in a
+/// RUN or ASSERT.
+fn setup_failure(
+    block: &TestBlock,
+    (filename, line): (&str, usize),
+    error: String,
+    command_runs: Vec<CommandRun>,
+    test_start: std::time::Instant,
+    assertions_passed: usize,
+) -> ExecuteResult {
+    let test_result = TestResult {
+        name: block.test_name.clone().unwrap_or_default(),
+        line: block.test_node.as_ref().map(|n| n.line()).unwrap_or(1),
+        status: Status::Failed,
+        duration_ms: test_start.elapsed().as_millis() as u64,
+        runs: command_runs,
+        assertions: vec![],
+        message: None,
+        attempts: vec![],
+        tags: block_tags(block),
+        not_run: None,
+    };
+    ExecuteResult {
+        assertions_passed,
+        failure: Some(TestFailure {
+            filename: filename.to_string(),
+            line,
+            test_name: block.test_name.clone(),
+            run_command: None,
+            assertion: None,
+            expected: None,
+            actual: None,
+            error: Some(error),
+            full_output: Vec::new(),
+            shell: None,
+            attempts: 1,
+        }),
+        test_result: Some(test_result),
+        session_lost: false,
+    }
+}
+
+/// Runs a RUN's command, once or `REPEAT` times. Repetition stops early at the
+/// first non-zero exit code so that iteration's output can be asserted on.
+async fn run_command(
+    session: &mut ShellSession,
+    run_node: &RunNode,
+    timeout_ms: u64,
+    measure_rss: bool,
+) -> Result<RunResult, RunError> {
+    let Some(iterations) = run_node.repeat else {
+        return session
+            .run(
+                &run_node.command,
+                run_node.name.as_deref(),
+                timeout_ms,
+                measure_rss,
+            )
+            .await;
+    };
+
+    let mut samples = Vec::new();
+    let mut resources: Option<ResourceUsage> = None;
+
+    loop {
+        let mut result = session
+            .run(
+                &run_node.command,
+                run_node.name.as_deref(),
+                timeout_ms,
+                measure_rss,
+            )
+            .await?;
+        samples.push(result.duration_ms);
+        resources = match (resources, result.resources) {
+            (Some(total), Some(usage)) => Some(ResourceUsage {
+                max_rss_bytes: total.max_rss_bytes.max(usage.max_rss_bytes),
+                user_cpu_ms: total.user_cpu_ms + usage.user_cpu_ms,
+                system_cpu_ms: total.system_cpu_ms + usage.system_cpu_ms,
+            }),
+            (Some(total), None) => Some(total),
+            (None, usage) => usage,
+        };
+
+        if result.exit_code != 0 || samples.len() >= iterations as usize {
+            result.duration_ms = samples.iter().sum();
+            result.resources = resources;
+            result.bench = BenchStats::from_samples(&samples).map(|stats| BenchStats {
+                requested: iterations as usize,
+                ..stats
+            });
+            return Ok(result);
+        }
     }
 }
 
@@ -683,10 +1544,50 @@ async fn evaluate_assertion(
                 Err(assertion) => return assertion,
             };
 
+            if let Some(truncation) = get_output_truncation(target_result, selector) {
+                if !node.partial {
+                    return truncated_output(selector, truncation);
+                }
+            }
+
             let output = get_output_value(target_result, selector);
             evaluate_output_predicate(output, predicate)
         }
 
+        crate::parser::ast::AssertionExpression::Bytes {
+            target,
+            selector,
+            predicate,
+        } => {
+            let target_result = match resolve_target(target, last_run_result, run_results) {
+                Ok(result) => result,
+                Err(assertion) => return assertion,
+            };
+
+            if let Some(truncation) = get_output_truncation(target_result, selector) {
+                if !node.partial {
+                    return truncated_output(selector, truncation);
+                }
+            }
+
+            evaluate_bytes_predicate(get_output_bytes(target_result, selector), predicate)
+        }
+
+        crate::parser::ast::AssertionExpression::Length {
+            target,
+            selector,
+            predicate,
+        } => {
+            let target_result = match resolve_target(target, last_run_result, run_results) {
+                Ok(result) => result,
+                Err(assertion) => return assertion,
+            };
+
+            // The full length is known even when the output was truncated
+            let length = output_length(target_result, selector);
+            evaluate_length_predicate(selector, length, predicate)
+        }
+
         crate::parser::ast::AssertionExpression::ExitCode { target, predicate } => {
             let target_result = match resolve_target(target, last_run_result, run_results) {
                 Ok(result) => result,
@@ -705,6 +1606,77 @@ async fn evaluate_assertion(
             evaluate_duration_predicate(target_result.duration_ms, predicate)
         }
 
+        crate::parser::ast::AssertionExpression::DurationStat {
+            target,
+            statistic,
+            predicate,
+        } => {
+            let target_result = match resolve_target(target, last_run_result, run_results) {
+                Ok(result) => result,
+                Err(assertion) => return assertion,
+            };
+
+            let name = format!("duration.{}", statistic.as_str());
+            match &target_result.bench {
+                Some(stats) => {
+                    evaluate_named_duration_predicate(&name, stats.get(*statistic), predicate)
+                }
+                None => AssertionResult::with_error(
+                    false,
+                    format!("{} of a repeated RUN", name),
+                    "RUN was not repeated".to_string(),
+                    "Duration statistics need a RUN with REPEAT <count>".to_string(),
+                ),
+            }
+        }
+
+        crate::parser::ast::AssertionExpression::MaxRss { target, predicate } => {
+            let target_result = match resolve_target(target, last_run_result, run_results) {
+                Ok(result) => result,
+                Err(assertion) => return assertion,
+            };
+
+            match target_result.resources {
+                Some(ResourceUsage {
+                    max_rss_bytes: Some(max_rss_bytes),
+                    ..
+                }) => evaluate_memory_predicate(max_rss_bytes, predicate),
+                Some(_) => AssertionResult::with_error(
+                    false,
+                    "max_rss to be measured".to_string(),
+                    "not measured".to_string(),
+                    "The RUN's peak memory was not reported; it needs a POSIX shell, and the \
+                     command must not exit the subshell it runs in"
+                        .to_string(),
+                ),
+                None => resources_unavailable("max_rss"),
+            }
+        }
+
+        crate::parser::ast::AssertionExpression::CpuTime { target, predicate } => {
+            let target_result = match resolve_target(target, last_run_result, run_results) {
+                Ok(result) => result,
+                Err(assertion) => return assertion,
+            };
+
+            match target_result.resources {
+                Some(usage) => {
+                    evaluate_named_duration_predicate("cpu_time", usage.cpu_time_ms(), predicate)
+                }
+                None => resources_unavailable("cpu_time"),
+            }
+        }
+
+        // Deferred by `execute_test_block` until the TEST block finishes
+        crate::parser::ast::AssertionExpression::TestDuration { .. } => {
+            AssertionResult::with_error(
+                false,
+                "test.duration checked when the TEST finishes".to_string(),
+                "checked after a RUN".to_string(),
+                "test.duration can only be evaluated once the TEST block has finished".to_string(),
+            )
+        }
+
         crate::parser::ast::AssertionExpression::File { path, predicate } => {
             let shell_cwd = match session.get_cwd().await {
                 Ok(cwd) => cwd,
@@ -719,9 +1691,61 @@ async fn evaluate_assertion(
             };
             evaluate_file_predicate(path, predicate, &shell_cwd).await
         }
+
+        crate::parser::ast::AssertionExpression::Stub { command, predicate } => {
+            match session.stub_calls(command) {
+                Ok(Some(calls)) => evaluate_stub_predicate(command, &calls, predicate),
+                Ok(None) => AssertionResult::with_error(
+                    false,
+                    format!("a STUB for {}", command),
+                    "no STUB in this TEST".to_string(),
+                    format!(
+                        "ASSERT stub {} without a preceding STUB {}",
+                        command, command
+                    ),
+                ),
+                Err(e) => AssertionResult::with_error(
+                    false,
+                    format!("calls of stub {}", command),
+                    "failed to read recorded calls".to_string(),
+                    e,
+                ),
+            }
+        }
+
+        crate::parser::ast::AssertionExpression::Http { request, predicate } => {
+            match session.http_requests() {
+                Some(requests) => evaluate_http_predicate(request, &requests, predicate),
+                None => AssertionResult::with_error(
+                    false,
+                    format!("HTTP routes for {}", request.raw),
+                    "no HTTP in this TEST".to_string(),
+                    "ASSERT http request without a preceding HTTP route in the TEST".to_string(),
+                ),
+            }
+        }
     }
 }
 
+/// Files holding the complete output of any stream of `result` that was truncated.
+fn full_output_paths(result: Option<&RunResult>) -> Vec<String> {
+    result
+        .into_iter()
+        .flat_map(|r| [&r.stdout_truncation, &r.stderr_truncation])
+        .flatten()
+        .map(|truncation| truncation.path.clone())
+        .collect()
+}
+
+fn resources_unavailable(measurement: &str) -> AssertionResult {
+    AssertionResult::with_error(
+        false,
+        format!("{} to be measured", measurement),
+        "not measured".to_string(),
+        "Resource usage requires /proc, which is not available on this system".to_string(),
+    )
+}
+
 fn resolve_target<'a>(
     target: &Option<String>,
     last_run_result: Option<&'a RunResult>,
@@ -890,9 +1914,15 @@ mod tests {
             stdout: "output".to_string(),
             stdout_raw: "output".to_string(),
             stderr: String::new(),
+            stdout_bytes: Vec::new(),
+            stderr_bytes: Vec::new(),
             exit_code: 0,
             duration_ms: 100,
             stderr_path: String::new(),
+            resources: None,
+            bench: None,
+            stdout_truncation: None,
+            stderr_truncation: None,
         };
         let result = resolve_target(&None, Some(&last_run), &run_results);
 
@@ -908,9 +1938,15 @@ mod tests {
             stdout: "named output".to_string(),
             stdout_raw: "named output".to_string(),
             stderr: String::new(),
+            stdout_bytes: Vec::new(),
+            stderr_bytes: Vec::new(),
             exit_code: 0,
             duration_ms: 50,
             stderr_path: String::new(),
+            resources: None,
+            bench: None,
+            stdout_truncation: None,
+            stderr_truncation: None,
         };
         run_results.insert("build".to_string(), run);
 
@@ -920,4 +1956,346 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap().run_id, "named-run");
     }
+
+    fn make_run_node(timeout: Option<&str>) -> RunNode {
+        RunNode {
+            name: None,
+            command: "true".to_string(),
+            timeout: timeout.map(|raw| crate::parser::lexer::parse_duration(raw, 0).unwrap().0),
+            repeat: None,
+            line: 5,
+        }
+    }
+
+    #[test]
+    fn test_effective_timeout_defaults_to_file_limit() {
+        let run = make_run_node(None);
+        assert_eq!(
+            effective_timeout(&run, None, 30000),
+            (30000, TimeoutLimit::File)
+        );
+    }
+
+    #[test]
+    fn test_effective_timeout_test_overrides_file() {
+        let run = make_run_node(None);
+        assert_eq!(
+            effective_timeout(&run, Some((5000, 3)), 30000),
+            (5000, TimeoutLimit::Test { line: 3 })
+        );
+    }
+
+    #[test]
+    fn test_effective_timeout_run_overrides_test() {
+        let run = make_run_node(Some("180s"));
+        assert_eq!(
+            effective_timeout(&run, Some((5000, 3)), 30000),
+            (180000, TimeoutLimit::Run)
+        );
+    }
+
+    #[test]
+    fn test_describe_run_error_names_limit() {
+        assert_eq!(
+            describe_run_error(&RunError::Timeout(50), TimeoutLimit::Test { line: 4 }),
+            "command timed out after 50ms (TEST limit set on line 4)"
+        );
+        assert_eq!(
+            describe_run_error(&RunError::ShellExited(1), TimeoutLimit::Run),
+            "shell exited with code 1"
+        );
+    }
+
+    fn make_test_result(name: &str, duration_ms: u64) -> TestResult {
+        TestResult {
+            name: name.to_string(),
+            line: 1,
+            status: Status::Passed,
+            duration_ms,
+            runs: vec![],
+            assertions: vec![],
+            message: None,
+            attempts: vec![],
+            tags: vec![],
+            not_run: None,
+        }
+    }
+
+    #[test]
+    fn test_file_budget_uses_pragma_value() {
+        let pragmas = vec![PragmaNode {
+            pragma_type: PragmaType::Budget,
+            key: None,
+            value: "1.5s".to_string(),
+            line: 1,
+            raw: "#! budget: 1.5s".to_string(),
+        }];
+
+        let (pragma, budget_ms) = file_budget(&pragmas).unwrap();
+        assert_eq!(pragma.line, 1);
+        assert_eq!(budget_ms, 1500);
+        assert!(file_budget(&[]).is_none());
+    }
+
+    #[test]
+    fn test_budget_breakdown_lists_slowest_first() {
+        let tests = vec![
+            make_test_result("fast", 20),
+            make_test_result("slow", 12400),
+            make_test_result("medium", 900),
+        ];
+
+        let breakdown = budget_breakdown(&tests);
+        let lines: Vec<&str> = breakdown.lines().collect();
+        assert_eq!(lines[0], "Slowest tests:");
+        assert!(lines[1].contains("12.40s") && lines[1].contains("\"slow\""));
+        assert!(lines[2].contains("900ms") && lines[2].contains("\"medium\""));
+        assert!(lines[3].contains("20ms") && lines[3].contains("\"fast\""));
+    }
+
+    #[test]
+    fn test_file_shells_prefers_cli_over_pragma() {
+        let ast = vec![ASTNode::Pragma(PragmaNode {
+            pragma_type: PragmaType::Shells,
+            key: None,
+            value: "bash, dash".to_string(),
+            line: 1,
+            raw: "#! shells: bash, dash".to_string(),
+        })];
+
+        let defaults = RunnerOptions::default();
+        assert_eq!(file_shells(&ast, &defaults), vec!["bash", "dash"]);
+
+        let single = RunnerOptions {
+            shell: Some("zsh".to_string()),
+            ..Default::default()
+        };
+        assert!(file_shells(&ast, &single).is_empty());
+
+        let matrix = RunnerOptions {
+            shell_matrix: Some(vec!["sh".to_string(), "zsh".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(file_shells(&ast, &matrix), vec!["sh", "zsh"]);
+        assert_eq!(file_shells(&[], &matrix), vec!["sh", "zsh"]);
+        assert!(file_shells(&[], &defaults).is_empty());
+    }
+
+    #[test]
+    fn test_parameterized_test_expands_per_row() {
+        let content = r#"TEST "convert {fmt}" WITH fmt IN json, yaml
+RUN convert --to "$fmt"
+ASSERT exit_code == 0
+
+TEST "plain"
+RUN true
+"#;
+        let ParseResult::Success { file } = crate::parse_file(content, "test.hone") else {
+            panic!("should parse");
+        };
+
+        let blocks = group_nodes_by_test(&file.nodes);
+        let names: Vec<_> = blocks.iter().map(|b| b.test_name.as_deref()).collect();
+        assert_eq!(
+            names,
+            vec![Some("convert json"), Some("convert yaml"), Some("plain")]
+        );
+        assert!(matches!(
+            &blocks[1].nodes[0],
+            ASTNode::Env(EnvNode { key, value, .. }) if key == "fmt" && value == "yaml"
+        ));
+        assert_eq!(blocks[1].nodes.len(), 3);
+        assert_eq!(
+            count_assertions(&file.nodes, false, &TagSelection::default()),
+            2
+        );
+    }
+
+    #[test]
+    fn test_imported_nodes_keep_their_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("pragmas.hone"), "#! retries: 1\n").unwrap();
+        std::fs::write(
+            dir.path().join("steps.hone"),
+            "RUN true\nASSERT exit_code == 0\n",
+        )
+        .unwrap();
+        let main = dir.path().join("main.hone");
+        let main = main.to_string_lossy();
+        let content = r#"IMPORT "pragmas.hone"
+
+TEST "setup"
+IMPORT "steps.hone"
+ASSERT exit_code == 0
+"#;
+        let ParseResult::Success { file } = crate::parse_file(content, &main) else {
+            panic!("should parse");
+        };
+
+        // An import of pragmas only doesn't become an implicit block
+        let blocks = group_nodes_by_test(&file.nodes);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(file_retries(&file_pragmas(&file.nodes)), 1);
+        assert_eq!(
+            count_assertions(&file.nodes, false, &TagSelection::default()),
+            2
+        );
+
+        let nodes = with_imports(&blocks[0].nodes, &main);
+        let origins: Vec<(usize, &str)> = nodes
+            .iter()
+            .map(|(node, filename)| {
+                let name = Path::new(filename).file_name().unwrap().to_str().unwrap();
+                (node.line(), name)
+            })
+            .collect();
+        assert_eq!(
+            origins,
+            vec![(1, "steps.hone"), (2, "steps.hone"), (5, "main.hone")]
+        );
+    }
+
+    #[test]
+    fn test_retry_statement_overrides_retries_pragma() {
+        let content = r#"#! retries: 2
+
+TEST "uses the pragma"
+RUN true
+
+TEST "uses its own"
+RETRY 0
+RUN true
+"#;
+        let ParseResult::Success { file } = crate::parse_file(content, "test.hone") else {
+            panic!("should parse");
+        };
+
+        assert_eq!(file_retries(&file.pragmas), 2);
+        assert_eq!(file_retries(&[]), 0);
+
+        let blocks = group_nodes_by_test(&file.nodes);
+        assert_eq!(block_retries(&blocks[0]), None);
+        assert_eq!(block_retries(&blocks[1]), Some(0));
+    }
+
+    #[test]
+    fn test_reported_env_masks_passed_host_variables() {
+        let mut config = create_shell_config(&[], "test.hone", "/tmp", None, &Default::default());
+        config
+            .env
+            .insert("AWS_SECRET_ACCESS_KEY".to_string(), "hunter2".to_string());
+        config
+            .passed_env
+            .insert("AWS_SECRET_ACCESS_KEY".to_string());
+        config.env.insert("MODE".to_string(), "ci".to_string());
+
+        let env = reported_env(&config);
+        assert_eq!(env["AWS_SECRET_ACCESS_KEY"], "***");
+        assert_eq!(env["MODE"], "ci");
+    }
+
+    #[test]
+    fn test_rss_asserted_runs() {
+        let content = r#"TEST "memory"
+RUN build: make
+RUN echo one
+ASSERT max_rss < 1GB
+RUN echo two
+ASSERT build.max_rss < 2GB
+ASSERT cpu_time < 1s
+"#;
+        let ParseResult::Success { file } = crate::parse_file(content, "test.hone") else {
+            panic!("should parse");
+        };
+
+        let blocks = group_nodes_by_test(&file.nodes);
+        let nodes = with_imports(&blocks[0].nodes, "test.hone");
+        let mut measured: Vec<_> = rss_asserted_runs(&nodes).into_iter().collect();
+        measured.sort();
+        let commands: Vec<_> = measured
+            .iter()
+            .map(|&index| match nodes[index].0 {
+                ASTNode::Run(run_node) => run_node.command.as_str(),
+                _ => panic!("should be a RUN"),
+            })
+            .collect();
+        assert_eq!(commands, vec!["make", "echo one"]);
+    }
+
+    #[test]
+    fn test_not_run_status_for_modifiers() {
+        let content = r#"TEST "plain"
+RUN true
+ASSERT exit_code == 0
+
+SKIP TEST "skipped"
+RUN false
+ASSERT exit_code == 0
+
+TODO TEST "later"
+
+ONLY TEST "focused"
+RUN true
+ASSERT exit_code == 0
+"#;
+        let ParseResult::Success { file } = crate::parse_file(content, "test.hone") else {
+            panic!("should parse");
+        };
+
+        let blocks = group_nodes_by_test(&file.nodes);
+        let statuses: Vec<_> = blocks
+            .iter()
+            .map(|block| not_run_status(block, true).map(|(status, _)| status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Some(Status::Skipped),
+                Some(Status::Skipped),
+                Some(Status::Pending),
+                None
+            ]
+        );
+        assert_eq!(not_run_status(&blocks[0], false), None);
+
+        assert_eq!(only_test_lines(&file.nodes).collect::<Vec<_>>(), vec![11]);
+        assert_eq!(
+            count_assertions(&file.nodes, false, &TagSelection::default()),
+            2
+        );
+        assert_eq!(
+            count_assertions(&file.nodes, true, &TagSelection::default()),
+            1
+        );
+    }
+
+    #[test]
+    fn test_count_assertions_respects_tag_selection() {
+        let content = r#"TEST "quick" @smoke
+RUN true
+ASSERT exit_code == 0
+
+TEST "slow one" @slow
+RUN true
+ASSERT exit_code == 0
+ASSERT stdout == ""
+"#;
+        let ParseResult::Success { file } = crate::parse_file(content, "test.hone") else {
+            panic!("should parse");
+        };
+        let tags = |include: &[&str], exclude: &[&str]| {
+            let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            TagSelection::new(&strings(include), &strings(exclude)).unwrap()
+        };
+
+        assert_eq!(count_assertions(&file.nodes, false, &tags(&[], &[])), 3);
+        assert_eq!(
+            count_assertions(&file.nodes, false, &tags(&["smoke"], &[])),
+            1
+        );
+        assert_eq!(
+            count_assertions(&file.nodes, false, &tags(&[], &["smoke"])),
+            2
+        );
+    }
 }
@@ -0,0 +1,509 @@
+use crate::parser::ast::{HttpNode, HttpRequestSpec};
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{JoinHandle, JoinSet};
+
+/// Largest request line plus headers the server accepts
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// Largest request body the server accepts, counting chunk framing for a
+/// chunked one; bigger ones get a 413
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// One request received by a TEST's HTTP server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: String,
+    /// The path and query string, as sent
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Whether `spec` names this request. A path without `?` matches any
+    /// query string.
+    pub fn matches(&self, spec: &HttpRequestSpec) -> bool {
+        let target = if spec.path.contains('?') {
+            self.target.as_str()
+        } else {
+            self.target.split('?').next().unwrap_or_default()
+        };
+        self.method == spec.method && target == spec.path
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    routes: Vec<HttpNode>,
+    requests: Vec<HttpRequest>,
+}
+
+/// An HTTP/1.1 server on a random localhost port, answering with the routes
+/// of a TEST's HTTP statements and recording every request. Each connection
+/// serves one request. Stops when dropped.
+pub struct HttpServer {
+    url: String,
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+impl HttpServer {
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(State::default()));
+        let task = tokio::spawn(serve(listener, Arc::clone(&state)));
+        Ok(Self { url, state, task })
+    }
+
+    /// Base URL, such as `http://127.0.0.1:41234`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Adds `route`, replacing an earlier one for the same method and path.
+    pub fn add_route(&self, route: &HttpNode) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .routes
+            .retain(|existing| existing.request != route.request);
+        state.routes.push(route.clone());
+    }
+
+    /// Requests received so far, in the order they finished arriving.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.requests.clone()
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        // Dropping the task's JoinSet aborts connections still being served
+        self.task.abort();
+    }
+}
+
+async fn serve(listener: TcpListener, state: Arc<Mutex<State>>) {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    connections.spawn(handle_connection(stream, Arc::clone(&state)));
+                }
+            }
+            Some(_) = connections.join_next() => {}
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let (reader, mut writer) = stream.into_split();
+    let response = match read_request(&mut BufReader::new(reader), &mut writer).await {
+        Ok(request) => {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            // Recorded before the response goes out, so a command that got
+            // its response has been recorded by the time it exits
+            let response = respond(&state.routes, &request);
+            state.requests.push(request);
+            response
+        }
+        Err(RequestError::Rejected(status, message)) => {
+            response_bytes(status, &[], format!("{}\n", message).as_bytes(), true)
+        }
+        Err(RequestError::Closed) => return,
+    };
+    let _ = writer.write_all(&response).await;
+    let _ = writer.shutdown().await;
+}
+
+/// Why a request couldn't be read.
+enum RequestError {
+    /// Answered with this status and message
+    Rejected(u16, String),
+    /// The connection failed or closed early; there is no one to answer
+    Closed,
+}
+
+impl From<Error> for RequestError {
+    fn from(_: Error) -> Self {
+        RequestError::Closed
+    }
+}
+
+async fn read_request(
+    reader: &mut (impl AsyncBufReadExt + Unpin),
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<HttpRequest, RequestError> {
+    let mut head_bytes = 0;
+    let request_line = read_line(reader, &mut head_bytes).await?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(_version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid(format!("Malformed request line: {}", request_line)));
+    };
+
+    let mut request = HttpRequest {
+        method: method.to_string(),
+        target: target.to_string(),
+        headers: Vec::new(),
+        body: Vec::new(),
+    };
+    loop {
+        let line = read_line(reader, &mut head_bytes).await?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(invalid(format!("Malformed header: {}", line)));
+        };
+        request
+            .headers
+            .push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let chunked = request
+        .header("Transfer-Encoding")
+        .is_some_and(|encoding| encoding.to_ascii_lowercase().contains("chunked"));
+    let length = match request.header("Content-Length") {
+        Some(length) if !chunked => {
+            let length: usize = length
+                .parse()
+                .map_err(|_| invalid(format!("Invalid Content-Length: {}", length)))?;
+            if length > MAX_BODY_BYTES {
+                return Err(too_large());
+            }
+            length
+        }
+        _ => 0,
+    };
+
+    // Only once the body is known to be acceptable, so a rejected client
+    // doesn't start sending it
+    if request
+        .header("Expect")
+        .is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue"))
+    {
+        writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+    }
+
+    if chunked {
+        request.body = read_chunked_body(reader).await?;
+    } else {
+        request.body = vec![0; length];
+        reader.read_exact(&mut request.body).await?;
+    }
+
+    Ok(request)
+}
+
+/// Reads a line of the request head without its line ending.
+async fn read_line(
+    reader: &mut (impl AsyncBufReadExt + Unpin),
+    head_bytes: &mut usize,
+) -> Result<String, RequestError> {
+    read_line_within(reader, head_bytes, MAX_HEAD_BYTES)
+        .await?
+        .ok_or_else(|| invalid("Request headers are too large".to_string()))
+}
+
+/// Reads a line of a chunked body's framing, counted in `body_bytes`.
+async fn read_body_line(
+    reader: &mut (impl AsyncBufReadExt + Unpin),
+    body_bytes: &mut usize,
+) -> Result<String, RequestError> {
+    read_line_within(reader, body_bytes, MAX_BODY_BYTES)
+        .await?
+        .ok_or_else(too_large)
+}
+
+/// Reads a line without its line ending, adding the bytes read to `used`.
+/// Returns `None` if the line would take `used` past `limit`.
+async fn read_line_within(
+    reader: &mut (impl AsyncBufReadExt + Unpin),
+    used: &mut usize,
+    limit: usize,
+) -> Result<Option<String>, RequestError> {
+    let mut line = Vec::new();
+    let read = (&mut *reader)
+        .take(limit.saturating_sub(*used) as u64)
+        .read_until(b'\n', &mut line)
+        .await?;
+    *used += read;
+    if !line.ends_with(b"\n") {
+        return if *used >= limit {
+            Ok(None)
+        } else {
+            Err(Error::from(ErrorKind::UnexpectedEof).into())
+        };
+    }
+    Ok(Some(String::from_utf8_lossy(&line).trim_end().to_string()))
+}
+
+async fn read_chunked_body(
+    reader: &mut (impl AsyncBufReadExt + Unpin),
+) -> Result<Vec<u8>, RequestError> {
+    let mut body = Vec::new();
+    // Chunk size lines and trailers count against the body limit too
+    let mut body_bytes = 0;
+    loop {
+        let line = read_body_line(reader, &mut body_bytes).await?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| invalid(format!("Invalid chunk size: {}", size)))?;
+        if size == 0 {
+            break;
+        }
+        body_bytes = body_bytes
+            .checked_add(size)
+            .filter(|&total| total <= MAX_BODY_BYTES)
+            .ok_or_else(too_large)?;
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await?;
+        read_body_line(reader, &mut body_bytes).await?;
+    }
+    // Trailers end with an empty line
+    while !read_body_line(reader, &mut body_bytes).await?.is_empty() {}
+    Ok(body)
+}
+
+fn invalid(message: String) -> RequestError {
+    RequestError::Rejected(400, message)
+}
+
+fn too_large() -> RequestError {
+    RequestError::Rejected(
+        413,
+        format!("Request body is larger than {} bytes", MAX_BODY_BYTES),
+    )
+}
+
+fn respond(routes: &[HttpNode], request: &HttpRequest) -> Vec<u8> {
+    let include_body = request.method != "HEAD";
+    match routes.iter().find(|route| request.matches(&route.request)) {
+        Some(route) => response_bytes(route.status, &route.headers, &route.body, include_body),
+        None => response_bytes(
+            404,
+            &[],
+            format!(
+                "hone: no HTTP route for {} {}\n",
+                request.method, request.target
+            )
+            .as_bytes(),
+            include_body,
+        ),
+    }
+}
+
+fn response_bytes(
+    status: u16,
+    headers: &[(String, String)],
+    body: &[u8],
+    include_body: bool,
+) -> Vec<u8> {
+    let mut response = format!("HTTP/1.1 {} {}\r\n", status, reason_phrase(status));
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+
+    let mut response = response.into_bytes();
+    if include_body {
+        response.extend_from_slice(body);
+    }
+    response
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Content Too Large",
+        422 => "Unprocessable Content",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        // The reason phrase is optional and clients ignore it
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(method: &str, path: &str) -> HttpRequestSpec {
+        HttpRequestSpec {
+            method: method.to_string(),
+            path: path.to_string(),
+            raw: format!("{} {}", method, path),
+        }
+    }
+
+    fn route(method: &str, path: &str, status: u16, body: &str) -> HttpNode {
+        HttpNode {
+            request: spec(method, path),
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.as_bytes().to_vec(),
+            raw: String::new(),
+            line: 1,
+        }
+    }
+
+    async fn send(server: &HttpServer, request: &[u8]) -> String {
+        let address = server.url().trim_start_matches("http://");
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(request).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_request_matches() {
+        let request = HttpRequest {
+            method: "GET".to_string(),
+            target: "/v1/items?page=2".to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+        assert!(request.matches(&spec("GET", "/v1/items")));
+        assert!(request.matches(&spec("GET", "/v1/items?page=2")));
+        assert!(!request.matches(&spec("GET", "/v1/items?page=3")));
+        assert!(!request.matches(&spec("POST", "/v1/items")));
+        assert!(!request.matches(&spec("GET", "/v1")));
+    }
+
+    #[tokio::test]
+    async fn test_server_answers_routes_and_records_requests() {
+        let server = HttpServer::start().await.unwrap();
+        assert!(server.url().starts_with("http://127.0.0.1:"));
+        server.add_route(&route("GET", "/v1/items", 500, "old"));
+        server.add_route(&route("GET", "/v1/items", 200, "[]"));
+
+        let response = send(&server, b"GET /v1/items HTTP/1.1\r\nHost: x\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        assert!(response.ends_with("\r\n\r\n[]"));
+
+        let response = send(
+            &server,
+            b"POST /v1/items HTTP/1.1\r\nContent-Length: 8\r\n\r\n{\"a\": 1}",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(response.ends_with("hone: no HTTP route for POST /v1/items\n"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].target, "/v1/items");
+        assert_eq!(requests[0].header("host"), Some("x"));
+        assert_eq!(requests[1].method, "POST");
+        assert_eq!(requests[1].body, b"{\"a\": 1}");
+    }
+
+    #[tokio::test]
+    async fn test_server_reads_chunked_bodies() {
+        let server = HttpServer::start().await.unwrap();
+        server.add_route(&route("PUT", "/v1/items/1", 204, ""));
+
+        let response = send(
+            &server,
+            b"PUT /v1/items/1 HTTP/1.1\r\nTransfer-Encoding: chunked\r\nExpect: 100-continue\r\n\r\n\
+              4\r\nname\r\n6;ext=1\r\n: demo\r\n0\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No Content\r\n"));
+        assert_eq!(server.requests()[0].body, b"name: demo");
+    }
+
+    #[tokio::test]
+    async fn test_server_rejects_malformed_requests() {
+        let server = HttpServer::start().await.unwrap();
+        let response = send(&server, b"nonsense\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_server_rejects_oversized_bodies() {
+        let server = HttpServer::start().await.unwrap();
+        let response = send(
+            &server,
+            b"POST /upload HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n",
+        )
+        .await;
+        assert!(
+            response.starts_with("HTTP/1.1 413 Content Too Large\r\n"),
+            "{}",
+            response
+        );
+
+        // A chunk size that would overflow the body length
+        let response = send(
+            &server,
+            b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+              1\r\na\r\nffffffffffffffff\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 413 Content Too Large\r\n"));
+
+        // Rejected before the client is told to send the body
+        let response = send(
+            &server,
+            b"POST /upload HTTP/1.1\r\nContent-Length: 99999999999\r\n\
+              Expect: 100-continue\r\n\r\n",
+        )
+        .await;
+        assert!(
+            response.starts_with("HTTP/1.1 413 Content Too Large\r\n"),
+            "{}",
+            response
+        );
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_server_accepts_many_small_chunks() {
+        let server = HttpServer::start().await.unwrap();
+        // More chunk framing than the head may have
+        let mut request = b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for _ in 0..20_000 {
+            request.extend_from_slice(b"1\r\na\r\n");
+        }
+        request.extend_from_slice(b"0\r\n\r\n");
+
+        let response = send(&server, &request).await;
+        assert!(response.starts_with("HTTP/1.1 404 "), "{}", response);
+        assert_eq!(server.requests()[0].body.len(), 20_000);
+    }
+}
@@ -1,9 +1,21 @@
+pub mod adapter;
+pub mod baseline;
+pub mod bench;
+pub mod conditions;
+pub mod config;
 pub mod executor;
 mod files;
+pub mod http;
+pub mod pool;
+pub mod repeat;
 pub mod reporter;
+pub mod resources;
 pub mod sentinel;
 pub mod shell;
+pub mod stub;
+pub mod tags;
 
+pub use config::ProjectConfig;
 pub use executor::{run_tests, RunnerOptions};
 pub use files::resolve_patterns;
 pub use reporter::{OutputFormat, TestRunOutput};
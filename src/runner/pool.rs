@@ -0,0 +1,141 @@
+use crate::runner::shell::{ShellConfig, ShellSession};
+use std::collections::VecDeque;
+use tokio::task::JoinHandle;
+
+/// How many sessions are started ahead of the TEST that will use them. Tiny
+/// TEST blocks finish faster than a shell starts, so one isn't enough.
+const WARM_SESSIONS: usize = 4;
+
+type Warming = JoinHandle<Result<ShellSession, String>>;
+
+/// Hands out started shell sessions for a file's TEST blocks, starting the
+/// following ones in the background while the current TEST runs.
+///
+/// Every session is a new shell process built from the same `ShellConfig`,
+/// so no state carries over between TEST blocks; only the startup cost is
+/// taken off the critical path.
+pub struct SessionPool {
+    config: ShellConfig,
+    warming: VecDeque<Warming>,
+    /// Sessions still to be started before every TEST has one
+    remaining: usize,
+}
+
+impl SessionPool {
+    /// Creates a pool that will hand out `sessions` sessions in total.
+    pub fn new(config: ShellConfig, sessions: usize) -> Self {
+        Self {
+            config,
+            warming: VecDeque::new(),
+            remaining: sessions,
+        }
+    }
+
+    /// Returns a ready session, waiting for the oldest pre-warmed one, and
+    /// tops the pool back up.
+    pub async fn take(&mut self) -> Result<ShellSession, String> {
+        self.fill();
+        let result = match self.warming.pop_front() {
+            Some(warming) => warming
+                .await
+                .map_err(|e| format!("Shell startup task failed: {}", e))?,
+            None => start_session(self.config.clone()).await,
+        };
+        self.fill();
+        result
+    }
+
+    fn fill(&mut self) {
+        while self.warming.len() < WARM_SESSIONS && self.remaining > 0 {
+            self.remaining -= 1;
+            let config = self.config.clone();
+            self.warming.push_back(tokio::spawn(start_session(config)));
+        }
+    }
+
+    /// Stops sessions that were started but not used, without waiting for
+    /// those still starting.
+    pub async fn shutdown(&mut self) {
+        self.remaining = 0;
+        while let Some(warming) = self.warming.pop_front() {
+            if !warming.is_finished() {
+                warming.abort();
+                continue;
+            }
+            if let Ok(Ok(mut session)) = warming.await {
+                let _ = session.stop().await;
+            }
+        }
+    }
+}
+
+impl Drop for SessionPool {
+    fn drop(&mut self) {
+        // Dropping a task's output kills a session that already started
+        for warming in self.warming.drain(..) {
+            warming.abort();
+        }
+    }
+}
+
+async fn start_session(config: ShellConfig) -> Result<ShellSession, String> {
+    let mut session = ShellSession::new(config);
+    session.start().await?;
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::shell::create_shell_config;
+
+    fn test_pool(sessions: usize) -> (tempfile::TempDir, SessionPool) {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let cwd = dir.path().to_string_lossy().into_owned();
+        let config =
+            create_shell_config(&[], "pool.hone", &cwd, Some("/bin/sh"), &Default::default());
+        (dir, SessionPool::new(config, sessions))
+    }
+
+    #[tokio::test]
+    async fn test_prewarmed_sessions_are_separate_shells() {
+        let (_dir, mut pool) = test_pool(2);
+
+        let mut first = pool.take().await.expect("shell should start");
+        let first_pid = first
+            .run("export LEAKED=yes; echo $$", None, 5000, false)
+            .await
+            .expect("command should run")
+            .stdout;
+        first.stop().await.unwrap();
+
+        let mut second = pool.take().await.expect("prewarmed shell should start");
+        let result = second
+            .run("echo \"$$ ${LEAKED:-unset}\"", None, 5000, false)
+            .await
+            .expect("command should run");
+        let (second_pid, leaked) = result.stdout.split_once(' ').unwrap();
+        assert_ne!(second_pid, first_pid);
+        assert_eq!(leaked, "unset");
+
+        second.stop().await.unwrap();
+        pool.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_pool_starts_no_more_sessions_than_needed() {
+        let (_dir, mut pool) = test_pool(2);
+
+        let mut first = pool.take().await.expect("shell should start");
+        assert_eq!(pool.warming.len(), 1);
+        first.stop().await.unwrap();
+
+        let mut second = pool.take().await.expect("shell should start");
+        assert!(pool.warming.is_empty());
+        second.stop().await.unwrap();
+
+        // Past the expected count, sessions are started on demand
+        let mut extra = pool.take().await.expect("shell should start");
+        extra.stop().await.unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+use crate::runner::bench::BenchStats;
+use crate::runner::config::ProjectConfig;
+use crate::runner::reporter::{shell_name, FileResult, Status};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Outcome of every iteration of a `--repeat` or `--until-failure` run,
+/// aggregated per TEST.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RepeatReport {
+    pub iterations: usize,
+    /// Set when `--until-failure` stopped the run at a failing iteration
+    pub stopped_at_failure: bool,
+    pub tests: Vec<RepeatedTest>,
+    /// Durations of each TEST in `tests` so far, summarized by `finish`
+    #[serde(skip)]
+    samples: Vec<Vec<u64>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepeatedTest {
+    pub file: String,
+    pub name: String,
+    pub line: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Durations of the TEST across iterations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub durations: Option<BenchStats>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<FailedIteration>,
+}
+
+/// An iteration a TEST failed in, and where its RUN artifacts were kept.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedIteration {
+    pub iteration: usize,
+    pub artifacts: String,
+}
+
+impl RepeatedTest {
+    pub fn failure_rate(&self) -> f64 {
+        let total = self.passed + self.failed;
+        if total == 0 {
+            0.0
+        } else {
+            self.failed as f64 / total as f64 * 100.0
+        }
+    }
+}
+
+/// Directory an iteration of a file keeps its RUN artifacts in, so that
+/// iterations don't overwrite each other. `shell` is set for a shell matrix.
+pub fn iteration_artifact_dir(
+    runs_dir: &Path,
+    filename: &str,
+    run_stamp: &str,
+    iteration: usize,
+    shell: Option<&str>,
+) -> PathBuf {
+    let shell = shell.map(|s| format!("-{}", s)).unwrap_or_default();
+    let basename = Path::new(filename)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("test");
+    runs_dir
+        .join(format!("{}-{}-repeat", run_stamp, basename))
+        .join(format!("iteration-{}{}", iteration, shell))
+}
+
+impl RepeatReport {
+    /// Adds a file's result from one iteration. TESTs keep the order they
+    /// first ran in; `run_stamp` is the one artifacts were kept under.
+    pub fn add(&mut self, file: &FileResult, run_stamp: &str, project: &ProjectConfig) {
+        let Some(iteration) = file.iteration else {
+            return;
+        };
+        self.iterations = self.iterations.max(iteration);
+        let label = file.label();
+
+        // Skipped and TODO tests never ran, so they have nothing to count
+        for test in file
+            .tests
+            .iter()
+            .filter(|t| matches!(t.status, Status::Passed | Status::Failed))
+        {
+            let index = match self
+                .tests
+                .iter()
+                .position(|t| t.file == label && t.name == test.name)
+            {
+                Some(index) => index,
+                None => {
+                    self.tests.push(RepeatedTest {
+                        file: label.clone(),
+                        name: test.name.clone(),
+                        line: test.line,
+                        passed: 0,
+                        failed: 0,
+                        durations: None,
+                        failures: Vec::new(),
+                    });
+                    self.samples.push(Vec::new());
+                    self.tests.len() - 1
+                }
+            };
+
+            let entry = &mut self.tests[index];
+            if test.status == Status::Failed {
+                entry.failed += 1;
+                entry.failures.push(FailedIteration {
+                    iteration,
+                    artifacts: iteration_artifact_dir(
+                        &project.runs_dir(&file.file),
+                        &file.file,
+                        run_stamp,
+                        iteration,
+                        file.matrix.then(|| shell_name(&file.shell)),
+                    )
+                    .to_string_lossy()
+                    .into_owned(),
+                });
+            } else {
+                entry.passed += 1;
+            }
+            self.samples[index].push(test.duration_ms);
+        }
+    }
+
+    /// Computes the duration statistics once every iteration has been added.
+    pub fn finish(mut self, stopped_at_failure: bool) -> Self {
+        for (test, samples) in self.tests.iter_mut().zip(&self.samples) {
+            test.durations = BenchStats::from_samples(samples);
+        }
+        self.samples = Vec::new();
+        self.stopped_at_failure = stopped_at_failure;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::reporter::TestResult;
+
+    fn make_file(iteration: usize, statuses: &[(&str, Status, u64)]) -> FileResult {
+        FileResult {
+            file: "tests/race.hone".to_string(),
+            shell: "/bin/sh".to_string(),
+            matrix: false,
+            iteration: Some(iteration),
+            env: Default::default(),
+            error: None,
+            tests: statuses
+                .iter()
+                .map(|(name, status, duration_ms)| TestResult {
+                    name: name.to_string(),
+                    line: 1,
+                    status: *status,
+                    duration_ms: *duration_ms,
+                    runs: vec![],
+                    assertions: vec![],
+                    message: None,
+                    attempts: vec![],
+                    tags: vec![],
+                    not_run: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_report_counts_per_test() {
+        let files = vec![
+            make_file(
+                1,
+                &[("lock", Status::Passed, 10), ("read", Status::Passed, 5)],
+            ),
+            make_file(2, &[("lock", Status::Failed, 30)]),
+            make_file(
+                3,
+                &[("lock", Status::Passed, 20), ("read", Status::Passed, 7)],
+            ),
+        ];
+
+        let mut report = RepeatReport::default();
+        for file in &files {
+            report.add(file, "2026-01-01_00-00-00", &ProjectConfig::default());
+        }
+        let report = report.finish(false);
+
+        assert_eq!(report.iterations, 3);
+        assert_eq!(report.tests.len(), 2);
+
+        let lock = &report.tests[0];
+        assert_eq!((lock.passed, lock.failed), (2, 1));
+        assert_eq!(lock.durations.as_ref().unwrap().median_ms, 20);
+        assert_eq!(lock.failures.len(), 1);
+        assert_eq!(lock.failures[0].iteration, 2);
+        assert!(lock.failures[0]
+            .artifacts
+            .ends_with(".hone/runs/2026-01-01_00-00-00-race-repeat/iteration-2"));
+        assert!((lock.failure_rate() - 33.3).abs() < 0.1);
+
+        let read = &report.tests[1];
+        assert_eq!((read.passed, read.failed), (2, 0));
+        assert!(read.failures.is_empty());
+    }
+
+    #[test]
+    fn test_iteration_artifact_dir_per_shell() {
+        let dir = iteration_artifact_dir(
+            Path::new("tests/.hone/runs"),
+            "tests/race.hone",
+            "stamp",
+            4,
+            Some("dash"),
+        );
+        assert_eq!(
+            dir,
+            Path::new("tests/.hone/runs/stamp-race-repeat/iteration-4-dash")
+        );
+    }
+}
@@ -1,6 +1,13 @@
+use crate::assertions::timing::format_duration;
 use crate::parser::ast::ParseErrorDetail;
+use crate::runner::baseline::{BaselineComparison, MIN_REGRESSION_MS};
+use crate::runner::bench::BenchStats;
+use crate::runner::repeat::RepeatReport;
+use crate::runner::resources::ResourceUsage;
+use crate::runner::shell::Truncation;
 use owo_colors::OwoColorize;
 use serde::Serialize;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
 pub enum OutputFormat {
@@ -24,6 +31,44 @@ pub struct FileResult {
     pub file: String,
     pub shell: String,
     pub tests: Vec<TestResult>,
+    /// Whether this is one of several runs of the file in a shell matrix
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub matrix: bool,
+    /// Which iteration of a `--repeat` or `--until-failure` run this is, from 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<usize>,
+    /// Environment the file's shell sessions started with, before any ENV
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Failure of the file as a whole rather than of a TEST, such as going
+    /// over its `#! budget:`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FileResult {
+    /// The file name, followed by the shell when the file ran in a matrix.
+    pub fn label(&self) -> String {
+        if self.matrix {
+            format!("{} [{}]", self.file, shell_name(&self.shell))
+        } else {
+            self.file.clone()
+        }
+    }
+
+    /// Neither the file nor any TEST failed; skipped and pending ones don't
+    /// count against the file.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.tests.iter().all(|t| t.status != Status::Failed)
+    }
+}
+
+/// Shell executable name without its directory, e.g. `zsh` for `/bin/zsh`.
+pub fn shell_name(shell: &str) -> &str {
+    std::path::Path::new(shell)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(shell)
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -33,6 +78,37 @@ pub struct TestResult {
     pub status: Status,
     pub duration_ms: u64,
     pub runs: Vec<CommandRun>,
+    /// Assertions on the TEST block as a whole, such as `test.duration`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<AssertionOutput>,
+    /// Explanation for results that are not tied to a single assertion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Failed attempts before this one, for a TEST that was retried
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<TestResult>,
+    /// `@tag`s of the TEST, without the `@`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Why a skipped or TODO TEST did not run; `message` describes it for reports
+    #[serde(skip)]
+    pub not_run: Option<NotRun>,
+}
+
+/// Why a TEST was not run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotRun {
+    /// Marked `SKIP` or `TODO`, or not marked `ONLY` while other TESTs are
+    Marked,
+    /// Skipped by `#! require:` or a `SKIP IF` condition, for the reason given
+    Condition(String),
+}
+
+impl TestResult {
+    /// Passed only after failing at least once.
+    pub fn is_flaky(&self) -> bool {
+        self.status == Status::Passed && !self.attempts.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -47,6 +123,16 @@ pub struct CommandRun {
     pub stdout: String,
     pub stderr: String,
     pub assertions: Vec<AssertionOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bench: Option<BenchStats>,
+    /// Set when the stream went over the `max_output` limit; `stdout` then
+    /// holds only the start of it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdout_truncated: Option<Truncation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr_truncated: Option<Truncation>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -72,8 +158,14 @@ pub struct Summary {
     pub skipped: usize,
     #[serde(default)]
     pub other: usize,
+    /// Tests that passed on a retry
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub flaky: usize,
     #[serde(default, skip_serializing_if = "is_zero")]
     pub parse_errors: usize,
+    /// Files that failed as a whole, such as by going over their budget
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub file_errors: usize,
     #[serde(skip)]
     pub duration_ms: u64,
     #[serde(rename = "start")]
@@ -90,6 +182,10 @@ fn is_zero(val: &usize) -> bool {
 pub struct TestRunOutput {
     pub files: Vec<FileResult>,
     pub summary: Summary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineComparison>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<RepeatReport>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -105,6 +201,28 @@ pub struct Results {
     pub tool: Tool,
     pub summary: Summary,
     pub tests: Vec<Test>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<ResultsExtra>,
+}
+
+/// hone-specific details about the whole run, in the CTRF `extra` field
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultsExtra {
+    pub files: Vec<FileExtra>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<RepeatReport>,
+}
+
+/// A run of one file, which CTRF has no place for
+#[derive(Debug, Clone, Serialize)]
+pub struct FileExtra {
+    pub file: String,
+    pub shell: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<usize>,
+    pub env: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -128,11 +246,47 @@ pub struct Test {
     pub message: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub trace: Option<String>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub retries: usize,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub flaky: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub retry_attempts: Vec<RetryAttempt>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<TestExtra>,
+}
+
+/// A failed attempt of a retried test
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryAttempt {
+    pub attempt: usize,
+    pub status: Status,
+    pub duration: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub runs: Vec<CommandRun>,
+}
+
+/// hone-specific details carried in the CTRF `extra` field
+#[derive(Debug, Clone, Serialize)]
+pub struct TestExtra {
+    pub runs: Vec<CommandRun>,
+    /// Shell the test ran in, for files run in a shell matrix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Iteration of a `--repeat` or `--until-failure` run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<usize>,
 }
 
 impl TestRunOutput {
     pub fn has_failures(&self) -> bool {
-        self.summary.failed > 0 || self.summary.parse_errors > 0
+        self.summary.failed > 0 || self.summary.parse_errors > 0 || self.summary.file_errors > 0
     }
 }
 
@@ -144,18 +298,21 @@ pub struct JsonFormatter;
 
 impl JsonFormatter {
     fn build_failure_message(test: &TestResult) -> Option<String> {
-        for run in &test.runs {
-            for assertion in &run.assertions {
-                if assertion.status == Status::Failed {
-                    let mut parts = vec![assertion.expression.clone()];
-                    if let Some(expected) = &assertion.expected {
-                        parts.push(format!("Expected: {}", expected));
-                    }
-                    if let Some(actual) = &assertion.actual {
-                        parts.push(format!("Actual: {}", actual));
-                    }
-                    return Some(parts.join("\n"));
+        if test.message.is_some() {
+            return test.message.clone();
+        }
+
+        let run_assertions = test.runs.iter().flat_map(|run| &run.assertions);
+        for assertion in run_assertions.chain(&test.assertions) {
+            if assertion.status == Status::Failed {
+                let mut parts = vec![assertion.expression.clone()];
+                if let Some(expected) = &assertion.expected {
+                    parts.push(format!("Expected: {}", expected));
                 }
+                if let Some(actual) = &assertion.actual {
+                    parts.push(format!("Actual: {}", actual));
+                }
+                return Some(parts.join("\n"));
             }
         }
         None
@@ -173,6 +330,12 @@ impl JsonFormatter {
                     if !run.stderr.is_empty() {
                         trace_parts.push(format!("Stderr:\n{}", run.stderr));
                     }
+                    if let Some(truncation) = &run.stdout_truncated {
+                        trace_parts.push(format!("Full stdout: {}", truncation.path));
+                    }
+                    if let Some(truncation) = &run.stderr_truncated {
+                        trace_parts.push(format!("Full stderr: {}", truncation.path));
+                    }
                 }
             }
             if !trace_parts.is_empty() {
@@ -189,13 +352,33 @@ impl OutputFormatter for JsonFormatter {
 
         for file in &output.files {
             for test in &file.tests {
-                let message = if test.status == Status::Failed {
+                let message = if test.status != Status::Passed {
                     Self::build_failure_message(test)
                 } else {
                     None
                 };
 
                 let trace = Self::build_trace(test, file);
+                let retry_attempts = test
+                    .attempts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, attempt)| RetryAttempt {
+                        attempt: i + 1,
+                        status: attempt.status,
+                        duration: attempt.duration_ms,
+                        message: Self::build_failure_message(attempt),
+                        trace: Self::build_trace(attempt, file),
+                        runs: attempt.runs.clone(),
+                    })
+                    .collect();
+                let shell = file.matrix.then(|| file.shell.clone());
+                let extra = (!test.runs.is_empty() || shell.is_some() || file.iteration.is_some())
+                    .then(|| TestExtra {
+                        runs: test.runs.clone(),
+                        shell,
+                        iteration: file.iteration,
+                    });
 
                 ctrf_tests.push(Test {
                     name: test.name.clone(),
@@ -205,6 +388,11 @@ impl OutputFormatter for JsonFormatter {
                     line: Some(test.line),
                     message,
                     trace,
+                    retries: test.attempts.len(),
+                    flaky: test.is_flaky(),
+                    retry_attempts,
+                    tags: test.tags.clone(),
+                    extra,
                 });
             }
         }
@@ -219,6 +407,22 @@ impl OutputFormatter for JsonFormatter {
                 },
                 summary: output.summary.clone(),
                 tests: ctrf_tests,
+                extra: (!output.files.is_empty() || output.repeat.is_some()).then(|| {
+                    ResultsExtra {
+                        files: output
+                            .files
+                            .iter()
+                            .map(|file| FileExtra {
+                                file: file.file.clone(),
+                                shell: file.shell.clone(),
+                                iteration: file.iteration,
+                                env: file.env.clone(),
+                                error: file.error.clone(),
+                            })
+                            .collect(),
+                        repeat: output.repeat.clone(),
+                    }
+                }),
             },
         };
 
@@ -256,6 +460,14 @@ impl TextFormatter {
         println!();
     }
 
+    pub fn print_iteration(iteration: usize, total: Option<usize>) {
+        let total = total.map(|n| format!("/{}", n)).unwrap_or_default();
+        println!(
+            "{}",
+            format!("── Iteration {}{} ──", iteration, total).dimmed()
+        );
+    }
+
     pub fn print_file_result(
         filename: &str,
         passed: bool,
@@ -279,24 +491,248 @@ impl TextFormatter {
             status, filename, assertion_count, assertions_text, duration_secs
         );
     }
+
+    /// Prints the TESTs in the file that were skipped or left TODO.
+    pub fn print_not_run(file: &FileResult) {
+        for test in &file.tests {
+            let label = match test.status {
+                Status::Skipped => "SKIP",
+                Status::Pending => "TODO",
+                _ => continue,
+            };
+            // Conditional skips say why; SKIP, TODO and ONLY are evident from the file
+            let reason = match &test.not_run {
+                Some(NotRun::Condition(reason)) => format!(" ({})", reason),
+                _ => String::new(),
+            };
+            println!(
+                "      {} {}{}",
+                label.yellow(),
+                format!("\"{}\"", test.name).dimmed(),
+                reason.dimmed()
+            );
+        }
+    }
+
+    /// Prints the environment the file's shells started with, for `--verbose`.
+    pub fn print_env(env: &BTreeMap<String, String>) {
+        for (name, value) in env {
+            println!("      {}", format!("env {}={}", name, value).dimmed());
+        }
+    }
+
+    /// Prints duration statistics for every RUN in the file that used `REPEAT`.
+    pub fn print_bench_stats(file: &FileResult) {
+        for test in &file.tests {
+            for run in &test.runs {
+                if let Some(stats) = &run.bench {
+                    let label = run.name.as_deref().unwrap_or(&run.command);
+                    println!("      {}", Self::format_bench_line(label, stats).dimmed());
+                }
+            }
+        }
+    }
+
+    fn format_bench_line(label: &str, stats: &BenchStats) -> String {
+        let runs_text = if stats.requested == 1 { "run" } else { "runs" };
+        let count = if stats.iterations < stats.requested {
+            format!("{} of {}", stats.iterations, stats.requested)
+        } else {
+            stats.iterations.to_string()
+        };
+        format!(
+            "{}  {} {}  min {}  median {}  p95 {}  max {}",
+            label,
+            count,
+            runs_text,
+            format_duration(stats.min_ms as f64),
+            format_duration(stats.median_ms as f64),
+            format_duration(stats.p95_ms as f64),
+            format_duration(stats.max_ms as f64)
+        )
+    }
+
+    /// `, 2 skipped, 1 todo` for tests that did not run, or nothing.
+    fn format_not_run_counts(summary: &Summary) -> String {
+        let mut counts = Vec::new();
+        if summary.skipped > 0 {
+            counts.push(format!("{} skipped", summary.skipped));
+        }
+        if summary.pending > 0 {
+            counts.push(format!("{} todo", summary.pending));
+        }
+        counts
+            .iter()
+            .map(|count| format!(", {}", count.yellow()))
+            .collect()
+    }
+
+    fn format_repeat_table(report: &RepeatReport) -> String {
+        let times_text = if report.iterations == 1 {
+            "time"
+        } else {
+            "times"
+        };
+        let stopped = if report.stopped_at_failure {
+            ", stopped at the first failure"
+        } else {
+            ""
+        };
+        let mut lines = vec![format!(
+            "Repeated {} {}{}",
+            report.iterations, times_text, stopped
+        )];
+
+        let labels: Vec<String> = report
+            .tests
+            .iter()
+            .map(|test| format!("{} › {}", test.file, test.name))
+            .collect();
+        let width = labels
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        lines.push(
+            format!(
+                "  {:<width$}  {:>5}  {:>5}  {:>6}  {:>8}  {:>8}  {:>8}",
+                "TEST", "pass", "fail", "rate", "min", "median", "max"
+            )
+            .dimmed()
+            .to_string(),
+        );
+
+        for (test, label) in report.tests.iter().zip(&labels) {
+            let (min, median, max) = test
+                .durations
+                .as_ref()
+                .map(|d| {
+                    (
+                        format_duration(d.min_ms as f64),
+                        format_duration(d.median_ms as f64),
+                        format_duration(d.max_ms as f64),
+                    )
+                })
+                .unwrap_or_default();
+            let line = format!(
+                "  {:<width$}  {:>5}  {:>5}  {:>5.1}%  {:>8}  {:>8}  {:>8}",
+                label,
+                test.passed,
+                test.failed,
+                test.failure_rate(),
+                min,
+                median,
+                max
+            );
+            if test.failed > 0 {
+                lines.push(line.red().to_string());
+            } else {
+                lines.push(line);
+            }
+        }
+
+        for test in report.tests.iter().filter(|t| t.failed > 0) {
+            for failure in &test.failures {
+                lines.push(
+                    format!(
+                        "  {} › {} failed in iteration {}: {}",
+                        test.file, test.name, failure.iteration, failure.artifacts
+                    )
+                    .dimmed()
+                    .to_string(),
+                );
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    fn format_baseline_table(comparison: &BaselineComparison) -> String {
+        let limit = comparison
+            .max_regression
+            .map(|percent| {
+                format!(
+                    " (max regression {}%, at least {}ms)",
+                    percent, MIN_REGRESSION_MS
+                )
+            })
+            .unwrap_or_default();
+        let mut lines = vec![format!("Baseline \"{}\"{}", comparison.name, limit)];
+
+        if comparison.rows.is_empty() {
+            lines.push("  No RUNs matched the baseline".dimmed().to_string());
+        } else {
+            let width = comparison
+                .rows
+                .iter()
+                .map(|row| row.run.chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(3);
+            lines.push(
+                format!(
+                    "  {:<width$}  {:>9}  {:>9}  {:>8}",
+                    "RUN", "baseline", "current", "change"
+                )
+                .dimmed()
+                .to_string(),
+            );
+            for row in &comparison.rows {
+                let change = format!("{:+.1}%", row.change_percent);
+                let line = format!(
+                    "  {:<width$}  {:>9}  {:>9}  {:>8}",
+                    row.run,
+                    format_duration(row.baseline_ms as f64),
+                    format_duration(row.current_ms as f64),
+                    change
+                );
+                if row.regressed {
+                    lines.push(format!("{}  {}", line.red(), "✗ regression".red()));
+                } else {
+                    lines.push(line);
+                }
+            }
+        }
+
+        if comparison.unmatched > 0 {
+            let runs_text = if comparison.unmatched == 1 {
+                "RUN"
+            } else {
+                "RUNs"
+            };
+            lines.push(
+                format!("  {} {} not in baseline", comparison.unmatched, runs_text)
+                    .dimmed()
+                    .to_string(),
+            );
+        }
+
+        lines.join("\n")
+    }
 }
 
 impl OutputFormatter for TextFormatter {
     fn format(&self, output: &TestRunOutput) -> String {
         let mut result = String::new();
 
+        if let Some(ref comparison) = output.baseline {
+            result.push_str(&Self::format_baseline_table(comparison));
+            result.push_str("\n\n");
+        }
+
+        if let Some(ref report) = output.repeat {
+            result.push_str(&Self::format_repeat_table(report));
+            result.push_str("\n\n");
+        }
+
         let total_files = output.files.len();
-        let passed_files = output
-            .files
-            .iter()
-            .filter(|f| f.tests.iter().all(|t| t.status == Status::Passed))
-            .count();
+        let passed_files = output.files.iter().filter(|f| f.passed()).count();
         let total_assertions: usize = output
             .files
             .iter()
             .flat_map(|f| &f.tests)
-            .flat_map(|t| &t.runs)
-            .map(|r| r.assertions.len())
+            .map(|t| t.assertions.len() + t.runs.iter().map(|r| r.assertions.len()).sum::<usize>())
             .sum();
         let duration_secs = output.summary.duration_ms as f64 / 1000.0;
 
@@ -312,7 +748,7 @@ impl OutputFormatter for TextFormatter {
                 output.summary.parse_errors,
                 files_text
             ));
-        } else if output.summary.failed == 0 {
+        } else if output.summary.failed == 0 && output.summary.file_errors == 0 {
             result.push_str(&format!(
                 "{} Passed {}/{} files ({} assertions) in {:.2}s",
                 "✓".green(),
@@ -321,6 +757,18 @@ impl OutputFormatter for TextFormatter {
                 total_assertions,
                 duration_secs
             ));
+            if output.summary.flaky > 0 {
+                let tests_text = if output.summary.flaky == 1 {
+                    "test"
+                } else {
+                    "tests"
+                };
+                result.push_str(&format!(
+                    ", {}",
+                    format!("{} flaky {}", output.summary.flaky, tests_text).yellow()
+                ));
+            }
+            result.push_str(&Self::format_not_run_counts(&output.summary));
         } else {
             let failed_files = total_files - passed_files;
             result.push_str(&format!(
@@ -331,6 +779,34 @@ impl OutputFormatter for TextFormatter {
                 total_assertions,
                 duration_secs
             ));
+            for file in output.files.iter().filter(|f| f.matrix && !f.passed()) {
+                let failed_tests = file
+                    .tests
+                    .iter()
+                    .filter(|t| t.status == Status::Failed)
+                    .count();
+                result.push_str(&format!(
+                    "\n  {} {} in {}: {} failed",
+                    "✗".red(),
+                    file.file,
+                    shell_name(&file.shell),
+                    failed_tests
+                ));
+            }
+            // Files are printed before the comparison, so they showed these RUNs as passing
+            if let Some(ref comparison) = output.baseline {
+                for row in comparison.rows.iter().filter(|row| row.regressed) {
+                    result.push_str(&format!(
+                        "\n  {} {}: {} against {} in baseline \"{}\" ({:+.1}%)",
+                        "✗".red(),
+                        row.run,
+                        format_duration(row.current_ms as f64),
+                        format_duration(row.baseline_ms as f64),
+                        comparison.name,
+                        row.change_percent
+                    ));
+                }
+            }
         }
 
         result
@@ -348,6 +824,12 @@ pub struct TestFailure {
     pub expected: Option<String>,
     pub actual: Option<String>,
     pub error: Option<String>,
+    /// Files holding output that was cut off at the `max_output` limit
+    pub full_output: Vec<String>,
+    /// Shell the failure happened in, for files run in a shell matrix
+    pub shell: Option<String>,
+    /// Times the TEST was run, when it was retried
+    pub attempts: usize,
 }
 
 pub trait Reporter {
@@ -411,7 +893,28 @@ pub fn print_failure(failure: &TestFailure) {
         .map(|name| format!(":: \"{}\"", name).dimmed().to_string())
         .unwrap_or_default();
 
-    println!("{} {} {}", "FAIL".red(), location, test_name);
+    let shell = failure
+        .shell
+        .as_deref()
+        .map(|shell| format!("[{}] ", shell_name(shell)).yellow().to_string())
+        .unwrap_or_default();
+
+    let attempts = if failure.attempts > 1 {
+        format!(" (failed {} attempts)", failure.attempts)
+            .dimmed()
+            .to_string()
+    } else {
+        String::new()
+    };
+
+    println!(
+        "{} {}{} {}{}",
+        "FAIL".red(),
+        shell,
+        location,
+        test_name,
+        attempts
+    );
 
     if let Some(ref run_command) = failure.run_command {
         println!("{} {}", "RUN:".dimmed(), run_command);
@@ -438,6 +941,49 @@ pub fn print_failure(failure: &TestFailure) {
     if let Some(ref error) = failure.error {
         println!("{} {}", "Error:".red(), error);
     }
+
+    for path in &failure.full_output {
+        println!("{} {}", "Full output:".dimmed(), path);
+    }
+}
+
+/// Prints a TEST that passed on a retry, with why each earlier attempt failed.
+pub fn print_flaky(failures: &[TestFailure], max_attempts: usize) {
+    let Some(first) = failures.first() else {
+        return;
+    };
+
+    println!();
+    let test_name = first
+        .test_name
+        .as_ref()
+        .map(|name| format!(":: \"{}\"", name).dimmed().to_string())
+        .unwrap_or_default();
+    println!(
+        "{} {} {} {}",
+        "FLAKY".yellow(),
+        format!("{}:{}", first.filename, first.line).dimmed(),
+        test_name,
+        format!(
+            "passed on attempt {} of {}",
+            failures.len() + 1,
+            max_attempts
+        )
+        .dimmed()
+    );
+
+    for (i, failure) in failures.iter().enumerate() {
+        let reason = failure
+            .assertion
+            .as_ref()
+            .or(failure.error.as_ref())
+            .cloned()
+            .unwrap_or_default();
+        println!("  {} {}", format!("Attempt {}:", i + 1).dimmed(), reason);
+        if let Some(ref actual) = failure.actual {
+            println!("    {} {}", "Actual:".yellow(), actual.trim());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -457,7 +1003,9 @@ mod tests {
             pending: 0,
             skipped: 0,
             other: 0,
+            flaky: 0,
             parse_errors,
+            file_errors: 0,
             duration_ms: 0,
             start_time: 0,
             stop_time: 0,
@@ -468,6 +1016,8 @@ mod tests {
         TestRunOutput {
             files: vec![],
             summary,
+            baseline: None,
+            repeat: None,
         }
     }
 
@@ -495,6 +1045,38 @@ mod tests {
         assert!(output.has_failures());
     }
 
+    #[test]
+    fn test_file_error_fails_the_run_without_adding_a_test() {
+        let mut summary = make_summary(1, 1, 0, 0);
+        summary.file_errors = 1;
+        let output = TestRunOutput {
+            files: vec![FileResult {
+                file: "slow.hone".to_string(),
+                shell: "/bin/sh".to_string(),
+                tests: vec![],
+                matrix: false,
+                iteration: None,
+                env: Default::default(),
+                error: Some("File took 2.00s, exceeding its budget of 1s".to_string()),
+            }],
+            summary,
+            baseline: None,
+            repeat: None,
+        };
+        assert!(!output.files[0].passed());
+        assert!(output.has_failures());
+
+        let json: serde_json::Value = serde_json::from_str(&JsonFormatter.format(&output)).unwrap();
+        assert_eq!(json["results"]["summary"]["tests"], 1);
+        assert_eq!(json["results"]["summary"]["file_errors"], 1);
+        assert!(json["results"]["extra"]["files"][0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("exceeding its budget"));
+
+        assert!(TextFormatter.format(&output).contains("Failed 1/1 files"));
+    }
+
     #[test]
     fn test_has_failures_empty_run() {
         let output = make_output(make_summary(0, 0, 0, 0));
@@ -540,4 +1122,230 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_format_bench_line() {
+        let stats = BenchStats {
+            iterations: 20,
+            requested: 20,
+            min_ms: 12,
+            median_ms: 14,
+            p95_ms: 19,
+            max_ms: 1250,
+        };
+        assert_eq!(
+            TextFormatter::format_bench_line("startup", &stats),
+            "startup  20 runs  min 12ms  median 14ms  p95 19ms  max 1.25s"
+        );
+
+        let stopped = BenchStats {
+            iterations: 3,
+            ..stats
+        };
+        assert_eq!(
+            TextFormatter::format_bench_line("startup", &stopped),
+            "startup  3 of 20 runs  min 12ms  median 14ms  p95 19ms  max 1.25s"
+        );
+    }
+
+    #[test]
+    fn test_format_baseline_table() {
+        use crate::runner::baseline::BaselineRow;
+
+        let comparison = BaselineComparison {
+            name: "main".to_string(),
+            max_regression: Some(20.0),
+            rows: vec![BaselineRow {
+                run: "cli.hone › startup › version".to_string(),
+                baseline_ms: 100,
+                current_ms: 150,
+                change_percent: 50.0,
+                regressed: true,
+            }],
+            unmatched: 2,
+        };
+
+        let table = TextFormatter::format_baseline_table(&comparison);
+        assert!(table.starts_with("Baseline \"main\" (max regression 20%, at least 10ms)"));
+        assert!(table.contains("cli.hone › startup › version"));
+        assert!(table.contains("+50.0%"));
+        assert!(table.contains("regression"));
+        assert!(table.contains("2 RUNs not in baseline"));
+    }
+
+    #[test]
+    fn test_summary_lists_baseline_regressions() {
+        use crate::runner::baseline::BaselineRow;
+
+        let mut output = make_output(make_summary(1, 0, 1, 0));
+        output.baseline = Some(BaselineComparison {
+            name: "main".to_string(),
+            max_regression: Some(20.0),
+            rows: vec![BaselineRow {
+                run: "cli.hone › startup › version".to_string(),
+                baseline_ms: 100,
+                current_ms: 150,
+                change_percent: 50.0,
+                regressed: true,
+            }],
+            unmatched: 0,
+        });
+
+        let text = strip_ansi_escapes::strip_str(TextFormatter.format(&output));
+        let summary = text.split_once("✗ Failed").unwrap().1;
+        assert!(summary.contains(
+            "✗ cli.hone › startup › version: 150ms against 100ms in baseline \"main\" (+50.0%)"
+        ));
+    }
+
+    #[test]
+    fn test_summary_names_shell_of_matrix_failures() {
+        let make_file = |shell: &str, status: Status| FileResult {
+            file: "cli.hone".to_string(),
+            shell: shell.to_string(),
+            matrix: true,
+            iteration: None,
+            env: Default::default(),
+            error: None,
+            tests: vec![TestResult {
+                name: "startup".to_string(),
+                line: 1,
+                status,
+                duration_ms: 0,
+                runs: vec![],
+                assertions: vec![],
+                message: None,
+                attempts: vec![],
+                tags: vec![],
+                not_run: None,
+            }],
+        };
+        let output = TestRunOutput {
+            files: vec![
+                make_file("/bin/bash", Status::Passed),
+                make_file("/usr/bin/dash", Status::Failed),
+            ],
+            summary: make_summary(2, 1, 1, 0),
+            baseline: None,
+            repeat: None,
+        };
+
+        assert_eq!(output.files[1].label(), "cli.hone [dash]");
+        let text = TextFormatter.format(&output);
+        assert!(text.contains("Failed 1/2 files"));
+        assert!(text.contains("cli.hone in dash: 1 failed"));
+        assert!(!text.contains("in bash"));
+
+        let json = JsonFormatter.format(&output);
+        assert!(json.contains("\"shell\": \"/usr/bin/dash\""));
+    }
+
+    #[test]
+    fn test_flaky_test_keeps_failed_attempts() {
+        let make_test = |status: Status, message: &str| TestResult {
+            name: "daemon answers".to_string(),
+            line: 3,
+            status,
+            duration_ms: 5,
+            runs: vec![],
+            assertions: vec![],
+            message: Some(message.to_string()),
+            attempts: vec![],
+            tags: vec![],
+            not_run: None,
+        };
+        let mut test = make_test(Status::Passed, "ok");
+        test.attempts = vec![make_test(Status::Failed, "connection refused")];
+        assert!(test.is_flaky());
+
+        let mut summary = make_summary(1, 1, 0, 0);
+        summary.flaky = 1;
+        let output = TestRunOutput {
+            files: vec![FileResult {
+                file: "daemon.hone".to_string(),
+                shell: "/bin/sh".to_string(),
+                tests: vec![test],
+                matrix: false,
+                iteration: None,
+                env: [("CI".to_string(), "true".to_string())].into(),
+                error: None,
+            }],
+            summary,
+            baseline: None,
+            repeat: None,
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&JsonFormatter.format(&output)).unwrap();
+        let file = &json["results"]["extra"]["files"][0];
+        assert_eq!(file["file"], "daemon.hone");
+        assert_eq!(file["env"]["CI"], "true");
+        assert!(json["results"]["extra"].get("repeat").is_none());
+
+        let ctrf_test = &json["results"]["tests"][0];
+        assert_eq!(ctrf_test["flaky"], true);
+        assert_eq!(ctrf_test["retries"], 1);
+        assert_eq!(ctrf_test["retryAttempts"][0]["attempt"], 1);
+        assert_eq!(
+            ctrf_test["retryAttempts"][0]["message"],
+            "connection refused"
+        );
+        assert_eq!(json["results"]["summary"]["flaky"], 1);
+
+        assert!(TextFormatter.format(&output).contains("1 flaky test"));
+    }
+
+    #[test]
+    fn test_skipped_and_pending_tests_do_not_fail_file() {
+        let make_test = |name: &str, status: Status, message: Option<&str>| TestResult {
+            name: name.to_string(),
+            line: 1,
+            status,
+            duration_ms: 0,
+            runs: vec![],
+            assertions: vec![],
+            message: message.map(str::to_string),
+            attempts: vec![],
+            tags: vec![],
+            not_run: None,
+        };
+        let mut summary = make_summary(3, 1, 0, 0);
+        summary.skipped = 1;
+        summary.pending = 1;
+        let output = TestRunOutput {
+            files: vec![FileResult {
+                file: "cli.hone".to_string(),
+                shell: "/bin/sh".to_string(),
+                tests: vec![
+                    TestResult {
+                        tags: vec!["smoke".to_string()],
+                        not_run: None,
+                        ..make_test("works", Status::Passed, None)
+                    },
+                    make_test("upload", Status::Skipped, Some("Skipped with SKIP")),
+                    make_test("later", Status::Pending, Some("Marked TODO")),
+                ],
+                matrix: false,
+                iteration: None,
+                env: Default::default(),
+                error: None,
+            }],
+            summary,
+            baseline: None,
+            repeat: None,
+        };
+
+        assert!(output.files[0].passed());
+        let text = TextFormatter.format(&output);
+        assert!(text.contains("Passed 1/1 files"));
+        assert!(text.contains("1 skipped"));
+        assert!(text.contains("1 todo"));
+
+        let json: serde_json::Value = serde_json::from_str(&JsonFormatter.format(&output)).unwrap();
+        assert_eq!(json["results"]["summary"]["skipped"], 1);
+        assert_eq!(json["results"]["summary"]["pending"], 1);
+        assert_eq!(json["results"]["tests"][1]["status"], "skipped");
+        assert_eq!(json["results"]["tests"][1]["message"], "Skipped with SKIP");
+        assert_eq!(json["results"]["tests"][0]["tags"][0], "smoke");
+        assert!(json["results"]["tests"][1].get("tags").is_none());
+    }
 }
@@ -0,0 +1,205 @@
+use crate::runner::sentinel::{CONTROL_FD, UNIT_SEPARATOR};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Argument that makes hone act as the rusage helper, see [`report_rusage`].
+pub const RUSAGE_HELPER_ARG: &str = "__rusage";
+
+/// Starts the control line the rusage helper writes for a measured RUN.
+const RUSAGE_PREFIX: &str = "__HONE_RUSAGE__";
+
+static RUSAGE_HELPER: OnceLock<PathBuf> = OnceLock::new();
+
+/// Resources consumed by a single RUN.
+///
+/// CPU times are the change in the shell's own and reaped children's CPU time
+/// across the RUN, so they are exact up to the kernel's clock tick (usually 10ms).
+/// `max_rss_bytes` is only measured for a RUN with a `max_rss` assertion. Such a
+/// RUN runs in a subshell that execs the rusage helper once the command is done,
+/// and the helper reports the largest peak RSS among the processes it reaped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ResourceUsage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rss_bytes: Option<u64>,
+    pub user_cpu_ms: u64,
+    pub system_cpu_ms: u64,
+}
+
+impl ResourceUsage {
+    pub fn cpu_time_ms(&self) -> u64 {
+        self.user_cpu_ms + self.system_cpu_ms
+    }
+}
+
+/// Measures the CPU time the commands a shell runs use during one RUN, using `/proc`.
+pub struct CpuMeter {
+    shell_pid: u32,
+    start: CpuTimes,
+}
+
+impl CpuMeter {
+    /// Starts measuring, or returns `None` if `/proc` is not available.
+    pub fn start(shell_pid: u32) -> Option<Self> {
+        let start = read_cpu_times(shell_pid)?;
+        Some(Self { shell_pid, start })
+    }
+
+    /// Usage since `start`, with the peak RSS the rusage helper reported, if any.
+    pub fn finish(self, max_rss_bytes: Option<u64>) -> Option<ResourceUsage> {
+        let end = read_cpu_times(self.shell_pid)?;
+        let ms_per_tick = 1000.0 / clock_ticks_per_second() as f64;
+        let to_ms = |ticks: u64| (ticks as f64 * ms_per_tick).round() as u64;
+
+        Some(ResourceUsage {
+            max_rss_bytes,
+            user_cpu_ms: to_ms(end.user.saturating_sub(self.start.user)),
+            system_cpu_ms: to_ms(end.system.saturating_sub(self.start.system)),
+        })
+    }
+}
+
+/// Registers the executable that measured RUNs exec into: hone itself. Without
+/// one, `max_rss` is never measured.
+pub fn set_rusage_helper(path: PathBuf) {
+    let _ = RUSAGE_HELPER.set(path);
+}
+
+pub fn rusage_helper() -> Option<&'static Path> {
+    RUSAGE_HELPER.get().map(PathBuf::as_path)
+}
+
+/// Runs the rusage helper, `hone __rusage <run-id> <exit-code>`. The subshell
+/// of a measured RUN execs it after the command, so its `RUSAGE_CHILDREN`
+/// covers every process the command waited for. It reports their peak RSS on
+/// the control channel and exits with the command's exit code.
+pub fn report_rusage(args: &[String]) -> ! {
+    use std::io::Write;
+    use std::os::fd::FromRawFd;
+
+    let (Some(run_id), Some(exit_code)) = (args.first(), args.get(1)) else {
+        std::process::exit(2);
+    };
+    if let Some(max_rss_bytes) = children_max_rss_bytes() {
+        // SAFETY: the subshell hands the control channel to the helper as CONTROL_FD
+        let mut control = unsafe { std::fs::File::from_raw_fd(CONTROL_FD) };
+        let _ = writeln!(control, "{}{}", rusage_line_prefix(run_id), max_rss_bytes);
+    }
+    std::process::exit(exit_code.parse().unwrap_or(1));
+}
+
+fn children_max_rss_bytes() -> Option<u64> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+    // SAFETY: getrusage only writes to the struct it is given
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: getrusage succeeded, so the struct is filled in
+    let max_rss = unsafe { usage.assume_init() }.ru_maxrss.max(0) as u64;
+    // Linux reports kilobytes, macOS bytes
+    Some(if cfg!(target_os = "macos") {
+        max_rss
+    } else {
+        max_rss * 1024
+    })
+}
+
+pub fn rusage_line_prefix(run_id: &str) -> String {
+    format!(
+        "{}{}{}{}",
+        RUSAGE_PREFIX, UNIT_SEPARATOR, run_id, UNIT_SEPARATOR
+    )
+}
+
+/// Peak RSS from a control line the rusage helper wrote for `run_id`.
+pub fn parse_rusage_line(line: &str, run_id: &str) -> Option<u64> {
+    line.strip_prefix(&rusage_line_prefix(run_id))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// CPU time of a process plus its reaped children, in clock ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CpuTimes {
+    user: u64,
+    system: u64,
+}
+
+fn clock_ticks_per_second() -> u64 {
+    static TICKS: OnceLock<u64> = OnceLock::new();
+    *TICKS.get_or_init(|| {
+        // SAFETY: sysconf only reads a system configuration value
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks > 0 {
+            ticks as u64
+        } else {
+            100
+        }
+    })
+}
+
+fn read_cpu_times(pid: u32) -> Option<CpuTimes> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    parse_cpu_times(&stat)
+}
+
+fn parse_cpu_times(stat: &str) -> Option<CpuTimes> {
+    // The command name may contain spaces and parentheses, so fields are
+    // counted from the last closing parenthesis. After it come field 3 (state)
+    // onwards; utime, stime, cutime and cstime are fields 14 to 17.
+    let fields: Vec<&str> = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect();
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+
+    Some(CpuTimes {
+        user: field(14)? + field(16)?,
+        system: field(15)? + field(17)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_times_adds_children() {
+        let stat = "1234 (my (odd) shell) S 1 1234 1234 0 -1 4194560 100 0 0 0 \
+                    7 3 20 5 20 0 1 0 100 1000 200";
+        assert_eq!(
+            parse_cpu_times(stat),
+            Some(CpuTimes {
+                user: 27,
+                system: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_cpu_times_rejects_truncated_stat() {
+        assert_eq!(parse_cpu_times("1234 (sh) S 1 1234"), None);
+    }
+
+    #[test]
+    fn test_parse_rusage_line() {
+        let line = format!("{}52428800", rusage_line_prefix("cli-big-0"));
+        assert_eq!(parse_rusage_line(&line, "cli-big-0"), Some(52_428_800));
+        assert_eq!(parse_rusage_line(&line, "cli-big-1"), None);
+        assert_eq!(
+            parse_rusage_line("__HONE__\x1fcli-big-0\x1f0", "cli-big-0"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cpu_time_sums_user_and_system() {
+        let usage = ResourceUsage {
+            max_rss_bytes: None,
+            user_cpu_ms: 120,
+            system_cpu_ms: 30,
+        };
+        assert_eq!(usage.cpu_time_ms(), 150);
+    }
+}
@@ -1,13 +1,13 @@
+use crate::runner::adapter::posix_quote;
+use crate::runner::resources::RUSAGE_HELPER_ARG;
 use std::path::Path;
 
-const UNIT_SEPARATOR: char = '\x1f';
-const SENTINEL_PREFIX: &str = "__HONE__";
+pub const UNIT_SEPARATOR: char = '\x1f';
+pub const SENTINEL_PREFIX: &str = "__HONE__";
 
-// Full sentinel marker includes the unit separator to avoid false positives
-// when user output happens to contain "__HONE__" as plain text
-fn sentinel_marker() -> String {
-    format!("{}{}", SENTINEL_PREFIX, UNIT_SEPARATOR)
-}
+/// File descriptor the shell writes sentinels and other control messages to.
+/// Keeping them off stdout means stdout carries nothing but command output.
+pub const CONTROL_FD: i32 = 3;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SentinelData {
@@ -71,21 +71,50 @@ fn escape_for_shell_string(s: &str) -> String {
     result
 }
 
-pub fn generate_shell_wrapper(command: &str, run_id: &str, stderr_path: &str) -> String {
+pub fn generate_shell_wrapper(
+    command: &str,
+    run_id: &str,
+    stderr_path: &str,
+    rusage_helper: Option<&str>,
+) -> String {
     let escaped_stderr_path = stderr_path.replace('\'', "'\"'\"'");
     let escaped_run_id = escape_for_shell_string(run_id);
+    let group = format!(
+        "{{ {} ; }} 2> '{}' {}>&- < /dev/null",
+        command, escaped_stderr_path, CONTROL_FD
+    );
 
     // Shell wrapper uses command grouping {...} to preserve shell state
     // (working directory, variables, etc.) across commands.
     // Note: Commands that would exit the shell (like bare `exit`) should
     // be wrapped in a subshell by the test: (exit 42) instead of exit 42
+    // The command runs with the control descriptor closed so it cannot
+    // write (or forge) a sentinel, and with stdin from /dev/null so it
+    // cannot read the commands hone sends after it.
+    // With a rusage helper the group runs in a subshell that execs the helper
+    // afterwards, so the helper's RUSAGE_CHILDREN covers what the command ran.
+    let group = match rusage_helper {
+        Some(helper) => format!(
+            "( {} ; exec {} {} {} \"$?\" )",
+            group,
+            posix_quote(helper),
+            RUSAGE_HELPER_ARG,
+            posix_quote(run_id)
+        ),
+        None => group,
+    };
     [
         format!(": > '{}'", escaped_stderr_path),
-        format!("{{ {} ; }} 2> '{}'", command, escaped_stderr_path),
+        group,
         "HONE_EC=$?".to_string(),
         format!(
-            "printf \"{}{}{}{}%d{}%s\\n\" \"$HONE_EC\" \"$(date +%s%3N)\"",
-            SENTINEL_PREFIX, UNIT_SEPARATOR, escaped_run_id, UNIT_SEPARATOR, UNIT_SEPARATOR
+            "printf \"{}{}{}{}%d{}%s\\n\" \"$HONE_EC\" \"$(date +%s%3N)\" >&{}",
+            SENTINEL_PREFIX,
+            UNIT_SEPARATOR,
+            escaped_run_id,
+            UNIT_SEPARATOR,
+            UNIT_SEPARATOR,
+            CONTROL_FD
         ),
     ]
     .join("\n")
@@ -121,72 +150,37 @@ pub fn parse_sentinel(line: &str) -> Option<SentinelData> {
     })
 }
 
-pub fn contains_sentinel(line: &str) -> bool {
-    line.contains(&sentinel_marker())
-}
-
-#[derive(Debug)]
-pub struct SentinelExtractResult {
-    pub found: bool,
-    pub output: String,
-    pub sentinel: Option<SentinelData>,
-    pub remaining: String,
+/// Start of the sentinel line for `run_id`, used to pick it out of the
+/// control channel.
+pub fn sentinel_line_prefix(run_id: &str) -> String {
+    format!(
+        "{}{}{}{}",
+        SENTINEL_PREFIX, UNIT_SEPARATOR, run_id, UNIT_SEPARATOR
+    )
 }
 
-pub fn extract_sentinel(buffer: &str, expected_run_id: &str) -> SentinelExtractResult {
-    // Search for the full sentinel marker (prefix + unit separator) to avoid false positives
-    // when user output contains "__HONE__" as plain text
-    let marker = sentinel_marker();
-    let Some(sentinel_index) = buffer.find(&marker) else {
-        return SentinelExtractResult {
-            found: false,
-            output: buffer.to_string(),
-            sentinel: None,
-            remaining: String::new(),
-        };
-    };
-
-    let output = &buffer[..sentinel_index];
-
-    let after_sentinel = &buffer[sentinel_index..];
-    let newline_index = after_sentinel.find('\n');
-
-    let (sentinel_line, remaining) = if let Some(newline_index) = newline_index {
-        (
-            &after_sentinel[..newline_index],
-            &after_sentinel[newline_index + 1..],
-        )
-    } else {
-        return SentinelExtractResult {
-            found: false,
-            output: buffer.to_string(),
-            sentinel: None,
-            remaining: String::new(),
-        };
-    };
-
-    let parsed = parse_sentinel(sentinel_line.trim());
-
-    let parsed = match parsed {
-        Some(p) if p.run_id == expected_run_id => p,
-        _ => {
-            return SentinelExtractResult {
-                found: false,
-                output: buffer.to_string(),
-                sentinel: None,
-                remaining: String::new(),
-            };
+/// Takes the first complete line starting with `prefix` out of a control
+/// channel buffer. Lines before it are stale (e.g. left behind by a command
+/// that timed out) and are discarded along with it.
+pub fn take_control_line(buffer: &mut Vec<u8>, prefix: &str) -> Option<String> {
+    let mut line_start = 0;
+    while let Some(len) = buffer[line_start..].iter().position(|&b| b == b'\n') {
+        let line = &buffer[line_start..line_start + len];
+        if line.starts_with(prefix.as_bytes()) {
+            let line = String::from_utf8_lossy(line).into_owned();
+            buffer.drain(..line_start + len + 1);
+            return Some(line);
         }
-    };
-
-    let clean_output = output.strip_suffix('\n').unwrap_or(output);
-
-    SentinelExtractResult {
-        found: true,
-        output: clean_output.to_string(),
-        sentinel: Some(parsed),
-        remaining: remaining.to_string(),
+        line_start += len + 1;
     }
+    None
+}
+
+/// Decodes captured output for text assertions: invalid UTF-8 is replaced
+/// and the newline that ends the output, if any, is dropped.
+pub fn output_text(output: &[u8]) -> String {
+    let output = output.strip_suffix(b"\n").unwrap_or(output);
+    String::from_utf8_lossy(output).into_owned()
 }
 
 #[cfg(test)]
@@ -322,181 +316,60 @@ mod tests {
     }
 
     #[test]
-    fn test_extract_sentinel_simple() {
-        let sentinel_line = format!(
-            "__HONE__{}test-1{}0{}1000",
-            UNIT_SEPARATOR, UNIT_SEPARATOR, UNIT_SEPARATOR
-        );
-        let buffer = format!("command output\n{}\nremaining", sentinel_line);
-
-        let result = extract_sentinel(&buffer, "test-1");
-
-        assert!(result.found);
-        assert_eq!(result.output, "command output");
-        assert!(result.sentinel.is_some());
-        assert_eq!(result.sentinel.unwrap().run_id, "test-1");
-        assert_eq!(result.remaining, "remaining");
-    }
-
-    #[test]
-    fn test_extract_sentinel_no_preceding_output() {
-        let sentinel_line = format!(
-            "__HONE__{}run-2{}0{}2000",
-            UNIT_SEPARATOR, UNIT_SEPARATOR, UNIT_SEPARATOR
-        );
-        let buffer = format!("{}\nafter", sentinel_line);
-
-        let result = extract_sentinel(&buffer, "run-2");
-
-        assert!(result.found);
-        assert_eq!(result.output, "");
-        assert_eq!(result.remaining, "after");
-    }
-
-    #[test]
-    fn test_extract_sentinel_output_no_trailing_newline() {
-        let sentinel_line = format!(
-            "__HONE__{}test{}0{}3000",
-            UNIT_SEPARATOR, UNIT_SEPARATOR, UNIT_SEPARATOR
-        );
-        let buffer = format!("output{}\n", sentinel_line);
-
-        let result = extract_sentinel(&buffer, "test");
-
-        assert!(result.found);
-        assert_eq!(result.output, "output");
-        assert_eq!(result.remaining, "");
-    }
-
-    #[test]
-    fn test_extract_sentinel_wrong_run_id() {
-        let sentinel_line = format!(
-            "__HONE__{}wrong-id{}0{}4000",
-            UNIT_SEPARATOR, UNIT_SEPARATOR, UNIT_SEPARATOR
-        );
-        let buffer = format!("output\n{}\n", sentinel_line);
-
-        let result = extract_sentinel(&buffer, "expected-id");
-
-        assert!(!result.found);
-        assert_eq!(result.output, buffer);
-        assert!(result.sentinel.is_none());
-    }
-
-    #[test]
-    fn test_extract_sentinel_not_found() {
-        let buffer = "output without sentinel\nmore output\n";
-
-        let result = extract_sentinel(buffer, "test");
-
-        assert!(!result.found);
-        assert_eq!(result.output, buffer);
-        assert!(result.sentinel.is_none());
-        assert_eq!(result.remaining, "");
-    }
-
-    #[test]
-    fn test_extract_sentinel_incomplete_no_newline() {
-        let sentinel_line = format!(
-            "__HONE__{}test{}0{}5000",
-            UNIT_SEPARATOR, UNIT_SEPARATOR, UNIT_SEPARATOR
-        );
-        let buffer = format!("output\n{}", sentinel_line);
-
-        let result = extract_sentinel(&buffer, "test");
-
-        assert!(!result.found);
-    }
-
-    #[test]
-    fn test_extract_sentinel_malformed() {
-        let buffer = "output\n__HONE__malformed\n";
-
-        let result = extract_sentinel(buffer, "test");
-
-        assert!(!result.found);
+    fn test_output_text() {
+        assert_eq!(output_text(b"hello\n"), "hello");
+        assert_eq!(output_text(b"a\nb"), "a\nb");
+        assert_eq!(output_text(b"\n\n"), "\n");
+        assert_eq!(output_text(&[b'o', b'k', 0xff]), "ok\u{fffd}");
     }
 
     #[test]
-    fn test_extract_sentinel_multiline_output() {
-        let sentinel_line = format!(
-            "__HONE__{}test{}0{}6000",
-            UNIT_SEPARATOR, UNIT_SEPARATOR, UNIT_SEPARATOR
-        );
-        let buffer = format!("line1\nline2\nline3\n{}\nafter", sentinel_line);
+    fn test_take_control_line() {
+        let sentinel = format!("{}0{}1000", sentinel_line_prefix("run-1"), UNIT_SEPARATOR);
+        let mut buffer = format!("stale\n{}\nnext\npartial", sentinel).into_bytes();
 
-        let result = extract_sentinel(&buffer, "test");
+        let line = take_control_line(&mut buffer, &sentinel_line_prefix("run-1"));
 
-        assert!(result.found);
-        assert_eq!(result.output, "line1\nline2\nline3");
-        assert_eq!(result.remaining, "after");
+        assert_eq!(line.as_deref(), Some(sentinel.as_str()));
+        assert_eq!(buffer, b"next\npartial");
+        assert_eq!(parse_sentinel(&sentinel).unwrap().run_id, "run-1");
     }
 
     #[test]
-    fn test_extract_sentinel_skips_false_positive_hone_in_output() {
-        // User output contains "__HONE__" but without the unit separator - this is a false positive
-        let real_sentinel = format!(
-            "__HONE__{}test-run{}0{}7000",
-            UNIT_SEPARATOR, UNIT_SEPARATOR, UNIT_SEPARATOR
-        );
-        let buffer = format!(
-            "__HONE__ is just some user output\n{}\nremaining",
-            real_sentinel
-        );
+    fn test_take_control_line_needs_complete_line() {
+        let mut buffer = sentinel_line_prefix("run-1").into_bytes();
 
-        let result = extract_sentinel(&buffer, "test-run");
-
-        assert!(
-            result.found,
-            "Should find the real sentinel, not get confused by false positive"
-        );
-        assert_eq!(result.output, "__HONE__ is just some user output");
-        assert!(result.sentinel.is_some());
-        assert_eq!(result.sentinel.unwrap().run_id, "test-run");
-        assert_eq!(result.remaining, "remaining");
+        assert!(take_control_line(&mut buffer, &sentinel_line_prefix("run-1")).is_none());
+        assert!(!buffer.is_empty());
     }
 
     #[test]
-    fn test_extract_sentinel_multiple_false_positives() {
-        // Multiple occurrences of "__HONE__" without unit separator before the real one
-        let real_sentinel = format!(
-            "__HONE__{}mytest{}42{}8000",
-            UNIT_SEPARATOR, UNIT_SEPARATOR, UNIT_SEPARATOR
-        );
-        let buffer = format!(
-            "line with __HONE__ marker\nanother __HONE__ here\n{}\n",
-            real_sentinel
+    fn test_take_control_line_ignores_other_runs() {
+        let other = format!(
+            "{}0{}1000\n",
+            sentinel_line_prefix("run-10"),
+            UNIT_SEPARATOR
         );
+        let mut buffer = other.into_bytes();
 
-        let result = extract_sentinel(&buffer, "mytest");
-
-        assert!(result.found);
-        assert_eq!(
-            result.output,
-            "line with __HONE__ marker\nanother __HONE__ here"
-        );
-        assert!(result.sentinel.is_some());
-        assert_eq!(result.sentinel.unwrap().exit_code, 42);
-    }
-
-    #[test]
-    fn test_contains_sentinel_present() {
-        // Real sentinel has the unit separator after the prefix
-        let line = format!("__HONE__{}some data", UNIT_SEPARATOR);
-        assert!(contains_sentinel(&line));
+        assert!(take_control_line(&mut buffer, &sentinel_line_prefix("run-1")).is_none());
     }
 
     #[test]
-    fn test_contains_sentinel_absent() {
-        let line = "normal output";
-        assert!(!contains_sentinel(line));
+    fn test_generate_shell_wrapper_uses_control_fd() {
+        let wrapper = generate_shell_wrapper("echo hi", "test-run", "/tmp/stderr", None);
+        assert!(wrapper.contains("{ echo hi ; } 2> '/tmp/stderr' 3>&-"));
+        assert!(wrapper.ends_with(">&3"));
     }
 
     #[test]
-    fn test_contains_sentinel_false_positive_without_separator() {
-        // "__HONE__" without unit separator should NOT be detected as sentinel
-        let line = "__HONE__ is just text";
-        assert!(!contains_sentinel(line));
+    fn test_measured_wrapper_execs_rusage_helper_in_subshell() {
+        let wrapper =
+            generate_shell_wrapper("echo hi", "test-run", "/tmp/stderr", Some("/opt/hone"));
+        assert!(wrapper.contains(
+            "( { echo hi ; } 2> '/tmp/stderr' 3>&- < /dev/null ; exec '/opt/hone' __rusage 'test-run' \"$?\" )"
+        ));
+        assert!(wrapper.ends_with(">&3"));
     }
 
     #[test]
@@ -624,7 +497,7 @@ mod tests {
 
     #[test]
     fn test_generate_shell_wrapper_escapes_run_id() {
-        let wrapper = generate_shell_wrapper("echo hi", "test-$x-run", "/tmp/stderr");
+        let wrapper = generate_shell_wrapper("echo hi", "test-$x-run", "/tmp/stderr", None);
         assert!(
             wrapper.contains(r"test-\$x-run"),
             "run_id should have $ escaped"
@@ -646,7 +519,7 @@ mod tests {
     #[test]
     fn test_generate_shell_wrapper_escapes_percent_in_run_id() {
         // If run_id contains %, it must be escaped to %% to avoid printf format injection
-        let wrapper = generate_shell_wrapper("echo hi", "test-100%-run", "/tmp/stderr");
+        let wrapper = generate_shell_wrapper("echo hi", "test-100%-run", "/tmp/stderr", None);
         assert!(
             wrapper.contains("test-100%%-run"),
             "run_id should have % escaped to %%"
@@ -660,7 +533,8 @@ mod tests {
     #[test]
     fn test_generate_shell_wrapper_escapes_single_quotes_in_stderr_path() {
         // Single quotes in stderr_path must be escaped to prevent shell injection
-        let wrapper = generate_shell_wrapper("echo hi", "test-run", "/tmp/user's dir/stderr.txt");
+        let wrapper =
+            generate_shell_wrapper("echo hi", "test-run", "/tmp/user's dir/stderr.txt", None);
         // Single quote escaping: ' becomes '"'"' (end single-quote, double-quote single-quote, start single-quote)
         assert!(
             wrapper.contains(r#"'/tmp/user'"'"'s dir/stderr.txt'"#),
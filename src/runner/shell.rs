@@ -1,25 +1,82 @@
-use crate::parser::{PragmaNode, PragmaType};
+use crate::assertions::resources::memory_to_bytes;
+use crate::assertions::timing::duration_to_ms;
+use crate::parser::dotenv::load_env_file;
+use crate::parser::lexer::parse_memory_size;
+use crate::parser::{Duration as DslDuration, HttpNode, PragmaNode, PragmaType, StubNode};
+use crate::runner::adapter::{shell_adapter, ShellAdapter};
+use crate::runner::bench::BenchStats;
+use crate::runner::config::FileSettings;
+use crate::runner::http::{HttpRequest, HttpServer};
+use crate::runner::resources::{
+    parse_rusage_line, rusage_helper, rusage_line_prefix, CpuMeter, ResourceUsage,
+};
 use crate::runner::sentinel::{
-    extract_sentinel, generate_run_id, generate_shell_wrapper, SentinelData,
+    generate_run_id, output_text, parse_sentinel, sentinel_line_prefix, take_control_line,
+    SentinelData, CONTROL_FD,
 };
-use std::collections::HashMap;
+use crate::runner::stub::{self, StubCall};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::ExitStatusExt;
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tempfile::TempDir;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::unix::pipe;
 use tokio::process::{Child, ChildStdin, ChildStdout, Command};
-use tokio::time::{sleep, timeout, Duration};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, timeout_at, Duration, Instant};
 
 /// Maximum allowed timeout: 24 hours in milliseconds.
 /// Prevents integer overflow and unreasonably long test timeouts.
 const MAX_TIMEOUT_MS: u64 = 24 * 60 * 60 * 1000; // 86,400,000 ms
 
+/// How often a running command's shell is checked for having exited. Output
+/// is handled as soon as it arrives.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long to wait for output the shell wrote just before it exited.
+const EXIT_GRACE: Duration = Duration::from_millis(100);
+
+/// Output kept in memory per stream and RUN unless `#! max_output` says otherwise.
+pub const DEFAULT_MAX_OUTPUT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Chunks of up to 8KB the reader task may queue before waiting for the session.
+const OUTPUT_CHANNEL_CHUNKS: usize = 64;
+
+/// Variables a hermetic shell starts with, so output doesn't depend on the
+/// host's locale, timezone or terminal. Anything set explicitly overrides them.
+const HERMETIC_ENV: &[(&str, &str)] = &[
+    ("LC_ALL", "C.UTF-8"),
+    ("TZ", "UTC"),
+    ("COLUMNS", "80"),
+    ("LINES", "24"),
+    ("NO_COLOR", "1"),
+];
+
+/// umask of a hermetic shell, so created files get the same modes everywhere.
+const HERMETIC_UMASK: libc::mode_t = 0o022;
+
 #[derive(Debug, Clone)]
 pub struct ShellConfig {
     pub shell: String,
     pub env: HashMap<String, String>,
+    /// Names of `env` entries taken from the host through `pass_env`, whose
+    /// values are kept out of reports
+    pub passed_env: HashSet<String>,
     pub timeout_ms: u64,
     pub cwd: String,
     pub filename: String,
+    /// Bytes of stdout and of stderr kept per RUN; the rest is only on disk
+    pub max_output_bytes: u64,
+    /// Where RUN artifacts go instead of a new timestamped directory under `.hone/runs`
+    pub artifact_dir: Option<PathBuf>,
+    /// Set the umask and, unless `env` has a HOME, give every session an empty
+    /// private HOME; the `HERMETIC_ENV` defaults are already in `env`
+    pub hermetic: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -28,43 +85,73 @@ pub struct RunResult {
     pub stdout: String,
     pub stdout_raw: String,
     pub stderr: String,
+    /// Exact bytes written to stdout; `stdout` and `stdout_raw` are lossy
+    /// UTF-8 decodings of these
+    pub stdout_bytes: Vec<u8>,
+    pub stderr_bytes: Vec<u8>,
     pub exit_code: i32,
     pub duration_ms: u64,
     pub stderr_path: String,
+    /// `None` where per-process accounting is unavailable (no `/proc`)
+    pub resources: Option<ResourceUsage>,
+    /// Per-iteration statistics, set by the executor for a RUN with `REPEAT`
+    pub bench: Option<BenchStats>,
+    /// Set when stdout went over `max_output_bytes`; `stdout_bytes` then
+    /// holds only the first `max_output_bytes`
+    pub stdout_truncation: Option<Truncation>,
+    pub stderr_truncation: Option<Truncation>,
 }
 
-fn get_shell_flags(shell_path: &str) -> Vec<&str> {
-    let shell_name = Path::new(shell_path)
-        .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or("");
-
-    match shell_name {
-        "bash" => vec!["--norc", "--noprofile"],
-        "zsh" => vec!["--no-rcs"],
-        "sh" => vec![],
-        _ => vec![],
-    }
+/// A stream that was cut off at the `max_output` limit, and where to find all of it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Truncation {
+    pub limit_bytes: u64,
+    pub total_bytes: u64,
+    pub path: String,
 }
 
-pub fn is_shell_supported(shell_path: &str) -> bool {
-    let shell_name = Path::new(shell_path)
-        .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or("");
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RunError {
+    #[error("command timed out after {0}ms")]
+    Timeout(u64),
+    #[error("shell exited with code {0}")]
+    ShellExited(i32),
+    #[error("{0}")]
+    Session(String),
+}
 
-    matches!(shell_name, "bash" | "zsh" | "sh")
+impl RunError {
+    /// Whether the shell session is unusable after this error and must be
+    /// replaced before any further commands are run.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, RunError::Timeout(_) | RunError::ShellExited(_))
+    }
 }
 
 pub struct ShellSession {
     process: Option<Child>,
     stdin: Option<ChildStdin>,
-    stdout_reader: Option<BufReader<ChildStdout>>,
-    output_buffer: String,
+    /// Chunks forwarded by `reader_task` from the shell's stdout and the
+    /// control channel it writes sentinels to on `CONTROL_FD`
+    output_rx: Option<mpsc::Receiver<ShellOutput>>,
+    reader_task: Option<JoinHandle<()>>,
+    output_buffer: Vec<u8>,
+    /// Where stdout past `max_output_bytes` goes during the current RUN
+    spill: Option<Spill>,
+    control_buffer: Vec<u8>,
     config: ShellConfig,
     run_index: usize,
     current_test_name: Option<String>,
     artifact_dir: PathBuf,
+    /// Private HOME of a hermetic session, removed when the session ends
+    home_dir: Option<TempDir>,
+    /// Scripts and recorded calls of the TEST's STUBs, made by the first
+    /// one and removed when the session ends
+    stub_dir: Option<TempDir>,
+    /// Server for the TEST's HTTP routes, started by the first one
+    http_server: Option<HttpServer>,
+    /// Peak RSS the rusage helper reported for the current RUN
+    max_rss_bytes: Option<u64>,
 }
 
 impl ShellSession {
@@ -75,44 +162,89 @@ impl ShellSession {
             .and_then(|s| s.to_str())
             .unwrap_or("test");
 
-        let artifact_dir = PathBuf::from(&config.cwd)
-            .join(".hone")
-            .join("runs")
-            .join(format!("{}-{}", timestamp, basename));
+        let artifact_dir = config.artifact_dir.clone().unwrap_or_else(|| {
+            PathBuf::from(&config.cwd)
+                .join(".hone")
+                .join("runs")
+                .join(format!("{}-{}", timestamp, basename))
+        });
 
         Self {
             process: None,
             stdin: None,
-            stdout_reader: None,
-            output_buffer: String::new(),
+            output_rx: None,
+            reader_task: None,
+            output_buffer: Vec::new(),
+            spill: None,
+            control_buffer: Vec::new(),
             config,
             run_index: 0,
             current_test_name: None,
             artifact_dir,
+            home_dir: None,
+            stub_dir: None,
+            http_server: None,
+            max_rss_bytes: None,
         }
     }
 
     pub async fn start(&mut self) -> Result<(), String> {
-        let shell_flags = get_shell_flags(&self.config.shell);
+        let adapter = self.adapter()?;
 
         tokio::fs::create_dir_all(&self.artifact_dir)
             .await
             .map_err(|e| format!("Failed to create artifact directory: {}", e))?;
 
         let mut env = self.config.env.clone();
-        env.insert("PS1".to_string(), "".to_string());
+        for (key, value) in adapter.startup_env() {
+            env.insert(key.to_string(), value.to_string());
+        }
         env.insert("TERM".to_string(), "dumb".to_string());
+        if self.config.hermetic && !env.contains_key("HOME") {
+            let home_dir = private_dir("hone-home")
+                .map_err(|e| format!("Failed to create private HOME: {}", e))?;
+            env.insert(
+                "HOME".to_string(),
+                home_dir.path().to_string_lossy().into_owned(),
+            );
+            self.home_dir = Some(home_dir);
+        }
+        let umask = self.config.hermetic.then_some(HERMETIC_UMASK);
+
+        let (control_read, control_write) =
+            control_pipe().map_err(|e| format!("Failed to create control channel: {}", e))?;
+        let control_write_fd = control_write.as_raw_fd();
 
-        let mut child = Command::new(&self.config.shell)
-            .args(&shell_flags)
+        let mut command = Command::new(&self.config.shell);
+        command
+            .args(adapter.startup_flags())
             .current_dir(&self.config.cwd)
             .env_clear()
             .envs(&env)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
+            // Run the shell in its own process group so a timed out command
+            // and everything it spawned can be killed together
+            .process_group(0);
+        // SAFETY: the closure runs in the forked child before exec and only
+        // makes async-signal-safe system calls
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(umask) = umask {
+                    libc::umask(umask);
+                }
+                attach_control_fd(control_write_fd)
+            });
+        }
+        let mut child = command
             .spawn()
             .map_err(|e| format!("Failed to spawn shell: {}", e))?;
+        // Only the shell may hold the write end
+        drop(control_write);
+
+        let control = pipe::Receiver::from_owned_fd(control_read)
+            .map_err(|e| format!("Failed to open control channel: {}", e))?;
 
         self.stdin = Some(
             child
@@ -124,7 +256,9 @@ impl ShellSession {
             .stdout
             .take()
             .ok_or("Failed to capture stdout from shell process")?;
-        self.stdout_reader = Some(BufReader::new(stdout));
+        let (output_tx, output_rx) = mpsc::channel(OUTPUT_CHANNEL_CHUNKS);
+        self.reader_task = Some(tokio::spawn(forward_output(stdout, control, output_tx)));
+        self.output_rx = Some(output_rx);
         self.process = Some(child);
 
         self.wait_for_ready().await?;
@@ -132,12 +266,16 @@ impl ShellSession {
         Ok(())
     }
 
+    fn adapter(&self) -> Result<&'static dyn ShellAdapter, String> {
+        shell_adapter(&self.config.shell)
+    }
+
     async fn wait_for_ready(&mut self) -> Result<(), String> {
         let ready_marker = format!("__HONE_READY_{}__", chrono::Utc::now().timestamp_millis());
-        self.write_to_shell(&format!("echo \"{}\"\n", ready_marker))
-            .await?;
+        let probe = self.adapter()?.readiness_probe(&ready_marker);
+        self.write_to_shell(&format!("{}\n", probe)).await?;
 
-        let found = self.wait_for_string(&ready_marker, 5000).await;
+        let found = self.wait_for_control(&ready_marker, 5000).await.is_some();
 
         if !found {
             return Err(format!(
@@ -150,47 +288,81 @@ impl ShellSession {
         Ok(())
     }
 
-    async fn wait_for_string(&mut self, marker: &str, timeout_ms: u64) -> bool {
-        let start = std::time::Instant::now();
+    /// Waits for a control line starting with `marker` and returns the rest of it.
+    async fn wait_for_control(&mut self, marker: &str, timeout_ms: u64) -> Option<String> {
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
 
         loop {
-            self.read_available().await;
-
-            if self.output_buffer.contains(marker) {
-                return true;
+            if let Some(line) = take_control_line(&mut self.control_buffer, marker) {
+                return Some(line[marker.len()..].to_string());
             }
 
-            // Check timeout after attempting read, ensuring at least one attempt
-            if start.elapsed().as_millis() >= timeout_ms as u128 {
-                return false;
+            match self.receive(deadline).await {
+                Received::Output => {}
+                Received::Closed | Received::TimedOut => return None,
             }
-
-            sleep(Duration::from_millis(10)).await;
         }
     }
 
-    async fn read_available(&mut self) {
-        if let Some(reader) = &mut self.stdout_reader {
-            loop {
-                let mut line = String::new();
-                match timeout(Duration::from_millis(5), reader.read_line(&mut line)).await {
-                    Ok(Ok(n)) if n > 0 => {
-                        self.output_buffer.push_str(&line);
-                    }
-                    _ => break, // No more data available or timeout/error
-                }
+    /// Waits until the reader task forwards more output, or `deadline` passes.
+    /// Cancel safe: nothing is awaited once a chunk has been taken.
+    async fn receive(&mut self, deadline: Instant) -> Received {
+        let Some(output_rx) = &mut self.output_rx else {
+            return Received::Closed;
+        };
+
+        match timeout_at(deadline, output_rx.recv()).await {
+            Ok(Some(ShellOutput::Stdout(bytes))) => {
+                self.capture_stdout(&bytes);
+                Received::Output
+            }
+            Ok(Some(ShellOutput::Control(bytes))) => {
+                self.control_buffer.extend_from_slice(&bytes);
+                Received::Output
             }
+            Ok(None) => Received::Closed,
+            Err(_) => Received::TimedOut,
         }
     }
 
+    /// Keeps a RUN's stdout in memory up to the `max_output` limit. Once the
+    /// output goes over it, all of it is also written to the RUN's spill file.
+    fn capture_stdout(&mut self, bytes: &[u8]) {
+        let Some(spill) = &mut self.spill else {
+            self.output_buffer.extend_from_slice(bytes);
+            return;
+        };
+
+        let limit = self.config.max_output_bytes;
+        let previous_total = spill.total_bytes;
+        spill.total_bytes += bytes.len() as u64;
+
+        if spill.total_bytes <= limit {
+            self.output_buffer.extend_from_slice(bytes);
+            return;
+        }
+
+        if previous_total <= limit {
+            // Just went over: the spill file starts with what was kept so far
+            spill.write(&self.output_buffer);
+            let kept = (limit - previous_total) as usize;
+            self.output_buffer.extend_from_slice(&bytes[..kept]);
+        }
+        spill.write(bytes);
+    }
+
+    pub fn config(&self) -> &ShellConfig {
+        &self.config
+    }
+
     pub fn set_current_test(&mut self, test_name: Option<String>) {
         self.current_test_name = test_name;
     }
 
     pub async fn set_env_vars(&mut self, vars: &[(String, String)]) -> Result<(), String> {
+        let adapter = self.adapter()?;
         for (key, value) in vars {
-            let escaped_value = value.replace('\'', "'\\''");
-            self.write_to_shell(&format!("export {}='{}'\n", key, escaped_value))
+            self.write_to_shell(&format!("{}\n", adapter.export_env(key, value)))
                 .await?;
         }
 
@@ -198,43 +370,99 @@ impl ShellSession {
         Ok(())
     }
 
-    pub async fn get_cwd(&mut self) -> Result<String, String> {
-        let marker = format!("__HONE_CWD_{}__", chrono::Utc::now().timestamp_millis());
-        self.write_to_shell(&format!("echo \"{}$PWD{}\"\n", marker, marker))
-            .await?;
+    /// Writes the script for a STUB. The first one creates the session's stub
+    /// directory and puts it in front of the shell's PATH.
+    pub async fn add_stub(&mut self, stub: &StubNode) -> Result<(), String> {
+        let stub_dir = match &self.stub_dir {
+            Some(stub_dir) => stub_dir.path().to_path_buf(),
+            None => {
+                let adapter = self.adapter()?;
+                let stub_dir = private_dir("hone-stubs")
+                    .map_err(|e| format!("Failed to create stub directory: {}", e))?;
+                let path = stub_dir.path().to_path_buf();
+                self.stub_dir = Some(stub_dir);
+                let bin_dir = stub::bin_dir(&path);
+                self.write_to_shell(&format!(
+                    "{}\n",
+                    adapter.prepend_path(&bin_dir.to_string_lossy())
+                ))
+                .await?;
+                self.flush().await?;
+                path
+            }
+        };
 
-        let found = self.wait_for_string(&marker, 2000).await;
-        if !found {
-            return Ok(self.config.cwd.clone()); // Fallback
-        }
+        stub::write_stub(&stub_dir, stub)
+            .map_err(|e| format!("Failed to write stub {}: {}", stub.command, e))
+    }
 
-        let pattern = format!("{}(.+?){}", regex::escape(&marker), regex::escape(&marker));
-        if let Ok(re) = regex::Regex::new(&pattern) {
-            if let Some(captures) = re.captures(&self.output_buffer) {
-                if let Some(cwd_match) = captures.get(1) {
-                    let cwd = cwd_match.as_str().to_string();
-                    self.output_buffer.clear();
-                    return Ok(cwd);
-                }
-            }
+    /// Calls recorded by the STUB for `command`, or `None` if there is none.
+    pub fn stub_calls(&self, command: &str) -> Result<Option<Vec<StubCall>>, String> {
+        let Some(stub_dir) = &self.stub_dir else {
+            return Ok(None);
+        };
+        stub::read_calls(stub_dir.path(), command)
+            .map_err(|e| format!("Failed to read calls of stub {}: {}", command, e))
+    }
+
+    /// Adds an HTTP route. The first one starts the session's HTTP server and
+    /// exports its URL as `HONE_HTTP_URL`.
+    pub async fn add_http_route(&mut self, route: &HttpNode) -> Result<(), String> {
+        if self.http_server.is_none() {
+            let server = HttpServer::start()
+                .await
+                .map_err(|e| format!("Failed to start HTTP server: {}", e))?;
+            let url = server.url().to_string();
+            self.http_server = Some(server);
+            self.set_env_vars(&[("HONE_HTTP_URL".to_string(), url)])
+                .await?;
         }
+        if let Some(server) = &self.http_server {
+            server.add_route(route);
+        }
+        Ok(())
+    }
+
+    /// Requests received by the HTTP server, or `None` if the TEST has no
+    /// HTTP routes.
+    pub fn http_requests(&self) -> Option<Vec<HttpRequest>> {
+        self.http_server.as_ref().map(HttpServer::requests)
+    }
+
+    pub async fn get_cwd(&mut self) -> Result<String, String> {
+        let marker = format!("__HONE_CWD_{}__", chrono::Utc::now().timestamp_millis());
+        let query = self.adapter()?.cwd_query(&marker);
+        self.write_to_shell(&format!("{}\n", query)).await?;
 
-        Ok(self.config.cwd.clone()) // Fallback
+        match self.wait_for_control(&marker, 2000).await {
+            Some(cwd) if !cwd.is_empty() => Ok(cwd),
+            _ => Ok(self.config.cwd.clone()), // Fallback
+        }
     }
 
     async fn flush(&mut self) -> Result<(), String> {
         let flush_marker = format!("__HONE_FLUSH_{}__", chrono::Utc::now().timestamp_millis());
-        self.write_to_shell(&format!("echo \"{}\"\n", flush_marker))
-            .await?;
+        let probe = self.adapter()?.readiness_probe(&flush_marker);
+        self.write_to_shell(&format!("{}\n", probe)).await?;
 
-        self.wait_for_string(&flush_marker, 2000).await;
+        self.wait_for_control(&flush_marker, 2000).await;
         self.output_buffer.clear();
         Ok(())
     }
 
-    pub async fn run(&mut self, command: &str, name: Option<&str>) -> Result<RunResult, String> {
+    pub async fn run(
+        &mut self,
+        command: &str,
+        name: Option<&str>,
+        timeout_ms: u64,
+        measure_rss: bool,
+    ) -> Result<RunResult, RunError> {
         if self.process.is_none() {
-            return Err("Shell session not started".to_string());
+            return Err(RunError::Session("Shell session not started".to_string()));
+        }
+
+        if let Some(code) = self.exit_code() {
+            return Err(RunError::ShellExited(code));
         }
 
         self.run_index += 1;
@@ -246,59 +474,179 @@ impl ShellSession {
         );
 
         let stderr_path = self.artifact_dir.join(format!("{}-stderr.txt", run_id));
-        let stderr_path_str = stderr_path
-            .to_str()
-            .ok_or("Failed to convert stderr path to string")?;
+        let stderr_path_str = stderr_path.to_str().ok_or_else(|| {
+            RunError::Session("Failed to convert stderr path to string".to_string())
+        })?;
 
-        let wrapper = generate_shell_wrapper(command, &run_id, stderr_path_str);
+        let helper = if measure_rss {
+            rusage_helper().and_then(Path::to_str)
+        } else {
+            None
+        };
+        let wrapper = self.adapter().map_err(RunError::Session)?.wrapper(
+            command,
+            &run_id,
+            stderr_path_str,
+            helper,
+        );
+        // Output written between RUNs (e.g. by background jobs) belongs to no RUN
+        self.output_buffer.clear();
+        self.spill = Some(Spill::new(
+            self.artifact_dir.join(format!("{}-stdout.txt", run_id)),
+        ));
+        self.max_rss_bytes = None;
+        let meter = self
+            .process
+            .as_ref()
+            .and_then(|p| p.id())
+            .and_then(CpuMeter::start);
         let start_time = std::time::Instant::now();
 
-        self.write_to_shell(&format!("{}\n", wrapper)).await?;
+        if let Err(e) = self.write_to_shell(&format!("{}\n", wrapper)).await {
+            return Err(match self.exit_code() {
+                Some(code) => RunError::ShellExited(code),
+                None => RunError::Session(e),
+            });
+        }
 
-        let result = self.wait_for_sentinel(&run_id).await?;
+        let result = self.wait_for_sentinel(&run_id, timeout_ms).await;
+        let spill = self.spill.take();
+        let result = result?;
         let duration_ms = start_time.elapsed().as_millis() as u64;
-
-        let stderr = tokio::fs::read_to_string(&stderr_path)
-            .await
-            .unwrap_or_default();
+        let resources = meter.and_then(|meter| meter.finish(self.max_rss_bytes.take()));
+
+        let limit = self.config.max_output_bytes;
+        let stdout_truncation = match spill {
+            Some(spill) => spill.finish(limit).map_err(RunError::Session)?,
+            None => None,
+        };
+        let (stderr_bytes, stderr_total) = read_capped(&stderr_path, limit).await;
+        let stderr_truncation = (stderr_total > limit).then(|| Truncation {
+            limit_bytes: limit,
+            total_bytes: stderr_total,
+            path: stderr_path_str.to_string(),
+        });
+        let stdout_raw = output_text(&result.output);
+        let exit_code = result.sentinel.exit_code;
 
         Ok(RunResult {
             run_id: run_id.clone(),
-            stdout: strip_ansi_escapes::strip_str(&result.output),
-            stdout_raw: result.output,
-            stderr,
-            exit_code: result.sentinel.as_ref().map(|s| s.exit_code).unwrap_or(-1),
+            stdout: strip_ansi_escapes::strip_str(&stdout_raw),
+            stdout_raw,
+            stderr: String::from_utf8_lossy(&stderr_bytes).into_owned(),
+            stdout_bytes: result.output,
+            stderr_bytes,
+            exit_code,
             duration_ms,
             stderr_path: stderr_path_str.to_string(),
+            resources,
+            bench: None,
+            stdout_truncation,
+            stderr_truncation,
         })
     }
 
-    async fn wait_for_sentinel(&mut self, run_id: &str) -> Result<SentinelResult, String> {
-        let start_time = std::time::Instant::now();
+    async fn wait_for_sentinel(
+        &mut self,
+        run_id: &str,
+        timeout_ms: u64,
+    ) -> Result<SentinelResult, RunError> {
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
 
         loop {
-            self.read_available().await;
+            if let Some(sentinel) = self.take_sentinel(run_id) {
+                return Ok(self.collect_output(sentinel));
+            }
 
-            let result = extract_sentinel(&self.output_buffer, run_id);
+            if let Some(code) = self.exit_code() {
+                // The shell may have written the sentinel right before exiting
+                let grace = Instant::now() + EXIT_GRACE;
+                while let Received::Output = self.receive(grace).await {
+                    if let Some(sentinel) = self.take_sentinel(run_id) {
+                        return Ok(self.collect_output(sentinel));
+                    }
+                }
+                return Err(RunError::ShellExited(code));
+            }
+
+            if Instant::now() >= deadline {
+                self.kill_process_group().await;
+                return Err(RunError::Timeout(timeout_ms));
+            }
 
-            if result.found {
-                self.output_buffer = result.remaining;
-                return Ok(SentinelResult {
-                    output: result.output,
-                    sentinel: result.sentinel,
-                });
+            tokio::select! {
+                received = self.receive(deadline) => {
+                    if let Received::Closed = received {
+                        // Output is closed but the shell hasn't exited yet;
+                        // keep checking until it does
+                        sleep(EXIT_POLL_INTERVAL).await;
+                    }
+                }
+                _ = sleep(EXIT_POLL_INTERVAL) => {}
             }
+        }
+    }
+
+    fn take_sentinel(&mut self, run_id: &str) -> Option<SentinelData> {
+        // The rusage helper of a measured RUN reports just before the sentinel
+        if let Some(line) = take_control_line(&mut self.control_buffer, &rusage_line_prefix(run_id))
+        {
+            self.max_rss_bytes = parse_rusage_line(&line, run_id);
+        }
+        let line = take_control_line(&mut self.control_buffer, &sentinel_line_prefix(run_id))?;
+        parse_sentinel(&line)
+    }
+
+    /// The command finished writing to stdout before the shell wrote its
+    /// sentinel, and the reader task forwards stdout ahead of the control
+    /// channel, so everything the command wrote has already been received.
+    fn collect_output(&mut self, sentinel: SentinelData) -> SentinelResult {
+        SentinelResult {
+            output: std::mem::take(&mut self.output_buffer),
+            sentinel,
+        }
+    }
+
+    /// Returns the shell's exit code if the shell process has exited.
+    /// Termination by a signal is reported as `128 + signal`, as shells do.
+    fn exit_code(&mut self) -> Option<i32> {
+        let process = self.process.as_mut()?;
+        match process.try_wait() {
+            Ok(Some(status)) => Some(
+                status
+                    .code()
+                    .or_else(|| status.signal().map(|signal| 128 + signal))
+                    .unwrap_or(-1),
+            ),
+            _ => None,
+        }
+    }
 
-            // Check timeout after attempting read, ensuring at least one attempt
-            if start_time.elapsed().as_millis() >= self.config.timeout_ms as u128 {
-                return Err(format!(
-                    "Timeout waiting for command completion ({}ms). Run ID: {}",
-                    self.config.timeout_ms, run_id
-                ));
+    /// Kills the shell and every process it started, then reaps the shell.
+    async fn kill_process_group(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            if let Some(pid) = process.id() {
+                // SAFETY: killpg has no memory safety requirements; the shell was
+                // spawned as the leader of its own process group
+                unsafe {
+                    libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+                }
             }
+            let _ = process.kill().await;
+        }
 
-            sleep(Duration::from_millis(10)).await;
+        self.stdin = None;
+        self.stop_reader();
+    }
+
+    /// Stops forwarding output. Background processes may keep the shell's
+    /// stdout open after it has gone, so the reader task can't be left to
+    /// finish on its own.
+    fn stop_reader(&mut self) {
+        if let Some(task) = self.reader_task.take() {
+            task.abort();
         }
+        self.output_rx = None;
     }
 
     async fn write_to_shell(&mut self, data: &str) -> Result<(), String> {
@@ -330,22 +678,247 @@ impl ShellSession {
         }
 
         self.stdin = None;
-        self.stdout_reader = None;
+        self.stop_reader();
+        self.remove_private_dirs();
+        self.http_server = None;
         Ok(())
     }
+
+    fn remove_private_dirs(&mut self) {
+        for dir in [self.home_dir.take(), self.stub_dir.take()]
+            .into_iter()
+            .flatten()
+        {
+            let _ = dir.close();
+        }
+    }
 }
 
 impl Drop for ShellSession {
     fn drop(&mut self) {
         if let Some(mut process) = self.process.take() {
+            if let Some(pid) = process.id() {
+                // SAFETY: see `kill_process_group`
+                unsafe {
+                    libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+                }
+            }
             let _ = process.start_kill();
         }
+        self.stop_reader();
+        self.remove_private_dirs();
     }
 }
 
 struct SentinelResult {
-    output: String,
-    sentinel: Option<SentinelData>,
+    output: Vec<u8>,
+    sentinel: SentinelData,
+}
+
+/// The file a RUN's stdout is written to once it goes over the `max_output`
+/// limit. Opened only when needed.
+///
+/// Writes are blocking so that `ShellSession::receive` stays cancel safe;
+/// they go through a buffer to a local file, so they are short.
+struct Spill {
+    path: PathBuf,
+    file: Option<std::io::BufWriter<std::fs::File>>,
+    total_bytes: u64,
+    error: Option<std::io::Error>,
+}
+
+impl Spill {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            total_bytes: 0,
+            error: None,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.try_write(bytes) {
+            self.error = Some(e);
+        }
+    }
+
+    fn try_write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self
+                .file
+                .insert(std::io::BufWriter::new(std::fs::File::create(&self.path)?)),
+        };
+        std::io::Write::write_all(file, bytes)
+    }
+
+    /// Describes the truncation, if the output went over `limit`.
+    fn finish(mut self, limit: u64) -> Result<Option<Truncation>, String> {
+        if let Some(file) = &mut self.file {
+            if let Err(e) = std::io::Write::flush(file) {
+                self.error.get_or_insert(e);
+            }
+        }
+        if let Some(e) = self.error {
+            return Err(format!(
+                "Failed to save full output to {}: {}",
+                self.path.display(),
+                e
+            ));
+        }
+
+        Ok((self.total_bytes > limit).then(|| Truncation {
+            limit_bytes: limit,
+            total_bytes: self.total_bytes,
+            path: self.path.to_string_lossy().into_owned(),
+        }))
+    }
+}
+
+/// Reads at most `limit` bytes of a file, along with the file's full size.
+async fn read_capped(path: &Path, limit: u64) -> (Vec<u8>, u64) {
+    let Ok(file) = tokio::fs::File::open(path).await else {
+        return (Vec::new(), 0);
+    };
+    let size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+
+    let mut bytes = Vec::new();
+    let _ = file.take(limit).read_to_end(&mut bytes).await;
+    (bytes, size)
+}
+
+enum ShellOutput {
+    Stdout(Vec<u8>),
+    Control(Vec<u8>),
+}
+
+enum Received {
+    Output,
+    Closed,
+    TimedOut,
+}
+
+/// Forwards the shell's stdout and control channel to the session as chunks
+/// arrive, until both are closed or the session stops listening. The channel
+/// is bounded, so a shell writing faster than the session reads blocks on its
+/// stdout pipe instead of growing memory.
+///
+/// Stdout is always drained first: a command's output is written before the
+/// shell writes its sentinel, so when both are readable, the output must reach
+/// the session ahead of the sentinel that ends it.
+async fn forward_output(
+    mut stdout: ChildStdout,
+    mut control: pipe::Receiver,
+    output_tx: mpsc::Sender<ShellOutput>,
+) {
+    let mut stdout_buf = vec![0u8; 8192];
+    let mut control_buf = vec![0u8; 1024];
+    let mut stdout_open = true;
+    let mut control_open = true;
+
+    while stdout_open || control_open {
+        let chunk = tokio::select! {
+            biased;
+            chunk = read_chunk(&mut stdout, &mut stdout_buf), if stdout_open => {
+                match chunk {
+                    Some(bytes) => ShellOutput::Stdout(bytes),
+                    None => {
+                        stdout_open = false;
+                        continue;
+                    }
+                }
+            }
+            chunk = read_chunk(&mut control, &mut control_buf), if control_open => {
+                match chunk {
+                    Some(bytes) => ShellOutput::Control(bytes),
+                    None => {
+                        control_open = false;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        if output_tx.send(chunk).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads the next chunk, or `None` once the stream is closed.
+async fn read_chunk<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8]) -> Option<Vec<u8>> {
+    match reader.read(buf).await {
+        Ok(0) | Err(_) => None,
+        Ok(n) => Some(buf[..n].to_vec()),
+    }
+}
+
+/// Creates the control channel pipe as `(read, write)`. Both ends are
+/// close-on-exec; `attach_control_fd` gives the shell its copy of the write end.
+fn control_pipe() -> std::io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0 as RawFd; 2];
+    // SAFETY: `fds` has room for the two descriptors pipe writes
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: pipe succeeded, so both descriptors are open and owned by us
+    let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    for fd in [&read, &write] {
+        // SAFETY: fcntl on a descriptor we own (pipe2 is not available on macOS)
+        if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok((read, write))
+}
+
+/// Creates an empty directory only accessible to us, such as a session's
+/// HOME, under an unpredictable name. It is removed when dropped.
+fn private_dir(prefix: &str) -> std::io::Result<TempDir> {
+    tempfile::Builder::new()
+        .prefix(&format!("{}-", prefix))
+        .tempdir()
+}
+
+/// Runs in the forked shell before exec: exposes the control pipe as `CONTROL_FD`.
+fn attach_control_fd(fd: RawFd) -> std::io::Result<()> {
+    // SAFETY: dup2 and fcntl are async-signal-safe and only touch descriptors
+    let result = unsafe {
+        if fd == CONTROL_FD {
+            // dup2 onto itself would leave close-on-exec set
+            libc::fcntl(fd, libc::F_SETFD, 0)
+        } else {
+            libc::dup2(fd, CONTROL_FD)
+        }
+    };
+    if result == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Converts a `TIMEOUT` duration to milliseconds, capped at `MAX_TIMEOUT_MS`.
+pub fn timeout_to_ms(duration: &DslDuration) -> u64 {
+    let ms_value = duration_to_ms(duration);
+    if ms_value > MAX_TIMEOUT_MS as f64 {
+        MAX_TIMEOUT_MS
+    } else {
+        ms_value as u64
+    }
+}
+
+/// Shells listed by the file's `shells` pragma, if it has one.
+pub fn pragma_shells(pragmas: &[PragmaNode]) -> Vec<String> {
+    pragmas
+        .iter()
+        .rev()
+        .find(|p| p.pragma_type == PragmaType::Shells)
+        .map(|p| p.value.split(',').map(|s| s.trim().to_string()).collect())
+        .unwrap_or_default()
 }
 
 pub fn create_shell_config(
@@ -353,41 +926,88 @@ pub fn create_shell_config(
     filename: &str,
     cwd: &str,
     override_shell: Option<&str>,
+    settings: &FileSettings,
 ) -> ShellConfig {
     let mut shell = override_shell
         .map(String::from)
+        .or_else(|| settings.shell.clone())
         .or_else(|| std::env::var("SHELL").ok())
         .unwrap_or_else(|| "/bin/bash".to_string());
 
+    let hermetic = pragmas
+        .iter()
+        .rev()
+        .find(|p| p.pragma_type == PragmaType::Hermetic)
+        .map(|p| p.value == "true")
+        .or(settings.hermetic)
+        .unwrap_or(true);
+
     let mut env = HashMap::new();
     env.insert(
         "PATH".to_string(),
         std::env::var("PATH").unwrap_or_else(|_| "/usr/bin:/bin".to_string()),
     );
-    env.insert(
-        "HOME".to_string(),
-        std::env::var("HOME").unwrap_or_else(|_| "/".to_string()),
-    );
+    if hermetic {
+        // HOME is left out so the session makes a private one
+        env.extend(
+            HERMETIC_ENV
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        );
+    } else {
+        env.insert(
+            "HOME".to_string(),
+            std::env::var("HOME").unwrap_or_else(|_| "/".to_string()),
+        );
+    }
+    // Host variables passed through sit under everything set explicitly
+    let pass_env: Vec<glob::Pattern> = settings
+        .pass_env
+        .iter()
+        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+        .collect();
+    let mut passed_env = HashSet::new();
+    if !pass_env.is_empty() {
+        for (name, value) in std::env::vars() {
+            if pass_env.iter().any(|p| p.matches(&name)) {
+                passed_env.insert(name.clone());
+                env.insert(name, value);
+            }
+        }
+    }
+    for (name, value) in &settings.env {
+        passed_env.remove(name);
+        env.insert(name.clone(), value.clone());
+    }
 
     static TIMEOUT_RE: OnceLock<regex::Regex> = OnceLock::new();
     let timeout_re = TIMEOUT_RE.get_or_init(|| {
         regex::Regex::new(r"^(\d+(?:\.\d+)?)(ms|s)$").expect("timeout regex should be valid")
     });
 
-    let mut timeout_ms = 30000; // 30 seconds default
+    let mut timeout_ms = settings.timeout_ms.unwrap_or(30000); // 30 seconds default
+    let mut max_output_bytes = DEFAULT_MAX_OUTPUT_BYTES;
 
     for pragma in pragmas {
         match pragma.pragma_type {
-            PragmaType::Shell => {
-                if override_shell.is_none() {
-                    shell = pragma.value.clone();
-                }
+            PragmaType::Shell if override_shell.is_none() => {
+                shell = pragma.value.clone();
             }
             PragmaType::Env => {
                 if let Some(key) = &pragma.key {
+                    passed_env.remove(key);
                     env.insert(key.clone(), pragma.value.clone());
                 }
             }
+            PragmaType::EnvFile => {
+                // The parser already reported unreadable or invalid files
+                if let Ok(vars) = load_env_file(Path::new(&pragma.value)) {
+                    for (name, value) in vars {
+                        passed_env.remove(&name);
+                        env.insert(name, value);
+                    }
+                }
+            }
             PragmaType::Timeout => {
                 if let Some(captures) = timeout_re.captures(&pragma.value) {
                     // Regex pattern guarantees groups 1 and 2 exist on match
@@ -407,6 +1027,11 @@ pub fn create_shell_config(
                     }
                 }
             }
+            PragmaType::MaxOutput => {
+                if let Some((size, _)) = parse_memory_size(&pragma.value, 0) {
+                    max_output_bytes = (memory_to_bytes(&size) as u64).max(1);
+                }
+            }
             _ => {}
         }
     }
@@ -414,9 +1039,13 @@ pub fn create_shell_config(
     ShellConfig {
         shell,
         env,
+        passed_env,
         timeout_ms,
         cwd: cwd.to_string(),
         filename: filename.to_string(),
+        max_output_bytes,
+        artifact_dir: None,
+        hermetic,
     }
 }
 
@@ -427,13 +1056,40 @@ mod tests {
 
     #[test]
     fn test_create_shell_config_defaults() {
-        let config = create_shell_config(&[], "test.hone", "/tmp", None);
+        let config = create_shell_config(&[], "test.hone", "/tmp", None, &FileSettings::default());
 
         assert_eq!(config.timeout_ms, 30000);
         assert_eq!(config.cwd, "/tmp");
         assert_eq!(config.filename, "test.hone");
         assert!(config.env.contains_key("PATH"));
+        assert!(config.hermetic);
+        assert_eq!(config.env.get("TZ"), Some(&"UTC".to_string()));
+        // The session creates a private HOME instead
+        assert!(!config.env.contains_key("HOME"));
+    }
+
+    #[test]
+    fn test_create_shell_config_hermetic_off() {
+        let settings = FileSettings {
+            hermetic: Some(false),
+            ..Default::default()
+        };
+        let config = create_shell_config(&[], "test.hone", "/tmp", None, &settings);
+        assert!(!config.hermetic);
         assert!(config.env.contains_key("HOME"));
+        assert!(!config.env.contains_key("LC_ALL"));
+
+        // The pragma wins over hone.toml
+        let pragmas = vec![PragmaNode {
+            pragma_type: PragmaType::Hermetic,
+            key: None,
+            value: "true".to_string(),
+            line: 1,
+            raw: "#! hermetic: true".to_string(),
+        }];
+        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None, &settings);
+        assert!(config.hermetic);
+        assert_eq!(config.env.get("LC_ALL"), Some(&"C.UTF-8".to_string()));
     }
 
     #[test]
@@ -446,7 +1102,13 @@ mod tests {
             raw: "#!timeout 5000ms".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.timeout_ms, 5000);
     }
 
@@ -460,7 +1122,13 @@ mod tests {
             raw: "#!timeout 10s".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.timeout_ms, 10000);
     }
 
@@ -474,7 +1142,13 @@ mod tests {
             raw: "#!timeout 2.5s".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.timeout_ms, 2500);
     }
 
@@ -488,7 +1162,13 @@ mod tests {
             raw: "#!timeout 100.7ms".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.timeout_ms, 100);
     }
 
@@ -502,7 +1182,13 @@ mod tests {
             raw: "#!timeout 0ms".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.timeout_ms, 0);
     }
 
@@ -516,7 +1202,13 @@ mod tests {
             raw: "#!timeout invalid".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.timeout_ms, 30000);
     }
 
@@ -531,7 +1223,13 @@ mod tests {
             raw: "#!timeout 999999s".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         // Capped at MAX_TIMEOUT_MS (24 hours)
         assert_eq!(config.timeout_ms, MAX_TIMEOUT_MS);
     }
@@ -546,7 +1244,13 @@ mod tests {
             raw: "#!shell /bin/zsh".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.shell, "/bin/zsh");
     }
 
@@ -560,7 +1264,13 @@ mod tests {
             raw: "#!shell /bin/zsh".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", Some("/bin/sh"));
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            Some("/bin/sh"),
+            &FileSettings::default(),
+        );
         assert_eq!(config.shell, "/bin/sh");
     }
 
@@ -574,7 +1284,13 @@ mod tests {
             raw: "#!env MY_VAR=test_value".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.env.get("MY_VAR"), Some(&"test_value".to_string()));
     }
 
@@ -604,12 +1320,103 @@ mod tests {
             },
         ];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         assert_eq!(config.shell, "/bin/zsh");
         assert_eq!(config.timeout_ms, 60000);
         assert_eq!(config.env.get("TEST"), Some(&"value".to_string()));
     }
 
+    #[test]
+    fn test_create_shell_config_project_settings_under_pragmas() {
+        let settings = FileSettings {
+            shell: Some("/bin/dash".to_string()),
+            timeout_ms: Some(5000),
+            env: vec![
+                ("MODE".to_string(), "project".to_string()),
+                ("ONLY_PROJECT".to_string(), "yes".to_string()),
+            ],
+            pass_env: vec![],
+            hermetic: None,
+        };
+
+        let config = create_shell_config(&[], "test.hone", "/tmp", None, &settings);
+        assert_eq!(config.shell, "/bin/dash");
+        assert_eq!(config.timeout_ms, 5000);
+        assert_eq!(config.env.get("MODE"), Some(&"project".to_string()));
+
+        let pragmas = vec![
+            PragmaNode {
+                pragma_type: PragmaType::Shell,
+                key: None,
+                value: "/bin/zsh".to_string(),
+                line: 1,
+                raw: "#! shell: /bin/zsh".to_string(),
+            },
+            PragmaNode {
+                pragma_type: PragmaType::Env,
+                key: Some("MODE".to_string()),
+                value: "pragma".to_string(),
+                line: 2,
+                raw: "#! env: MODE=pragma".to_string(),
+            },
+        ];
+        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None, &settings);
+        assert_eq!(config.shell, "/bin/zsh");
+        assert_eq!(config.timeout_ms, 5000);
+        assert_eq!(config.env.get("MODE"), Some(&"pragma".to_string()));
+        assert_eq!(config.env.get("ONLY_PROJECT"), Some(&"yes".to_string()));
+
+        let config = create_shell_config(&pragmas, "test.hone", "/tmp", Some("/bin/sh"), &settings);
+        assert_eq!(config.shell, "/bin/sh");
+    }
+
+    #[test]
+    fn test_create_shell_config_env_file_and_pass_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let env_file = dir.path().join(".env.test");
+        std::fs::write(&env_file, "FROM_FILE=yes\nMODE=file\n").unwrap();
+        let (host_name, host_value) = std::env::vars()
+            .find(|(name, _)| name != "PATH" && name != "HOME")
+            .expect("tests run with some environment");
+
+        let pragmas = vec![
+            PragmaNode {
+                pragma_type: PragmaType::EnvFile,
+                key: None,
+                value: env_file.to_string_lossy().into_owned(),
+                line: 1,
+                raw: "#! env_file: .env.test".to_string(),
+            },
+            PragmaNode {
+                pragma_type: PragmaType::Env,
+                key: Some("MODE".to_string()),
+                value: "pragma".to_string(),
+                line: 2,
+                raw: "#! env: MODE=pragma".to_string(),
+            },
+        ];
+        let settings = FileSettings {
+            pass_env: vec![host_name.clone()],
+            ..Default::default()
+        };
+
+        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None, &settings);
+        assert_eq!(config.env.get("FROM_FILE"), Some(&"yes".to_string()));
+        assert_eq!(config.env.get("MODE"), Some(&"pragma".to_string()));
+        assert_eq!(config.env.get(&host_name), Some(&host_value));
+        assert!(config.passed_env.contains(&host_name));
+        assert!(!config.passed_env.contains("MODE"));
+
+        let config = create_shell_config(&[], "test.hone", "/tmp", None, &FileSettings::default());
+        assert!(!config.env.contains_key(&host_name));
+    }
+
     #[test]
     fn test_create_shell_config_timeout_overflow_protection() {
         // Value large enough to overflow u64 when multiplied by 1000
@@ -621,7 +1428,13 @@ mod tests {
             raw: "#!timeout 18446744073709551s".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         // Should be capped at MAX_TIMEOUT_MS (24 hours) instead of overflowing
         assert_eq!(config.timeout_ms, MAX_TIMEOUT_MS);
     }
@@ -637,7 +1450,13 @@ mod tests {
             raw: "#!timeout 999999999999ms".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         // Should be capped at MAX_TIMEOUT_MS (24 hours)
         assert_eq!(config.timeout_ms, MAX_TIMEOUT_MS);
     }
@@ -653,8 +1472,137 @@ mod tests {
             raw: "#!timeout -5s".to_string(),
         }];
 
-        let config = create_shell_config(&pragmas, "test.hone", "/tmp", None);
+        let config = create_shell_config(
+            &pragmas,
+            "test.hone",
+            "/tmp",
+            None,
+            &FileSettings::default(),
+        );
         // Should use default timeout since negative is invalid
         assert_eq!(config.timeout_ms, 30000);
     }
+
+    fn test_session() -> (tempfile::TempDir, ShellSession) {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let cwd = dir.path().to_string_lossy().into_owned();
+        let config = create_shell_config(
+            &[],
+            "session.hone",
+            &cwd,
+            Some("/bin/sh"),
+            &FileSettings::default(),
+        );
+        (dir, ShellSession::new(config))
+    }
+
+    #[test]
+    fn test_timeout_to_ms() {
+        let duration = DslDuration {
+            value: 1.5,
+            unit: crate::parser::DurationUnit::Seconds,
+            raw: "1.5s".to_string(),
+        };
+        assert_eq!(timeout_to_ms(&duration), 1500);
+
+        let duration = DslDuration {
+            value: 100000.0,
+            unit: crate::parser::DurationUnit::Seconds,
+            raw: "100000s".to_string(),
+        };
+        assert_eq!(timeout_to_ms(&duration), MAX_TIMEOUT_MS);
+    }
+
+    #[test]
+    fn test_run_error_messages() {
+        assert_eq!(
+            RunError::Timeout(500).to_string(),
+            "command timed out after 500ms"
+        );
+        assert_eq!(
+            RunError::ShellExited(3).to_string(),
+            "shell exited with code 3"
+        );
+        assert!(RunError::Timeout(500).is_fatal());
+        assert!(RunError::ShellExited(3).is_fatal());
+        assert!(!RunError::Session("broken".to_string()).is_fatal());
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_timeout() {
+        let (_dir, mut session) = test_session();
+        session.start().await.expect("shell should start");
+
+        let result = session.run("sleep 5", None, 200, false).await;
+        assert_eq!(result.unwrap_err(), RunError::Timeout(200));
+
+        // The shell was killed along with the command
+        let result = session.run("echo after", None, 200, false).await;
+        assert!(matches!(result, Err(RunError::Session(_))));
+    }
+
+    #[tokio::test]
+    async fn test_hermetic_session_has_private_home_and_umask() {
+        let (_dir, mut session) = test_session();
+        session.start().await.expect("shell should start");
+
+        let result = session
+            .run(
+                "echo \"$HOME\"; ls -A \"$HOME\" | wc -l; umask",
+                None,
+                5000,
+                false,
+            )
+            .await
+            .expect("command should run");
+        let lines: Vec<&str> = result.stdout.lines().collect();
+        let home = PathBuf::from(lines[0]);
+        assert!(home.is_dir());
+        assert_eq!(lines[1].trim(), "0");
+        assert_eq!(lines[2], "0022");
+
+        session.stop().await.expect("shell should stop");
+        assert!(!home.exists());
+    }
+
+    #[tokio::test]
+    async fn test_output_larger_than_channel_is_kept() {
+        let (_dir, mut session) = test_session();
+        session.start().await.expect("shell should start");
+
+        // Many times what the output channel holds, so the reader task has to wait
+        let result = session
+            .run("head -c 2000000 /dev/zero | tr '\\0' a", None, 10000, false)
+            .await
+            .expect("command should run");
+        assert_eq!(result.stdout.len(), 2_000_000);
+        assert!(result.stdout.bytes().all(|b| b == b'a'));
+    }
+
+    #[tokio::test]
+    async fn test_measured_run_without_helper_keeps_cpu_time() {
+        let (_dir, mut session) = test_session();
+        session.start().await.expect("shell should start");
+
+        let result = session
+            .run("echo hi", None, 5000, true)
+            .await
+            .expect("command should run");
+        assert_eq!(result.stdout, "hi");
+        if let Some(resources) = result.resources {
+            assert_eq!(resources.max_rss_bytes, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_shell_exit() {
+        let (_dir, mut session) = test_session();
+        session.start().await.expect("shell should start");
+
+        let started = std::time::Instant::now();
+        let result = session.run("exit 7", None, 5000, false).await;
+        assert_eq!(result.unwrap_err(), RunError::ShellExited(7));
+        // Detected through the child handle rather than by waiting for the timeout
+        assert!(started.elapsed() < Duration::from_millis(5000));
+    }
 }
@@ -0,0 +1,208 @@
+use crate::parser::ast::{StubBehavior, StubNode};
+use crate::runner::adapter::posix_quote;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// One recorded call of a STUB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StubCall {
+    pub args: Vec<String>,
+    pub stdin: Vec<u8>,
+}
+
+impl StubCall {
+    /// The arguments joined by spaces, as `called with` compares them.
+    pub fn args_line(&self) -> String {
+        self.args.join(" ")
+    }
+}
+
+/// Directory a session's stubs go in; put first on PATH.
+pub fn bin_dir(stub_dir: &Path) -> PathBuf {
+    stub_dir.join("bin")
+}
+
+fn calls_dir(stub_dir: &Path, command: &str) -> PathBuf {
+    stub_dir.join("calls").join(command)
+}
+
+/// Writes the executable for `stub` into `bin_dir(stub_dir)`. Redefining a
+/// stub replaces its behavior and keeps the calls recorded so far.
+pub fn write_stub(stub_dir: &Path, stub: &StubNode) -> std::io::Result<()> {
+    let calls = calls_dir(stub_dir, &stub.command);
+    std::fs::create_dir_all(&calls)?;
+    std::fs::create_dir_all(bin_dir(stub_dir))?;
+
+    let path = bin_dir(stub_dir).join(&stub.command);
+    std::fs::write(&path, stub_script(stub, &calls))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+}
+
+/// A `/bin/sh` script that records its call as `<calls>/<n>/args`, with each
+/// argument followed by a NUL byte, and `<calls>/<n>/stdin`, then behaves as
+/// the STUB says.
+fn stub_script(stub: &StubNode, calls: &Path) -> String {
+    let mut script = vec![
+        "#!/bin/sh".to_string(),
+        format!("# STUB {}, written by hone", stub.command),
+        format!("calls={}", posix_quote(&calls.to_string_lossy())),
+        // mkdir is atomic, so calls made at the same time get different numbers
+        "n=1".to_string(),
+        "while ! mkdir \"$calls/$n\" 2>/dev/null; do n=$((n + 1)); done".to_string(),
+        "for arg in \"$@\"; do printf '%s\\0' \"$arg\"; done > \"$calls/$n/args\"".to_string(),
+        "cat > \"$calls/$n/stdin\"".to_string(),
+        "exec < \"$calls/$n/stdin\"".to_string(),
+    ];
+
+    match &stub.behavior {
+        StubBehavior::Canned {
+            stdout,
+            stderr,
+            exit_code,
+        } => {
+            if !stdout.is_empty() {
+                script.push(format!("printf '%s' {}", posix_quote(stdout)));
+            }
+            if !stderr.is_empty() {
+                script.push(format!("printf '%s' {} >&2", posix_quote(stderr)));
+            }
+            script.push(format!("exit {}", exit_code));
+        }
+        StubBehavior::Script(body) => script.push(body.clone()),
+    }
+
+    script.join("\n") + "\n"
+}
+
+/// Calls recorded for `command` in call order, or `None` if it was never stubbed.
+pub fn read_calls(stub_dir: &Path, command: &str) -> std::io::Result<Option<Vec<StubCall>>> {
+    let calls = calls_dir(stub_dir, command);
+    if !calls.is_dir() {
+        return Ok(None);
+    }
+
+    let mut numbered: Vec<(u64, PathBuf)> = Vec::new();
+    for entry in std::fs::read_dir(&calls)? {
+        let path = entry?.path();
+        if let Some(n) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse().ok())
+        {
+            numbered.push((n, path));
+        }
+    }
+    numbered.sort();
+
+    let calls = numbered
+        .into_iter()
+        .map(|(_, call)| {
+            // Either file may be missing if the call is still starting
+            let args = std::fs::read(call.join("args")).unwrap_or_default();
+            // Every argument ends with a NUL, so the last piece is empty
+            let mut args: Vec<String> = args
+                .split(|&byte| byte == 0)
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect();
+            args.pop();
+            StubCall {
+                args,
+                stdin: std::fs::read(call.join("stdin")).unwrap_or_default(),
+            }
+        })
+        .collect();
+    Ok(Some(calls))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    fn stub(command: &str, behavior: StubBehavior) -> StubNode {
+        StubNode {
+            command: command.to_string(),
+            behavior,
+            raw: command.to_string(),
+            line: 1,
+        }
+    }
+
+    fn call(stub_dir: &Path, command: &str, args: &[&str], stdin: &str) -> std::process::Output {
+        let mut child = Command::new(bin_dir(stub_dir).join(command))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("stub should start");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    #[test]
+    fn test_canned_stub_records_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_calls(dir.path(), "kubectl").unwrap(), None);
+
+        write_stub(
+            dir.path(),
+            &stub(
+                "kubectl",
+                StubBehavior::Canned {
+                    stdout: "pod-a\n".to_string(),
+                    stderr: "it's a warning".to_string(),
+                    exit_code: 3,
+                },
+            ),
+        )
+        .unwrap();
+
+        let output = call(dir.path(), "kubectl", &["get", "pods"], "");
+        assert_eq!(output.stdout, b"pod-a\n");
+        assert_eq!(output.stderr, b"it's a warning");
+        assert_eq!(output.status.code(), Some(3));
+        call(
+            dir.path(),
+            "kubectl",
+            &["apply", "-f", "a b.yaml"],
+            "kind: Pod\n",
+        );
+
+        let calls = read_calls(dir.path(), "kubectl").unwrap().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].args_line(), "get pods");
+        assert_eq!(calls[0].stdin, b"");
+        assert_eq!(calls[1].args, vec!["apply", "-f", "a b.yaml"]);
+        assert_eq!(calls[1].stdin, b"kind: Pod\n");
+    }
+
+    #[test]
+    fn test_script_stub_sees_arguments_and_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        write_stub(
+            dir.path(),
+            &stub(
+                "git",
+                StubBehavior::Script("echo \"git $#: $1\"; cat; exit 1".to_string()),
+            ),
+        )
+        .unwrap();
+
+        let output = call(dir.path(), "git", &["status", "--short"], "piped");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "git 2: status\npiped"
+        );
+        assert_eq!(output.status.code(), Some(1));
+
+        let calls = read_calls(dir.path(), "git").unwrap().unwrap();
+        assert_eq!(calls[0].args_line(), "status --short");
+    }
+}
@@ -0,0 +1,215 @@
+use crate::parser::ast::{is_valid_tag, RESERVED_TAGS};
+
+/// A `--tag` expression such as `smoke and not (slow or network)`.
+/// `and` binds tighter than `or`; tags may be written with or without `@`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagExpr {
+    Tag(String),
+    Not(Box<TagExpr>),
+    And(Box<TagExpr>, Box<TagExpr>),
+    Or(Box<TagExpr>, Box<TagExpr>),
+}
+
+impl TagExpr {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!(
+                "unexpected '{}' in tag expression '{}'",
+                token, expression
+            )),
+        }
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        match self {
+            TagExpr::Tag(tag) => tags.contains(tag),
+            TagExpr::Not(expr) => !expr.matches(tags),
+            TagExpr::And(left, right) => left.matches(tags) && right.matches(tags),
+            TagExpr::Or(left, right) => left.matches(tags) || right.matches(tags),
+        }
+    }
+}
+
+/// Tests selected by `--tag` and `--exclude-tag`: a TEST runs when it matches
+/// any `--tag` expression (or there are none) and no `--exclude-tag` expression.
+#[derive(Debug, Clone, Default)]
+pub struct TagSelection {
+    pub include: Vec<TagExpr>,
+    pub exclude: Vec<TagExpr>,
+}
+
+impl TagSelection {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let parse_all = |expressions: &[String]| {
+            expressions
+                .iter()
+                .map(|expression| TagExpr::parse(expression))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include: parse_all(include)?,
+            exclude: parse_all(exclude)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|expr| expr.matches(tags)))
+            && !self.exclude.iter().any(|expr| expr.matches(tags))
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            tokens.push(c.to_string());
+            chars.next();
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(word);
+        }
+    }
+    if tokens.is_empty() {
+        return Err("empty tag expression".to_string());
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.position).map(String::as_str);
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<TagExpr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some("or") {
+            self.next();
+            expr = TagExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<TagExpr, String> {
+        let mut expr = self.not()?;
+        while self.peek() == Some("and") {
+            self.next();
+            expr = TagExpr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<TagExpr, String> {
+        if self.peek() == Some("not") {
+            self.next();
+            return Ok(TagExpr::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<TagExpr, String> {
+        match self.next() {
+            Some("(") => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err("missing ')' in tag expression".to_string()),
+                }
+            }
+            Some(token) => {
+                let tag = token.strip_prefix('@').unwrap_or(token);
+                if RESERVED_TAGS.contains(&tag) || !is_valid_tag(tag) {
+                    Err(format!("expected a tag, found '{}'", token))
+                } else {
+                    Ok(TagExpr::Tag(tag.to_string()))
+                }
+            }
+            None => Err("tag expression ends early".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let expr = TagExpr::parse("smoke or slow and not @network").unwrap();
+        assert_eq!(
+            expr,
+            TagExpr::Or(
+                Box::new(TagExpr::Tag("smoke".to_string())),
+                Box::new(TagExpr::And(
+                    Box::new(TagExpr::Tag("slow".to_string())),
+                    Box::new(TagExpr::Not(Box::new(TagExpr::Tag("network".to_string())))),
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn test_matches_with_parentheses() {
+        let expr = TagExpr::parse("(smoke or slow) and not network-free").unwrap();
+        assert!(expr.matches(&tags(&["smoke"])));
+        assert!(!expr.matches(&tags(&["slow", "network-free"])));
+        assert!(!expr.matches(&tags(&[])));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(TagExpr::parse("").is_err());
+        assert!(TagExpr::parse("smoke and").is_err());
+        assert!(TagExpr::parse("(smoke").is_err());
+        assert!(TagExpr::parse("smoke slow").is_err());
+        assert!(TagExpr::parse("a!b").is_err());
+    }
+
+    #[test]
+    fn test_selection_includes_and_excludes() {
+        let selection = TagSelection::new(&tags(&["smoke", "fast"]), &tags(&["slow"])).unwrap();
+        assert!(selection.matches(&tags(&["smoke"])));
+        assert!(selection.matches(&tags(&["fast"])));
+        assert!(!selection.matches(&tags(&["smoke", "slow"])));
+        assert!(!selection.matches(&tags(&[])));
+
+        let exclude_only = TagSelection::new(&[], &tags(&["slow"])).unwrap();
+        assert!(exclude_only.matches(&tags(&[])));
+        assert!(!exclude_only.matches(&tags(&["slow"])));
+    }
+}